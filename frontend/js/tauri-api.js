@@ -31,9 +31,10 @@ export class PluginMessageBus {
         const { invoke } = await import('@tauri-apps/api/core');
         const { listen } = await import('@tauri-apps/api/event');
         
-        // 1. 告诉后端订阅这个主题
+        // 1. 告诉后端订阅这个主题（返回主题的保留消息）
+        let retained = [];
         try {
-            await invoke('subscribe_data', { 
+            retained = await invoke('subscribe_data', { 
                 topic, 
                 pluginId: this.pluginId 
             });
@@ -53,6 +54,9 @@ export class PluginMessageBus {
         this.unlisteners.push(unlisten);
         this.messageHandlers.set(topic, callback);
         
+        // 3. 立即交付保留消息，无需等待下一次发布
+        (retained || []).forEach((message) => callback(message));
+        
         return unlisten;
     }
     
@@ -125,6 +129,33 @@ export class PluginMessageBus {
         return unlisten;
     }
     
+    /**
+     * 获取消息总线统计（按主题与插件的消息计数、队列深度、路由延迟）
+     * @returns {Promise<object>} 总线统计
+     */
+    async getBusStats() {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return invoke('get_bus_stats');
+    }
+
+    /**
+     * 获取插件声明的主题 schema
+     * @returns {Promise<Array>} 主题 schema 列表（topic、owner、description、schema）
+     */
+    async getTopicSchemas() {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return invoke('get_topic_schemas');
+    }
+
+    /**
+     * 获取由主题 schema 生成的 TypeScript 类型声明（TopicPayloads 将主题映射到负载类型）
+     * @returns {Promise<string>} .d.ts 源码
+     */
+    async getTopicBindings() {
+        const { invoke } = await import('@tauri-apps/api/core');
+        return invoke('get_topic_bindings');
+    }
+
     /**
      * 清理所有监听器
      */
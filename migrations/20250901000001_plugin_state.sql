@@ -0,0 +1,2 @@
+-- 插件生命周期状态
+ALTER TABLE plugin_metadata ADD COLUMN state TEXT NOT NULL DEFAULT 'loaded';
@@ -0,0 +1,5 @@
+-- 消息日志：主题与失败原因
+ALTER TABLE message_log ADD COLUMN topic TEXT;
+ALTER TABLE message_log ADD COLUMN error TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_message_log_topic ON message_log(topic);
@@ -0,0 +1,27 @@
+-- 持久投递队列：接收方确认前保存的消息（每个接收方一行）
+CREATE TABLE IF NOT EXISTS durable_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    message TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- 下次投递时间（毫秒时间戳）
+    next_attempt_at INTEGER NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(message_id, recipient)
+);
+
+-- 死信表：超过最大投递次数的消息
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    message TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_durable_messages_due ON durable_messages(recipient, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_dead_letters_recipient ON dead_letters(recipient);
@@ -0,0 +1,10 @@
+-- 保留消息：需要持久化的保留主题最近发布的消息
+CREATE TABLE IF NOT EXISTS retained_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    topic TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_retained_messages_topic ON retained_messages(topic, id);
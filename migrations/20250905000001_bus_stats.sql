@@ -0,0 +1,6 @@
+-- 消息总线统计：运行中的内核定期写入的最新快照（只有一行）
+CREATE TABLE IF NOT EXISTS bus_stats (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    stats TEXT NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
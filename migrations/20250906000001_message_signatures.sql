@@ -0,0 +1,3 @@
+-- 消息日志：内核签名与验证结果
+ALTER TABLE message_log ADD COLUMN signature TEXT;
+ALTER TABLE message_log ADD COLUMN verified_from BOOLEAN NOT NULL DEFAULT 0;
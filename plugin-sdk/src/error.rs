@@ -11,6 +11,10 @@ pub enum PluginError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// 负载编解码错误（MessagePack、CBOR 等）
+    #[error("Payload codec error: {0}")]
+    Codec(String),
+
     /// 主机函数调用错误
     #[error("Host function error: {0}")]
     HostFunction(String),
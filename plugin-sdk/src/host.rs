@@ -3,7 +3,7 @@
 //! 提供对主机函数的高级封装，简化插件开发
 
 use crate::error::{PluginError, PluginResult};
-use crate::message::PluginMessage;
+use crate::message::{ContentType, PluginMessage};
 use extism_pdk::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -43,6 +43,9 @@ extern "ExtismHost" {
     fn delete_data_host(plugin_id: &str, key: &str) -> String;
     fn list_keys_host(plugin_id: &str) -> String;
     fn send_message_host(from: &str, to: &str, payload: &str) -> String;
+    fn send_bytes_host(from: &str, to: &str, content_type: &str, payload: &[u8]) -> String;
+    fn request_host(to: &str, payload: &str, timeout_ms: &str) -> String;
+    fn reply_host(to: &str, correlation_id: &str, payload: &str) -> String;
     fn log_message_host(level: &str, message: &str) -> String;
     fn sign_message_host(plugin_id: &str, message: &str) -> String;
     fn verify_signature_host(plugin_id: &str, message: &str, signature: &str) -> String;
@@ -50,16 +53,46 @@ extern "ExtismHost" {
     fn subscribe_topic_host(plugin_id: &str, topic: &str) -> String;
     fn unsubscribe_topic_host(plugin_id: &str, topic: &str) -> String;
     fn publish_message_host(plugin_id: &str, topic: &str, payload: &str) -> String;
+    fn publish_bytes_host(
+        plugin_id: &str,
+        topic: &str,
+        content_type: &str,
+        payload: &[u8],
+    ) -> String;
+    fn get_retained_host(topic: &str) -> String;
     fn get_config_host(plugin_id: &str) -> String;
     fn set_config_host(plugin_id: &str, config: &str) -> String;
 }
 
+/// 主机拒绝未授权调用时返回的错误码
+pub const PERMISSION_DENIED_CODE: &str = "permission_denied";
+
+/// 请求等待回复超时时返回的错误码
+pub const TIMEOUT_CODE: &str = "timeout";
+
 /// 主机函数响应结构
 #[derive(Debug, Serialize, Deserialize)]
 struct HostResponse<T> {
     success: bool,
     data: Option<T>,
     error: Option<String>,
+    /// 结构化错误码（如 "permission_denied"）
+    #[serde(default)]
+    code: Option<String>,
+}
+
+impl<T> HostResponse<T> {
+    /// 将失败响应转换为插件错误
+    ///
+    /// 权限拒绝统一映射为 `PluginError::Permission`，超时映射为 `PluginError::Timeout`
+    fn into_error(self, kind: fn(String) -> PluginError) -> PluginError {
+        let message = self.error.unwrap_or("Unknown error".to_string());
+        match self.code.as_deref() {
+            Some(PERMISSION_DENIED_CODE) => PluginError::Permission(message),
+            Some(TIMEOUT_CODE) => PluginError::Timeout(message),
+            _ => kind(message),
+        }
+    }
 }
 
 /// 存储操作
@@ -77,9 +110,7 @@ pub mod storage {
         if response.success {
             Ok(())
         } else {
-            Err(PluginError::Storage(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Storage))
         }
     }
 
@@ -101,9 +132,7 @@ pub mod storage {
                 Ok(None)
             }
         } else {
-            Err(PluginError::Storage(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Storage))
         }
     }
 
@@ -117,9 +146,7 @@ pub mod storage {
         if response.success {
             Ok(response.data.unwrap_or(false))
         } else {
-            Err(PluginError::Storage(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Storage))
         }
     }
 
@@ -133,9 +160,7 @@ pub mod storage {
         if response.success {
             Ok(response.data.unwrap_or_default())
         } else {
-            Err(PluginError::Storage(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Storage))
         }
     }
 
@@ -186,9 +211,7 @@ pub mod messaging {
         if response.success {
             Ok(response.data.unwrap_or_default())
         } else {
-            Err(PluginError::MessageProcessing(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::MessageProcessing))
         }
     }
 
@@ -214,6 +237,81 @@ pub mod messaging {
         send(&message)
     }
 
+    /// 发送二进制负载，内容类型记录在消息类型中
+    pub fn send_bytes(
+        from: &str,
+        to: &str,
+        content_type: ContentType,
+        payload: &[u8],
+    ) -> PluginResult<String> {
+        let result = unsafe { send_bytes_host(from, to, content_type.mime(), payload)? };
+
+        let response: HostResponse<String> = serde_json::from_str(&result)
+            .map_err(|e| PluginError::HostFunction(format!("Failed to parse response: {}", e)))?;
+
+        if response.success {
+            Ok(response.data.unwrap_or_default())
+        } else {
+            Err(response.into_error(PluginError::MessageProcessing))
+        }
+    }
+
+    /// 以 MessagePack 或 CBOR 等格式编码后发送
+    pub fn send_encoded<T: Serialize>(
+        from: &str,
+        to: &str,
+        content_type: ContentType,
+        payload: &T,
+    ) -> PluginResult<String> {
+        send_bytes(from, to, content_type, &content_type.encode(payload)?)
+    }
+
+    /// 向插件发送请求并等待回复，返回回复的负载
+    ///
+    /// 等待期间插件调用处于阻塞状态，`timeout_ms` 应小于插件的调用超时（0 使用主机默认值）
+    pub fn request(to: &str, payload: &str, timeout_ms: u64) -> PluginResult<String> {
+        let result = unsafe { request_host(to, payload, &timeout_ms.to_string())? };
+
+        let response: HostResponse<String> = serde_json::from_str(&result)
+            .map_err(|e| PluginError::HostFunction(format!("Failed to parse response: {}", e)))?;
+
+        if response.success {
+            Ok(response.data.unwrap_or_default())
+        } else {
+            Err(response.into_error(PluginError::MessageProcessing))
+        }
+    }
+
+    /// 发送 JSON 请求并解析 JSON 回复
+    pub fn request_json<T: Serialize, R: for<'de> Deserialize<'de>>(
+        to: &str,
+        payload: &T,
+        timeout_ms: u64,
+    ) -> PluginResult<R> {
+        let reply = request(to, &serde_json::to_string(payload)?, timeout_ms)?;
+        Ok(serde_json::from_str(&reply)?)
+    }
+
+    /// 回复收到的请求，返回回复消息的 ID
+    pub fn reply(request: &PluginMessage, payload: &str) -> PluginResult<String> {
+        let correlation_id = request.correlation_id().unwrap_or(&request.id);
+        let result = unsafe { reply_host(&request.from, correlation_id, payload)? };
+
+        let response: HostResponse<String> = serde_json::from_str(&result)
+            .map_err(|e| PluginError::HostFunction(format!("Failed to parse response: {}", e)))?;
+
+        if response.success {
+            Ok(response.data.unwrap_or_default())
+        } else {
+            Err(response.into_error(PluginError::MessageProcessing))
+        }
+    }
+
+    /// 以 JSON 负载回复请求
+    pub fn reply_json<T: Serialize>(request: &PluginMessage, payload: &T) -> PluginResult<String> {
+        reply(request, &serde_json::to_string(payload)?)
+    }
+
     /// 订阅主题
     pub fn subscribe(plugin_id: &str, topic: &str) -> PluginResult<()> {
         let result = unsafe { subscribe_topic_host(plugin_id, topic)? };
@@ -224,9 +322,7 @@ pub mod messaging {
         if response.success {
             Ok(())
         } else {
-            Err(PluginError::MessageProcessing(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::MessageProcessing))
         }
     }
 
@@ -240,12 +336,31 @@ pub mod messaging {
         if response.success {
             Ok(())
         } else {
-            Err(PluginError::MessageProcessing(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::MessageProcessing))
         }
     }
 
+    /// 读取主题（可含通配符）的保留消息，不订阅主题
+    ///
+    /// 返回的消息按主题分组、按发布顺序排列，元数据中带有 `retained` 标记
+    pub fn get_retained(topic: &str) -> PluginResult<Vec<PluginMessage>> {
+        let result = unsafe { get_retained_host(topic)? };
+
+        let response: HostResponse<Vec<PluginMessage>> = serde_json::from_str(&result)
+            .map_err(|e| PluginError::HostFunction(format!("Failed to parse response: {}", e)))?;
+
+        if response.success {
+            Ok(response.data.unwrap_or_default())
+        } else {
+            Err(response.into_error(PluginError::MessageProcessing))
+        }
+    }
+
+    /// 读取主题最新的保留消息
+    pub fn get_last_value(topic: &str) -> PluginResult<Option<PluginMessage>> {
+        Ok(get_retained(topic)?.pop())
+    }
+
     /// 发布消息到主题
     pub fn publish<T: Serialize>(
         plugin_id: &str,
@@ -261,11 +376,58 @@ pub mod messaging {
         if response.success {
             Ok(response.data.unwrap_or_default())
         } else {
-            Err(PluginError::MessageProcessing(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::MessageProcessing))
+        }
+    }
+
+    /// 发布完整的消息到 `message.topic`，保留其优先级、过期时间与元数据
+    pub fn publish_message(message: &PluginMessage) -> PluginResult<String> {
+        let payload = serde_json::to_string(message)?;
+        let result = unsafe { publish_message_host(&message.from, &message.topic, &payload)? };
+
+        let response: HostResponse<String> = serde_json::from_str(&result)
+            .map_err(|e| PluginError::HostFunction(format!("Failed to parse response: {}", e)))?;
+
+        if response.success {
+            Ok(response.data.unwrap_or_default())
+        } else {
+            Err(response.into_error(PluginError::MessageProcessing))
         }
     }
+
+    /// 发布二进制负载到主题
+    pub fn publish_bytes(
+        plugin_id: &str,
+        topic: &str,
+        content_type: ContentType,
+        payload: &[u8],
+    ) -> PluginResult<String> {
+        let result = unsafe { publish_bytes_host(plugin_id, topic, content_type.mime(), payload)? };
+
+        let response: HostResponse<String> = serde_json::from_str(&result)
+            .map_err(|e| PluginError::HostFunction(format!("Failed to parse response: {}", e)))?;
+
+        if response.success {
+            Ok(response.data.unwrap_or_default())
+        } else {
+            Err(response.into_error(PluginError::MessageProcessing))
+        }
+    }
+
+    /// 以 MessagePack 或 CBOR 等格式编码后发布到主题
+    pub fn publish_encoded<T: Serialize>(
+        plugin_id: &str,
+        topic: &str,
+        content_type: ContentType,
+        payload: &T,
+    ) -> PluginResult<String> {
+        publish_bytes(
+            plugin_id,
+            topic,
+            content_type,
+            &content_type.encode(payload)?,
+        )
+    }
 }
 
 /// 日志操作
@@ -282,9 +444,7 @@ pub mod logging {
         if response.success {
             Ok(())
         } else {
-            Err(PluginError::Generic(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Generic))
         }
     }
 
@@ -333,9 +493,7 @@ pub mod config {
                 Ok(None)
             }
         } else {
-            Err(PluginError::Configuration(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Configuration))
         }
     }
 
@@ -350,9 +508,7 @@ pub mod config {
         if response.success {
             Ok(())
         } else {
-            Err(PluginError::Configuration(
-                response.error.unwrap_or("Unknown error".to_string()),
-            ))
+            Err(response.into_error(PluginError::Configuration))
         }
     }
 }
@@ -397,6 +553,36 @@ macro_rules! log_trace {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_permission_denied_response() {
+        let denied: HostResponse<()> = serde_json::from_str(
+            r#"{"success":false,"error":"denied","code":"permission_denied"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            denied.into_error(PluginError::Storage),
+            PluginError::Permission(_)
+        ));
+
+        let failed: HostResponse<()> =
+            serde_json::from_str(r#"{"success":false,"error":"disk full"}"#).unwrap();
+        assert!(matches!(
+            failed.into_error(PluginError::Storage),
+            PluginError::Storage(_)
+        ));
+    }
+
+    #[test]
+    fn test_timeout_response() {
+        let timed_out: HostResponse<String> =
+            serde_json::from_str(r#"{"success":false,"error":"timed out","code":"timeout"}"#)
+                .unwrap();
+        assert!(matches!(
+            timed_out.into_error(PluginError::MessageProcessing),
+            PluginError::Timeout(_)
+        ));
+    }
+
     #[test]
     fn test_log_level_display() {
         assert_eq!(LogLevel::Error.to_string(), "error");
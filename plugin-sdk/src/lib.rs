@@ -22,7 +22,10 @@ pub mod testing;
 // 便捷的重新导出
 pub use error::{ErrorContext, PluginError, PluginErrorExt, PluginResult};
 pub use host::LogLevel;
-pub use message::{MessageBuilder, MessageFilter, MessageHandler, MessagePriority, PluginMessage};
+pub use message::{
+    ContentType, InterceptRequest, InterceptStage, InterceptVerdict, MessageBuilder, MessageFilter,
+    MessageHandler, MessagePriority, PluginMessage,
+};
 pub use plugin::{BasePlugin, Plugin, PluginConfig, PluginEvent, PluginMetadata, PluginStatus};
 
 /// 插件 SDK 版本
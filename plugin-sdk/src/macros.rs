@@ -124,6 +124,65 @@ macro_rules! plugin_main {
             }
         }
 
+        /// 暂停插件
+        #[plugin_fn]
+        pub fn pause() -> FnResult<String> {
+            let mut guard = PLUGIN_INSTANCE.lock().unwrap();
+            if let Some(ref mut plugin) = guard.as_mut() {
+                match plugin.pause() {
+                    Ok(_) => Ok(serde_json::json!({"success": true}).to_string()),
+                    Err(e) => Err(extism_pdk::Error::msg(format!("Failed to pause plugin: {}", e))),
+                }
+            } else {
+                Err(extism_pdk::Error::msg("Plugin not initialized"))
+            }
+        }
+
+        /// 恢复插件
+        #[plugin_fn]
+        pub fn resume() -> FnResult<String> {
+            let mut guard = PLUGIN_INSTANCE.lock().unwrap();
+            if let Some(ref mut plugin) = guard.as_mut() {
+                match plugin.resume() {
+                    Ok(_) => Ok(serde_json::json!({"success": true}).to_string()),
+                    Err(e) => Err(extism_pdk::Error::msg(format!("Failed to resume plugin: {}", e))),
+                }
+            } else {
+                Err(extism_pdk::Error::msg("Plugin not initialized"))
+            }
+        }
+
+        /// 导出热重载时交接的状态
+        #[plugin_fn]
+        pub fn export_state() -> FnResult<String> {
+            let guard = PLUGIN_INSTANCE.lock().unwrap();
+            let state = match guard.as_ref() {
+                Some(plugin) => plugin
+                    .export_state()
+                    .map_err(|e| extism_pdk::Error::msg(format!("Failed to export state: {}", e)))?,
+                None => None,
+            };
+            Ok(serde_json::to_string(&state)
+                .map_err(|e| extism_pdk::Error::msg(format!("Failed to serialize state: {}", e)))?)
+        }
+
+        /// 导入旧版本导出的状态
+        #[plugin_fn]
+        pub fn import_state(state_json: String) -> FnResult<String> {
+            let state: serde_json::Value = serde_json::from_str(&state_json)
+                .map_err(|e| extism_pdk::Error::msg(format!("Failed to parse state: {}", e)))?;
+
+            let mut guard = PLUGIN_INSTANCE.lock().unwrap();
+            if let Some(ref mut plugin) = guard.as_mut() {
+                match plugin.import_state(state) {
+                    Ok(_) => Ok(serde_json::json!({"success": true}).to_string()),
+                    Err(e) => Err(extism_pdk::Error::msg(format!("Failed to import state: {}", e))),
+                }
+            } else {
+                Err(extism_pdk::Error::msg("Plugin not initialized"))
+            }
+        }
+
         /// 关闭插件
         #[plugin_fn]
         pub fn shutdown() -> FnResult<String> {
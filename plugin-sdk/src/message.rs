@@ -2,6 +2,8 @@
 //!
 //! 提供插件间通信的消息类型和处理机制
 
+use crate::error::{PluginError, PluginResult};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -28,6 +30,12 @@ pub struct PluginMessage {
     pub expires_at: Option<u64>,
     /// 消息优先级
     pub priority: MessagePriority,
+    /// 内核以发送方派生密钥生成的签名（十六进制）
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// 内核已验证签名，`from` 可信（由内核设置，插件提交的值会被忽略）
+    #[serde(default)]
+    pub verified_from: bool,
 }
 
 /// 消息优先级
@@ -49,6 +57,80 @@ impl Default for MessagePriority {
     }
 }
 
+/// 负载内容类型（以 MIME 类型保存在 `message_type` 中）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    /// JSON
+    Json,
+    /// MessagePack
+    MessagePack,
+    /// CBOR
+    Cbor,
+    /// 原始字节
+    Raw,
+}
+
+impl ContentType {
+    /// MIME 类型
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ContentType::Json => "application/json",
+            ContentType::MessagePack => "application/msgpack",
+            ContentType::Cbor => "application/cbor",
+            ContentType::Raw => "application/octet-stream",
+        }
+    }
+
+    /// 从 MIME 类型解析（忽略 `;charset=` 等参数）
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let essence = mime.split(';').next().unwrap_or_default().trim();
+        match essence {
+            "application/json" => Some(ContentType::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(ContentType::MessagePack)
+            }
+            "application/cbor" => Some(ContentType::Cbor),
+            "application/octet-stream" => Some(ContentType::Raw),
+            _ => None,
+        }
+    }
+
+    /// 将值编码为该类型的负载（原始字节无需编码，返回错误）
+    pub fn encode<T: Serialize>(&self, value: &T) -> PluginResult<Vec<u8>> {
+        match self {
+            ContentType::Json => Ok(serde_json::to_vec(value)?),
+            ContentType::MessagePack => {
+                rmp_serde::to_vec_named(value).map_err(|e| PluginError::Codec(e.to_string()))
+            }
+            ContentType::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf)
+                    .map_err(|e| PluginError::Codec(e.to_string()))?;
+                Ok(buf)
+            }
+            ContentType::Raw => Err(PluginError::UnsupportedOperation(
+                "raw payloads are sent as bytes and cannot be encoded".to_string(),
+            )),
+        }
+    }
+
+    /// 按该类型解码负载
+    pub fn decode<T: DeserializeOwned>(&self, payload: &[u8]) -> PluginResult<T> {
+        match self {
+            ContentType::Json => Ok(serde_json::from_slice(payload)?),
+            ContentType::MessagePack => {
+                rmp_serde::from_slice(payload).map_err(|e| PluginError::Codec(e.to_string()))
+            }
+            ContentType::Cbor => {
+                ciborium::from_reader(payload).map_err(|e| PluginError::Codec(e.to_string()))
+            }
+            ContentType::Raw => Err(PluginError::UnsupportedOperation(
+                "raw payloads cannot be decoded, use payload_bytes".to_string(),
+            )),
+        }
+    }
+}
+
 /// 消息构建器
 #[derive(Debug)]
 pub struct MessageBuilder {
@@ -96,6 +178,17 @@ impl MessageBuilder {
         Ok(self)
     }
 
+    /// 以指定内容类型编码负载（JSON、MessagePack 或 CBOR）
+    pub fn payload_encoded<T: Serialize>(
+        mut self,
+        content_type: ContentType,
+        payload: &T,
+    ) -> PluginResult<Self> {
+        self.payload = Some(content_type.encode(payload)?);
+        self.message_type = Some(content_type.mime().to_string());
+        Ok(self)
+    }
+
     /// 设置负载（字符串）
     pub fn payload_string(mut self, payload: &str) -> Self {
         self.payload = Some(payload.as_bytes().to_vec());
@@ -161,6 +254,8 @@ impl MessageBuilder {
             timestamp: crate::utils::time::now_millis(),
             expires_at: self.expires_at,
             priority: self.priority,
+            signature: None,
+            verified_from: false,
         })
     }
 }
@@ -195,11 +290,34 @@ impl PluginMessage {
         &self.payload
     }
 
+    /// 负载的内容类型（消息类型不是已知的 MIME 类型时为 None）
+    pub fn content_type(&self) -> Option<ContentType> {
+        ContentType::from_mime(&self.message_type)
+    }
+
+    /// 按消息的内容类型解码负载
+    pub fn payload_decoded<T: DeserializeOwned>(&self) -> PluginResult<T> {
+        let content_type = self.content_type().ok_or_else(|| {
+            PluginError::Codec(format!("unknown content type: {}", self.message_type))
+        })?;
+        content_type.decode(&self.payload)
+    }
+
     /// 获取元数据
     pub fn get_metadata(&self, key: &str) -> Option<&String> {
         self.metadata.get(key)
     }
 
+    /// 获取请求的关联 ID（内核投递请求时写入元数据）
+    pub fn correlation_id(&self) -> Option<&String> {
+        self.get_metadata("correlation_id")
+    }
+
+    /// 检查是否为等待回复的请求（使用 `host::messaging::reply` 回复）
+    pub fn is_request(&self) -> bool {
+        self.message_type == "request" && self.correlation_id().is_some()
+    }
+
     /// 创建回复消息
     pub fn reply(&self, from: &str) -> MessageBuilder {
         MessageBuilder::new(from)
@@ -237,6 +355,42 @@ pub trait MessageHandler {
     }
 }
 
+/// 拦截阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterceptStage {
+    /// 路由前，返回 `InterceptVerdict`
+    BeforeRoute,
+    /// 路由完成后（返回值被忽略）
+    AfterDelivery,
+}
+
+/// 内核传给 `intercept_message` 导出函数的输入
+///
+/// 插件需要 `bus.intercept` 权限才会被注册为拦截器
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterceptRequest {
+    /// 拦截阶段
+    pub stage: InterceptStage,
+    /// 经过总线的消息
+    pub message: PluginMessage,
+    /// 投递结果（仅 after_delivery）：delivered、queued、not_found、expired、rejected、failed
+    #[serde(default)]
+    pub result: Option<String>,
+}
+
+/// `intercept_message` 在 before_route 阶段的返回值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum InterceptVerdict {
+    /// 原样放行
+    Pass,
+    /// 用修改后的消息继续路由（只有负载、类型、优先级、过期时间与元数据生效）
+    Modify { message: Box<PluginMessage> },
+    /// 拒绝消息
+    Reject { reason: String },
+}
+
 /// 消息过滤器
 #[derive(Debug, Clone)]
 pub struct MessageFilter {
@@ -387,4 +541,33 @@ mod tests {
         message.expires_at = Some(crate::utils::time::now_millis() - 1000);
         assert!(message.is_expired());
     }
+
+    #[test]
+    fn test_payload_codecs() {
+        let value = serde_json::json!({ "bpm": 72, "samples": [1, 2, 3] });
+
+        for content_type in [
+            ContentType::Json,
+            ContentType::MessagePack,
+            ContentType::Cbor,
+        ] {
+            let message = PluginMessage::builder("sensor")
+                .topic("health.heart_rate")
+                .payload_encoded(content_type, &value)
+                .unwrap()
+                .build()
+                .unwrap();
+
+            assert_eq!(message.message_type, content_type.mime());
+            assert_eq!(message.content_type(), Some(content_type));
+            let decoded: serde_json::Value = message.payload_decoded().unwrap();
+            assert_eq!(decoded, value);
+        }
+
+        assert_eq!(
+            ContentType::from_mime("application/x-msgpack"),
+            Some(ContentType::MessagePack)
+        );
+        assert!(ContentType::Raw.encode(&value).is_err());
+    }
 }
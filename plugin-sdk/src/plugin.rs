@@ -152,6 +152,16 @@ pub trait Plugin: Send + Sync {
         self.handle_event(PluginEvent::Shutdown)
     }
 
+    /// 导出热重载时交给新版本的状态（None 表示没有需要保留的状态）
+    fn export_state(&self) -> PluginResult<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// 导入旧版本导出的状态（热重载时在 initialize 之后调用）
+    fn import_state(&mut self, _state: serde_json::Value) -> PluginResult<()> {
+        Ok(())
+    }
+
     /// 获取插件健康状态
     fn health_check(&self) -> PluginResult<HashMap<String, serde_json::Value>> {
         Ok(HashMap::new())
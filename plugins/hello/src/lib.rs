@@ -63,7 +63,9 @@ pub fn send_greeting(input: String) -> FnResult<String> {
     }
 
     // 调用主机函数发送消息
-    let msg_id = unsafe { send_message_host("hello", &request.to, &request.content)? };
+    let response = unsafe { send_message_host("hello", &request.to, &request.content)? };
+    let response: serde_json::Value = serde_json::from_str(&response)?;
+    let msg_id = response["data"].as_str().unwrap_or_default();
 
     Ok(format!("已发送消息到 {}，消息ID: {}", request.to, msg_id))
 }
@@ -1,5 +1,9 @@
 use anyhow::{anyhow, Result};
+use minimal_kernel::kernel::bus_metrics::BusStats;
+use minimal_kernel::kernel::hot_reload::is_plugin_file;
 use minimal_kernel::kernel::message::Message;
+use minimal_kernel::kernel::schema_registry::{typescript_bindings, TopicSchema};
+use minimal_kernel::kernel::topic_index::{topic_matches, MULTI_LEVEL_WILDCARD};
 use minimal_kernel::kernel::Kernel;
 use minimal_kernel::storage::layout::{CreateWidgetRequest, LayoutManager, LayoutWidget};
 use serde::{Deserialize, Serialize};
@@ -29,6 +33,19 @@ pub struct UIMessage {
     pub timestamp: u64,
 }
 
+impl From<&Message> for UIMessage {
+    fn from(message: &Message) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            from: message.from.clone(),
+            to: message.to.clone(),
+            topic: message.topic.clone(),
+            payload: serde_json::from_slice(&message.payload).unwrap_or(serde_json::Value::Null),
+            timestamp: message.timestamp.timestamp_millis() as u64,
+        }
+    }
+}
+
 pub struct KernelBridge {
     kernel: Arc<Mutex<Option<Kernel>>>,
     kernel_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
@@ -95,20 +112,52 @@ impl KernelBridge {
 
             tracing::info!("Loading plugins from: {}", plugin_dir.display());
 
-            // 扫描并加载插件
-            let loaded_plugins = kernel.scan_and_load_plugins(&plugin_dir)?;
-
-            tracing::info!(
-                "Loaded {} plugins: {:?}",
-                loaded_plugins.len(),
-                loaded_plugins
-            );
-            Ok(loaded_plugins)
+            // 按依赖顺序加载插件
+            let report = kernel.load_plugins_with_dependencies(&plugin_dir, &[])?;
+            report.log();
+            Ok(report.loaded)
         } else {
             Err(anyhow!("Kernel not initialized"))
         }
     }
 
+    /// 热重载变更的插件文件：已加载的插件单独重载（失败时保留旧实例），新文件按依赖顺序加载
+    ///
+    /// 只处理插件目录中的插件文件，其它构建输出与临时目录中的变更被忽略
+    pub async fn reload_changed(
+        &self,
+        app_handle: &tauri::AppHandle,
+        paths: &[PathBuf],
+    ) -> Result<Vec<String>> {
+        let plugin_dir = self.get_plugin_directory(app_handle)?;
+        let mut kernel_guard = self.kernel.lock().await;
+        let kernel = kernel_guard
+            .as_mut()
+            .ok_or_else(|| anyhow!("Kernel not initialized"))?;
+
+        let mut reloaded = Vec::new();
+        let mut has_new = false;
+        for path in paths {
+            if !path.exists() || !is_plugin_file(&plugin_dir, path) {
+                continue;
+            }
+            match kernel.plugin_for_path(path).map(str::to_string) {
+                Some(name) => match kernel.reload_plugin(&name) {
+                    Ok(()) => reloaded.push(name),
+                    Err(e) => tracing::error!("Failed to reload plugin {}: {}", name, e),
+                },
+                None => has_new = true,
+            }
+        }
+
+        if has_new {
+            let report = kernel.load_plugins_with_dependencies(&plugin_dir, &[])?;
+            report.log();
+            reloaded.extend(report.loaded);
+        }
+        Ok(reloaded)
+    }
+
     /// 获取插件目录路径
     pub fn get_plugin_directory(&self, app_handle: &tauri::AppHandle) -> Result<PathBuf> {
         // 开发模式：使用项目根目录的 plugins 文件夹
@@ -220,12 +269,20 @@ impl KernelBridge {
         }
     }
 
-    pub async fn subscribe(&self, topic: &str, plugin_id: &str) -> Result<()> {
+    /// 订阅主题，返回主题当前的保留消息（订阅方无需等待下一次发布即可显示）
+    ///
+    /// 订阅只保存在内存中，内核重启后由 UI 重新订阅
+    pub async fn subscribe(&self, topic: &str, plugin_id: &str) -> Result<Vec<UIMessage>> {
         let kernel_guard = self.kernel.lock().await;
         if let Some(kernel) = kernel_guard.as_ref() {
             // 通过消息总线订阅主题
             let bus_handle = kernel.get_message_bus_handle();
             bus_handle.subscribe_topic(plugin_id, topic);
+            let retained = bus_handle
+                .retained_messages(topic)
+                .iter()
+                .map(UIMessage::from)
+                .collect();
 
             // 记录 UI 插件的订阅
             if plugin_id.starts_with("ui-") {
@@ -237,7 +294,7 @@ impl KernelBridge {
             }
 
             tracing::debug!("Plugin {} subscribed to topic {}", plugin_id, topic);
-            Ok(())
+            Ok(retained)
         } else {
             Err(anyhow!("Kernel not initialized"))
         }
@@ -251,8 +308,8 @@ impl KernelBridge {
             let bus_handle = kernel.get_message_bus_handle();
             let mut receiver = bus_handle.register_plugin("tauri-bridge".to_string());
 
-            // 订阅所有消息（作为中转站）
-            bus_handle.subscribe_topic("tauri-bridge", "*");
+            // 订阅所有主题（作为中转站）
+            bus_handle.subscribe_topic("tauri-bridge", MULTI_LEVEL_WILDCARD);
 
             let ui_subscriptions = self.ui_subscriptions.clone();
             let listener_handle = tokio::spawn(async move {
@@ -267,8 +324,10 @@ impl KernelBridge {
                         if message.to.starts_with("ui-") {
                             true
                         } else if let Some(topic) = &message.topic {
-                            // 检查主题消息
-                            subs.values().any(|topics| topics.contains(topic))
+                            // 检查主题消息（UI 订阅也可以是通配符模式）
+                            subs.values()
+                                .flatten()
+                                .any(|pattern| topic_matches(pattern, topic))
                         } else {
                             false
                         }
@@ -276,15 +335,7 @@ impl KernelBridge {
 
                     if should_forward {
                         // 转换为 UI 消息格式
-                        let ui_message = UIMessage {
-                            id: uuid::Uuid::new_v4().to_string(),
-                            from: message.from.clone(),
-                            to: message.to.clone(),
-                            topic: message.topic.clone(),
-                            payload: serde_json::from_slice(&message.payload)
-                                .unwrap_or(serde_json::Value::Null),
-                            timestamp: message.timestamp.timestamp_millis() as u64,
-                        };
+                        let ui_message = UIMessage::from(&message);
 
                         // 通过 Tauri 事件系统发送到前端
                         if let Err(e) = app_handle.emit("kernel-message", &ui_message) {
@@ -331,6 +382,31 @@ impl KernelBridge {
         }
     }
 
+    /// 获取消息总线统计
+    pub async fn bus_stats(&self) -> Result<BusStats> {
+        let kernel_guard = self.kernel.lock().await;
+        if let Some(kernel) = kernel_guard.as_ref() {
+            Ok(kernel.bus_stats())
+        } else {
+            Err(anyhow!("Kernel not initialized"))
+        }
+    }
+
+    /// 获取已加载插件声明的主题 schema
+    pub async fn topic_schemas(&self) -> Result<Vec<TopicSchema>> {
+        let kernel_guard = self.kernel.lock().await;
+        if let Some(kernel) = kernel_guard.as_ref() {
+            Ok(kernel.topic_schemas())
+        } else {
+            Err(anyhow!("Kernel not initialized"))
+        }
+    }
+
+    /// 由主题 schema 生成 UI 插件使用的 TypeScript 类型声明
+    pub async fn topic_bindings(&self) -> Result<String> {
+        Ok(typescript_bindings(&self.topic_schemas().await?))
+    }
+
     /// 获取所有 UI 插件的订阅信息
     pub async fn get_ui_subscriptions(&self) -> Result<Vec<UISubscription>> {
         let subscriptions = self.ui_subscriptions.read().await;
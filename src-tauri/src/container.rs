@@ -208,19 +208,23 @@ impl ContainerManager {
                 
                 // 消息订阅（使用后端的订阅管理）
                 subscribe: async function(topic, callback) {{
-                    // 告诉后端订阅
-                    await window.__TAURI__.core.invoke('subscribe_data', {{
+                    // 告诉后端订阅，返回主题的保留消息
+                    const retained = await window.__TAURI__.core.invoke('subscribe_data', {{
                         topic: topic,
                         pluginId: '{}'
                     }});
                     
                     // 监听内核消息
-                    return window.__TAURI__.event.listen('kernel-message', (event) => {{
+                    const unlisten = await window.__TAURI__.event.listen('kernel-message', (event) => {{
                         const message = event.payload;
                         if (message.topic === topic || message.to === '{}') {{
                             callback(message);
                         }}
                     }});
+                    
+                    // 立即交付保留消息
+                    (retained || []).forEach((message) => callback(message));
+                    return unlisten;
                 }},
                 
                 // 发送消息到其他插件
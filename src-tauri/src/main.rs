@@ -5,6 +5,7 @@ mod app_state;
 mod bridge;
 mod container;
 mod plugin_creator;
+mod plugin_watcher;
 mod system_monitor;
 
 use app_state::{is_app_ready, AppState};
@@ -195,6 +196,34 @@ async fn get_plugins(kernel_bridge: State<'_, Arc<KernelBridge>>) -> Result<Vec<
         .map_err(|e| e.to_string())
 }
 
+// Tauri 命令：获取消息总线统计
+#[tauri::command]
+async fn get_bus_stats(
+    kernel_bridge: State<'_, Arc<KernelBridge>>,
+) -> Result<minimal_kernel::kernel::bus_metrics::BusStats, String> {
+    kernel_bridge.bus_stats().await.map_err(|e| e.to_string())
+}
+
+// Tauri 命令：获取主题 schema 注册表
+#[tauri::command]
+async fn get_topic_schemas(
+    kernel_bridge: State<'_, Arc<KernelBridge>>,
+) -> Result<Vec<minimal_kernel::kernel::schema_registry::TopicSchema>, String> {
+    kernel_bridge
+        .topic_schemas()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Tauri 命令：获取由主题 schema 生成的 TypeScript 类型声明
+#[tauri::command]
+async fn get_topic_bindings(kernel_bridge: State<'_, Arc<KernelBridge>>) -> Result<String, String> {
+    kernel_bridge
+        .topic_bindings()
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // Tauri 命令：发送消息到插件
 #[tauri::command]
 async fn send_to_plugin(
@@ -208,13 +237,13 @@ async fn send_to_plugin(
         .map_err(|e| e.to_string())
 }
 
-// Tauri 命令：订阅数据
+// Tauri 命令：订阅数据（返回主题当前的保留消息）
 #[tauri::command]
 async fn subscribe_data(
     topic: String,
     plugin_id: String,
     kernel_bridge: State<'_, Arc<KernelBridge>>,
-) -> Result<(), String> {
+) -> Result<Vec<bridge::UIMessage>, String> {
     kernel_bridge
         .subscribe(&topic, &plugin_id)
         .await
@@ -367,6 +396,7 @@ fn main() {
             // 初始化内核
             let kernel_bridge_clone = kernel_bridge_for_setup.clone();
             let app_handle_for_listener = app_handle.clone();
+            let app_handle_for_watcher = app_handle.clone();
             let app_state_for_init = app_state.clone();
             tauri::async_runtime::spawn(async move {
                 match kernel_bridge_clone.initialize().await {
@@ -396,8 +426,8 @@ fn main() {
                             }
                         }
 
-                        // TODO: 插件监视功能待实现
-                        // plugin_watcher::init(app_handle.clone(), kernel_bridge_clone.clone());
+                        // 启动插件文件监视（防抖后热重载变更的插件）
+                        plugin_watcher::init(app_handle_for_watcher, kernel_bridge_clone.clone());
 
                         // 标记应用已就绪
                         app_state_for_init.set_ready();
@@ -426,6 +456,9 @@ fn main() {
             send_to_plugin,
             subscribe_data,
             unsubscribe_data,
+            get_bus_stats,
+            get_topic_schemas,
+            get_topic_bindings,
             reload_plugins,
             get_ui_subscriptions,
             unregister_ui_plugin,
@@ -1,11 +1,18 @@
 use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::AppHandle;
+use tokio::sync::mpsc;
 use tracing;
 
 use crate::bridge::KernelBridge;
+use minimal_kernel::kernel::hot_reload::next_batch;
 use once_cell::sync::OnceCell;
 
+/// 文件事件防抖间隔：最后一次事件后静默这么久才触发重载
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
 pub struct PluginWatcher {
     _watcher: RecommendedWatcher,
 }
@@ -14,36 +21,48 @@ static WATCHER: OnceCell<PluginWatcher> = OnceCell::new();
 
 impl PluginWatcher {
     pub fn new(app_handle: AppHandle, kernel_bridge: Arc<KernelBridge>) -> notify::Result<Self> {
-        let plugin_dir = kernel_bridge.get_plugin_directory(&app_handle)?;
-
-        let mut watcher = notify::recommended_watcher(move |res| {
-            if let Ok(event) = res {
-                if matches!(
-                    event.kind,
-                    EventKind::Modify(_)
-                        | EventKind::Create(_)
-                        | EventKind::Remove(_)
-                        | EventKind::Any
-                ) {
-                    let bridge = kernel_bridge.clone();
-                    let handle = app_handle.clone();
-                    tauri::async_runtime::spawn(async move {
-                        if let Err(e) = bridge.load_plugins(handle).await {
-                            tracing::error!("Plugin hot reload failed: {}", e);
-                        } else {
-                            tracing::info!("Plugins hot reloaded");
-                        }
-                    });
+        let plugin_dir = kernel_bridge
+            .get_plugin_directory(&app_handle)
+            .map_err(|e| notify::Error::generic(&e.to_string()))?;
+
+        let (tx, rx) = mpsc::unbounded_channel::<Vec<PathBuf>>();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                if let Ok(event) = res {
+                    if matches!(
+                        event.kind,
+                        EventKind::Modify(_) | EventKind::Create(_) | EventKind::Any
+                    ) {
+                        let _ = tx.send(event.paths);
+                    }
                 }
-            }
-        })?;
+            })?;
         watcher.configure(Config::PreciseEvents(true))?;
         watcher.watch(&plugin_dir, RecursiveMode::Recursive)?;
 
+        tauri::async_runtime::spawn(debounce_reloads(rx, app_handle, kernel_bridge));
+
         Ok(Self { _watcher: watcher })
     }
 }
 
+/// 合并一段时间内的文件事件，静默后一次性重载变更的插件
+async fn debounce_reloads(
+    mut rx: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+    app_handle: AppHandle,
+    kernel_bridge: Arc<KernelBridge>,
+) {
+    while let Some(changed) = next_batch(&mut rx, DEBOUNCE).await {
+        match kernel_bridge.reload_changed(&app_handle, &changed).await {
+            Ok(plugins) if !plugins.is_empty() => {
+                tracing::info!("Plugins hot reloaded: {:?}", plugins);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Plugin hot reload failed: {}", e),
+        }
+    }
+}
+
 pub fn init(app_handle: AppHandle, kernel_bridge: Arc<KernelBridge>) {
     if WATCHER.get().is_some() {
         return;
@@ -50,10 +50,125 @@ pub enum Commands {
         /// 插件名称
         name: String,
     },
+    /// 查询消息日志（需在配置中启用 message_bus.journal）
+    Journal {
+        /// 发送方或接收方插件
+        #[arg(long)]
+        plugin: Option<String>,
+        /// 主题
+        #[arg(long)]
+        topic: Option<String>,
+        /// 投递状态: sent, delivered, queued, expired, dropped_no_subscriber, rejected, failed
+        #[arg(long)]
+        status: Option<String>,
+        /// 起始时间（RFC 3339，如 2025-09-01T08:00:00Z）
+        #[arg(long)]
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        /// 截止时间（RFC 3339）
+        #[arg(long)]
+        until: Option<chrono::DateTime<chrono::Utc>>,
+        /// 最多显示的条数
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// 查看持久投递的死信
+    DeadLetters {
+        /// 接收方插件
+        #[arg(long)]
+        plugin: Option<String>,
+        /// 最多显示的条数
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// 重放死信（下次运行内核时重新投递）
+    ReplayDeadLetter {
+        /// 死信 ID
+        id: i64,
+    },
+    /// 将插件目录打包为签名的 .mkpkg 插件包（以身份主密钥签名）
+    Pack {
+        /// 插件目录（包含 manifest.toml）
+        dir: PathBuf,
+        /// 输出路径（默认为当前目录下的 <name>-<version>.mkpkg）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 校验插件包并安装到插件目录
+    Install {
+        /// 插件包路径
+        package: PathBuf,
+    },
+    /// 从插件目录卸载插件
+    Uninstall {
+        /// 插件名称
+        name: String,
+    },
+    /// 校验插件包的哈希与签名
+    Verify {
+        /// 插件包路径
+        package: PathBuf,
+    },
+    /// 插件仓库
+    Registry {
+        /// 仓库地址（目录或 file:// URL，默认使用 plugins.registry 配置）
+        #[arg(long)]
+        registry: Option<String>,
+        #[command(subcommand)]
+        command: RegistryCommands,
+    },
+    /// 消息总线
+    Bus {
+        #[command(subcommand)]
+        command: BusCommands,
+    },
     /// 重置配置
     ResetConfig,
 }
 
+/// 插件仓库子命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum RegistryCommands {
+    /// 按名称、描述或标签搜索插件
+    Search {
+        /// 关键字（为空时列出全部）
+        query: Option<String>,
+    },
+    /// 查看插件的所有版本
+    Info {
+        /// 插件名称
+        name: String,
+    },
+    /// 从仓库安装插件及其必需依赖
+    Install {
+        /// 插件名称，可带版本: name@1.2.0 或 name@^1.2
+        plugin: String,
+    },
+    /// 列出仓库中有新版本的已安装插件
+    Outdated,
+    /// 由目录中的 .mkpkg 插件包生成仓库索引 index.json
+    Index {
+        /// 仓库目录
+        dir: PathBuf,
+    },
+}
+
+/// 消息总线子命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum BusCommands {
+    /// 查看运行中内核最近保存的总线统计
+    Stats {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出插件清单中声明的主题 schema
+    Schemas {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 /// 日志级别
 #[derive(clap::ValueEnum, Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -91,6 +206,8 @@ pub struct Config {
     pub network: NetworkConfig,
     /// 身份管理配置
     pub identity: IdentityConfig,
+    /// 消息总线配置
+    pub message_bus: MessageBusConfig,
 }
 
 /// 数据库配置
@@ -119,6 +236,42 @@ pub struct PluginConfig {
     pub max_memory_mb: u32,
     /// 启用的插件列表
     pub enabled: Vec<String>,
+    /// 未声明 [permissions] 的插件获得的默认权限（空表示拒绝全部）
+    pub default_permissions: Vec<String>,
+    /// 每个插件的消息队列深度
+    pub queue_depth: usize,
+    /// 插件消息队列已满时的处理策略
+    pub queue_overflow: OverflowPolicy,
+    /// `block` 策略下等待队列空位的最长时间（毫秒）
+    pub queue_block_timeout_ms: u64,
+    /// 受信任的插件发布者（安装 .mkpkg 包时校验签名）
+    pub trusted_publishers: Vec<TrustedPublisher>,
+    /// 插件仓库地址（目录或 file:// URL）
+    pub registry: Option<String>,
+}
+
+/// 受信任的插件发布者
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedPublisher {
+    /// 发布者名称
+    pub name: String,
+    /// 发布者的以太坊地址
+    pub address: String,
+}
+
+/// 插件消息队列已满时的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// 丢弃队列中最旧的消息
+    DropOldest,
+    /// 丢弃新到达的消息
+    DropNewest,
+    /// 按到达顺序在等待缓冲（与队列同样深度）中等待空位，超时或缓冲已满时丢弃（不阻塞其它插件的投递）
+    #[default]
+    Block,
+    /// 写入持久投递队列，稍后重投（需启用 message_bus.durable）
+    Spill,
 }
 
 /// 日志配置
@@ -175,6 +328,118 @@ pub struct IdentityConfig {
     pub allow_env_key: bool,
 }
 
+/// 消息总线配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageBusConfig {
+    /// 消息缓冲区大小
+    pub buffer_size: usize,
+    /// 主通道已满时插件发送消息的最长等待时间（毫秒）
+    pub send_timeout_ms: u64,
+    /// 运行中的内核保存总线统计快照的间隔（秒，0 表示不保存），供 `bus stats` 命令读取
+    pub stats_snapshot_secs: u64,
+    /// 消息日志
+    pub journal: JournalConfig,
+    /// 持久投递队列
+    pub durable: DurableQueueConfig,
+    /// 保留消息的主题
+    pub retained: Vec<RetainedTopicConfig>,
+    /// 消息签名
+    pub signing: SigningConfig,
+    /// 按插件声明的主题 schema 校验发布的消息
+    pub schema_validation: SchemaValidation,
+}
+
+/// 保留主题配置
+///
+/// 总线保留匹配主题最近发布的消息，新订阅者订阅时立即收到
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetainedTopicConfig {
+    /// 主题模式（支持通配符，每个匹配的主题分别保留）
+    pub pattern: String,
+    /// 每个主题保留的消息数
+    pub depth: usize,
+    /// 是否保存到 SQLite（重启后恢复）
+    pub persist: bool,
+}
+
+impl Default for RetainedTopicConfig {
+    fn default() -> Self {
+        Self {
+            pattern: String::new(),
+            depth: 1,
+            persist: false,
+        }
+    }
+}
+
+/// 主题 schema 校验方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaValidation {
+    /// 不校验
+    Off,
+    /// 记录警告，消息照常投递
+    #[default]
+    Warn,
+    /// 拒绝不符合 schema 的消息
+    Reject,
+}
+
+/// 消息签名配置
+///
+/// 内核用发送方插件的派生密钥为消息签名，路由前验证签名，
+/// 通过验证的消息以 `verified_from` 告知接收方来源可信
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// 是否为所有消息签名
+    pub enabled: bool,
+    /// 受保护的主题模式（支持通配符）：消息总是签名，签名缺失或无效时拒绝
+    pub protected_topics: Vec<String>,
+}
+
+/// 消息日志配置（记录到 message_log 表）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    /// 是否记录经过总线的消息
+    pub enabled: bool,
+    /// 是否记录消息负载
+    pub record_payloads: bool,
+    /// 记录保留时长（秒，0 表示不按时间清理）
+    pub max_age_secs: u64,
+    /// 最多保留的记录数（0 表示不限制）
+    pub max_entries: u64,
+    /// 清理间隔（秒）
+    pub prune_interval_secs: u64,
+}
+
+/// 持久投递队列配置
+///
+/// 标记为 durable 的消息以及匹配 `topics` 的主题消息会保存到 SQLite，
+/// 接收方处理成功（确认）后删除，失败或超时未确认时按指数退避重投，
+/// 超过 `max_attempts` 次后移入死信表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DurableQueueConfig {
+    /// 是否启用持久投递
+    pub enabled: bool,
+    /// 需要持久投递的主题模式（支持通配符）
+    pub topics: Vec<String>,
+    /// 最大投递次数
+    pub max_attempts: u32,
+    /// 投递后等待确认的时间（毫秒），超时未确认视为失败
+    pub ack_timeout_ms: u64,
+    /// 首次重试的退避时间（毫秒），之后每次翻倍
+    pub retry_initial_ms: u64,
+    /// 退避时间上限（毫秒）
+    pub retry_max_ms: u64,
+    /// 检查待重投消息的间隔（毫秒）
+    pub poll_interval_ms: u64,
+}
+
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
@@ -193,6 +458,19 @@ impl Default for PluginConfig {
             timeout_ms: 5000,
             max_memory_mb: 128,
             enabled: vec![],
+            // 兼容尚未声明权限的旧插件
+            default_permissions: vec![
+                "storage".to_string(),
+                "identity.sign".to_string(),
+                "bus.publish:*".to_string(),
+                "bus.subscribe:*".to_string(),
+                "bus.send:*".to_string(),
+            ],
+            queue_depth: 100,
+            queue_overflow: OverflowPolicy::Block,
+            queue_block_timeout_ms: 1000,
+            trusted_publishers: vec![],
+            registry: None,
         }
     }
 }
@@ -219,6 +497,47 @@ impl Default for NetworkConfig {
     }
 }
 
+impl Default for MessageBusConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 1000,
+            send_timeout_ms: 1000,
+            stats_snapshot_secs: 10,
+            journal: JournalConfig::default(),
+            durable: DurableQueueConfig::default(),
+            retained: vec![],
+            signing: SigningConfig::default(),
+            schema_validation: SchemaValidation::default(),
+        }
+    }
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            record_payloads: true,
+            max_age_secs: 7 * 24 * 60 * 60, // 保留 7 天
+            max_entries: 100_000,
+            prune_interval_secs: 300,
+        }
+    }
+}
+
+impl Default for DurableQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            topics: vec![],
+            max_attempts: 5,
+            ack_timeout_ms: 30_000,
+            retry_initial_ms: 1_000,
+            retry_max_ms: 60_000,
+            poll_interval_ms: 1_000,
+        }
+    }
+}
+
 impl Default for IdentityConfig {
     fn default() -> Self {
         Self {
@@ -363,6 +682,37 @@ impl Config {
             return Err(anyhow!("监听端口不能为 0"));
         }
 
+        if self.plugins.queue_depth == 0 {
+            return Err(anyhow!("插件消息队列深度不能为 0"));
+        }
+
+        // 验证消息总线配置
+        if self.message_bus.buffer_size == 0 {
+            return Err(anyhow!("消息缓冲区大小不能为 0"));
+        }
+        if self.message_bus.journal.prune_interval_secs == 0 {
+            return Err(anyhow!("消息日志清理间隔不能为 0"));
+        }
+        let durable = &self.message_bus.durable;
+        if durable.max_attempts == 0 {
+            return Err(anyhow!("持久投递的最大投递次数不能为 0"));
+        }
+        if durable.poll_interval_ms == 0 {
+            return Err(anyhow!("持久投递的检查间隔不能为 0"));
+        }
+        for topic in &durable.topics {
+            crate::kernel::topic_index::validate_topic_pattern(topic)?;
+        }
+        for retained in &self.message_bus.retained {
+            crate::kernel::topic_index::validate_topic_pattern(&retained.pattern)?;
+            if retained.depth == 0 {
+                return Err(anyhow!(
+                    "保留主题 '{}' 的保留条数不能为 0",
+                    retained.pattern
+                ));
+            }
+        }
+
         Ok(())
     }
 
@@ -242,6 +242,22 @@ impl IdentityManager {
         Ok(recovered_address == plugin_address)
     }
 
+    /// 以主密钥签名消息（EIP-191）
+    pub fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let signature = self.master_key.sign_message_sync(message)?;
+        Ok(signature.as_bytes().to_vec())
+    }
+
+    /// 从 EIP-191 签名恢复签名者地址
+    pub fn recover_signer(message: &[u8], signature: &[u8]) -> Result<Address> {
+        let signature = alloy::primitives::Signature::try_from(signature)
+            .map_err(|e| anyhow!("Invalid signature format: {}", e))?;
+
+        signature
+            .recover_address_from_msg(message)
+            .map_err(|e| anyhow!("Failed to recover address: {}", e))
+    }
+
     /// 保存主密钥到系统keyring
     pub fn save_to_keyring(&self) -> Result<()> {
         let private_key_hex = hex::encode(self.master_key.to_bytes().as_slice());
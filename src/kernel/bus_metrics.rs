@@ -0,0 +1,395 @@
+//! 消息总线指标
+//!
+//! 路由器按主题与插件统计发布、投递、排队、丢弃、失败和过期的消息数，
+//! 并记录从发布到路由完成的延迟分布。`MessageBusHandle::stats()` 汇总为 `BusStats`，
+//! 运行中的内核定期把快照写入数据库，供 `bus stats` 命令读取
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::message::{Message, MessageResult};
+use super::message_bus::MessageBusHandle;
+use super::plugin_queue::PluginQueueStats;
+use crate::storage::Storage;
+
+/// 路由延迟直方图的桶上限（微秒），最后一个桶不设上限
+const LATENCY_BUCKETS_US: [u64; 10] = [
+    100, 250, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000,
+];
+
+/// 消息计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageCounters {
+    /// 发布的消息数（插件统计中为该插件发出的消息数）
+    pub published: u64,
+    /// 已放入接收方队列
+    pub delivered: u64,
+    /// 已写入持久投递队列，等待投递
+    pub queued: u64,
+    /// 因队列已满或没有订阅者而丢弃
+    pub dropped: u64,
+    /// 投递或处理失败
+    pub failed: u64,
+    /// 投递前已过期
+    pub expired: u64,
+    /// 被拦截器拒绝
+    #[serde(default)]
+    pub rejected: u64,
+}
+
+/// 一次投递的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    Queued,
+    Dropped,
+    Failed,
+    Expired,
+    Rejected,
+}
+
+impl MessageCounters {
+    fn record(&mut self, outcome: DeliveryOutcome) {
+        match outcome {
+            DeliveryOutcome::Delivered => self.delivered += 1,
+            DeliveryOutcome::Queued => self.queued += 1,
+            DeliveryOutcome::Dropped => self.dropped += 1,
+            DeliveryOutcome::Failed => self.failed += 1,
+            DeliveryOutcome::Expired => self.expired += 1,
+            DeliveryOutcome::Rejected => self.rejected += 1,
+        }
+    }
+}
+
+/// 延迟直方图的一个桶
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyBucket {
+    /// 桶上限（微秒，None 表示不设上限）
+    pub le_us: Option<u64>,
+    /// 落入该桶的消息数
+    pub count: u64,
+}
+
+/// 路由延迟统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// 统计的消息数
+    pub count: u64,
+    /// 平均延迟（微秒）
+    pub mean_us: u64,
+    /// 最大延迟（微秒）
+    pub max_us: u64,
+    /// 各桶的消息数
+    pub buckets: Vec<LatencyBucket>,
+}
+
+#[derive(Default)]
+struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS_US.len() + 1],
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, latency: Duration) {
+        let us = latency.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = LATENCY_BUCKETS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(LATENCY_BUCKETS_US.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    fn stats(&self) -> LatencyStats {
+        let bounds = LATENCY_BUCKETS_US.iter().map(|&bound| Some(bound));
+        LatencyStats {
+            count: self.count,
+            mean_us: self.sum_us.checked_div(self.count).unwrap_or(0),
+            max_us: self.max_us,
+            buckets: bounds
+                .chain(std::iter::once(None))
+                .zip(self.counts)
+                .map(|(le_us, count)| LatencyBucket { le_us, count })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MetricsState {
+    totals: MessageCounters,
+    topics: HashMap<String, MessageCounters>,
+    plugins: HashMap<String, MessageCounters>,
+    latency: LatencyHistogram,
+}
+
+/// 总线指标收集器（可克隆，由 Handle 与 Router 共享）
+#[derive(Clone)]
+pub struct BusMetrics {
+    started_at: DateTime<Utc>,
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl Default for BusMetrics {
+    fn default() -> Self {
+        Self {
+            started_at: Utc::now(),
+            state: Arc::default(),
+        }
+    }
+}
+
+impl BusMetrics {
+    /// 创建收集器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一条进入路由器的消息
+    pub fn record_published(&self, message: &Message) {
+        let mut state = self.state.lock();
+        state.totals.published += 1;
+        if let Some(topic) = &message.topic {
+            state.topics.entry(topic.clone()).or_default().published += 1;
+        }
+        state
+            .plugins
+            .entry(message.from.clone())
+            .or_default()
+            .published += 1;
+    }
+
+    /// 记录消息的路由结果与路由延迟（从发布到路由完成）
+    pub fn record_routed(
+        &self,
+        topic: Option<&str>,
+        published_at: DateTime<Utc>,
+        result: &MessageResult,
+    ) {
+        let outcome = match result {
+            MessageResult::Success => DeliveryOutcome::Delivered,
+            MessageResult::Queued => DeliveryOutcome::Queued,
+            MessageResult::Expired => DeliveryOutcome::Expired,
+            MessageResult::Rejected(_) => DeliveryOutcome::Rejected,
+            // 没有订阅者的主题消息视为丢弃
+            MessageResult::PluginNotFound(_) if topic.is_some() => DeliveryOutcome::Dropped,
+            MessageResult::PluginNotFound(_) | MessageResult::Failed(_) => DeliveryOutcome::Failed,
+        };
+        let latency = (Utc::now() - published_at).to_std().unwrap_or_default();
+
+        let mut state = self.state.lock();
+        state.totals.record(outcome);
+        if let Some(topic) = topic {
+            state
+                .topics
+                .entry(topic.to_string())
+                .or_default()
+                .record(outcome);
+        }
+        state.latency.observe(latency);
+    }
+
+    /// 记录发给某个插件的一次投递结果
+    pub fn record_delivery(&self, plugin_id: &str, outcome: DeliveryOutcome) {
+        self.state
+            .lock()
+            .plugins
+            .entry(plugin_id.to_string())
+            .or_default()
+            .record(outcome);
+    }
+
+    /// 记录插件分发时发现的过期消息（同时计入总数）
+    pub fn record_expired(&self, plugin_id: &str) {
+        let mut state = self.state.lock();
+        state.totals.expired += 1;
+        state
+            .plugins
+            .entry(plugin_id.to_string())
+            .or_default()
+            .expired += 1;
+    }
+
+    /// 所有消息的计数
+    pub fn totals(&self) -> MessageCounters {
+        self.state.lock().totals
+    }
+
+    fn snapshot(&self) -> MetricsSnapshot {
+        let state = self.state.lock();
+        MetricsSnapshot {
+            totals: state.totals,
+            topics: state
+                .topics
+                .iter()
+                .map(|(topic, counters)| (topic.clone(), *counters))
+                .collect(),
+            plugins: state
+                .plugins
+                .iter()
+                .map(|(plugin_id, counters)| (plugin_id.clone(), *counters))
+                .collect(),
+            latency: state.latency.stats(),
+        }
+    }
+}
+
+struct MetricsSnapshot {
+    totals: MessageCounters,
+    topics: BTreeMap<String, MessageCounters>,
+    plugins: BTreeMap<String, MessageCounters>,
+    latency: LatencyStats,
+}
+
+/// 插件的总线统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginBusStats {
+    /// 消息计数
+    #[serde(flatten)]
+    pub counters: MessageCounters,
+    /// 消息队列状态（插件未加载时为 None）
+    pub queue: Option<PluginQueueStats>,
+    /// 订阅的主题模式数
+    pub subscriptions: usize,
+}
+
+/// 消息总线统计快照
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusStats {
+    /// 采集时间
+    pub collected_at: DateTime<Utc>,
+    /// 统计开始后经过的秒数
+    pub uptime_secs: u64,
+    /// 所有消息的计数
+    pub totals: MessageCounters,
+    /// 按主题的计数
+    pub topics: BTreeMap<String, MessageCounters>,
+    /// 按插件的计数与队列状态
+    pub plugins: BTreeMap<String, PluginBusStats>,
+    /// 主通道中等待路由的消息数
+    pub router_backlog: usize,
+    /// 等待回复的请求数
+    pub pending_requests: usize,
+    /// 路由延迟
+    pub latency: LatencyStats,
+}
+
+impl BusStats {
+    pub(super) fn collect(
+        metrics: &BusMetrics,
+        queues: HashMap<String, PluginQueueStats>,
+        subscriptions: HashMap<String, usize>,
+        router_backlog: usize,
+        pending_requests: usize,
+    ) -> Self {
+        let snapshot = metrics.snapshot();
+        let collected_at = Utc::now();
+
+        let mut plugins: BTreeMap<String, PluginBusStats> = snapshot
+            .plugins
+            .into_iter()
+            .map(|(plugin_id, counters)| {
+                let stats = PluginBusStats {
+                    counters,
+                    ..Default::default()
+                };
+                (plugin_id, stats)
+            })
+            .collect();
+        for (plugin_id, queue) in queues {
+            plugins.entry(plugin_id).or_default().queue = Some(queue);
+        }
+        for (plugin_id, count) in subscriptions {
+            plugins.entry(plugin_id).or_default().subscriptions = count;
+        }
+
+        Self {
+            collected_at,
+            uptime_secs: (collected_at - metrics.started_at).num_seconds().max(0) as u64,
+            totals: snapshot.totals,
+            topics: snapshot.topics,
+            plugins,
+            router_backlog,
+            pending_requests,
+            latency: snapshot.latency,
+        }
+    }
+}
+
+/// 定期把总线统计写入数据库，供其它进程（`bus stats` 命令）读取
+pub(super) async fn run_stats_snapshots(
+    bus: MessageBusHandle,
+    storage: Arc<Storage>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let saved = match serde_json::to_string(&bus.stats()) {
+            Ok(json) => storage.save_bus_stats(&json).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = saved {
+            tracing::warn!("保存消息总线统计失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_latency_histogram() {
+        let metrics = BusMetrics::new();
+        let topic = Message::new_topic("monitor".into(), "system.stats".into(), vec![]);
+        let direct = Message::new("monitor".into(), "missing".into(), vec![]);
+
+        metrics.record_published(&topic);
+        metrics.record_routed(
+            topic.topic.as_deref(),
+            topic.timestamp,
+            &MessageResult::Success,
+        );
+        metrics.record_delivery("dashboard", DeliveryOutcome::Delivered);
+        metrics.record_delivery("logger", DeliveryOutcome::Dropped);
+        metrics.record_published(&direct);
+        let not_found = MessageResult::PluginNotFound("missing".into());
+        metrics.record_routed(None, direct.timestamp, &not_found);
+        metrics.record_expired("dashboard");
+
+        let stats = BusStats::collect(&metrics, HashMap::new(), HashMap::new(), 0, 0);
+        let totals = stats.totals;
+        assert_eq!(
+            (
+                totals.published,
+                totals.delivered,
+                totals.failed,
+                totals.expired
+            ),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(stats.topics["system.stats"].delivered, 1);
+        assert_eq!(stats.plugins["monitor"].counters.published, 2);
+        assert_eq!(stats.plugins["logger"].counters.dropped, 1);
+        assert_eq!(stats.plugins["dashboard"].counters.expired, 1);
+
+        assert_eq!(stats.latency.count, 2);
+        assert_eq!(stats.latency.buckets.len(), LATENCY_BUCKETS_US.len() + 1);
+        let bucketed: u64 = stats
+            .latency
+            .buckets
+            .iter()
+            .map(|bucket| bucket.count)
+            .sum();
+        assert_eq!(bucketed, 2);
+    }
+}
@@ -1,18 +1,70 @@
-//! 简单依赖解析器
+//! 插件依赖解析器
 //!
-//! 使用基础的HashMap和图遍历算法，避免复杂的依赖
+//! 同一插件可以有多个候选版本，解析时为每个插件选择兼容当前内核、
+//! 满足所有依赖方版本要求的最新版本，再按依赖关系排序。
+//! 无法满足时返回的错误列出冲突的约束链
 
+use super::manifest::DependencySpec;
 use super::plugin_loader::PluginInfo;
+use super::KERNEL_VERSION;
 use anyhow::{anyhow, Result};
-use std::collections::{HashMap, HashSet, VecDeque};
+use semver::VersionReq;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
-/// 简单的依赖解析器
-#[derive(Debug, Default)]
+/// 依赖解析器
+#[derive(Debug)]
 pub struct DependencyResolver {
-    /// 插件依赖图：插件名 -> 依赖列表
-    dependencies: HashMap<String, Vec<String>>,
-    /// 插件信息映射
-    plugins: HashMap<String, PluginInfo>,
+    /// 候选版本：插件名 -> 插件信息（按版本从新到旧）
+    plugins: HashMap<String, Vec<PluginInfo>>,
+    /// 用于检查 `min_kernel_version` 的内核版本
+    kernel_version: String,
+}
+
+impl Default for DependencyResolver {
+    fn default() -> Self {
+        Self {
+            plugins: HashMap::new(),
+            kernel_version: KERNEL_VERSION.to_string(),
+        }
+    }
+}
+
+/// 版本约束及其来源
+#[derive(Debug, Clone)]
+struct Constraint {
+    requirement: VersionReq,
+    /// 从加载目标到提出约束的插件，如 `["app 1.0.0", "metrics 2.0.0"]`（加载目标本身为空）
+    chain: Vec<String>,
+}
+
+impl Constraint {
+    fn describe(&self, name: &str) -> String {
+        if self.chain.is_empty() {
+            format!("加载 {} {}", name, self.requirement)
+        } else {
+            format!(
+                "{} 要求 {} {}",
+                self.chain.join(" -> "),
+                name,
+                self.requirement
+            )
+        }
+    }
+}
+
+/// 待满足的依赖
+#[derive(Debug, Clone)]
+struct Pending {
+    name: String,
+    constraint: Constraint,
+    optional: bool,
+}
+
+/// 解析中的状态：已选定的版本与每个插件收到的约束
+#[derive(Debug, Clone, Default)]
+struct Resolution {
+    selected: BTreeMap<String, PluginInfo>,
+    constraints: HashMap<String, Vec<Constraint>>,
 }
 
 impl DependencyResolver {
@@ -21,11 +73,18 @@ impl DependencyResolver {
         Self::default()
     }
 
-    /// 添加插件信息
+    /// 使用指定的内核版本检查插件兼容性
+    pub fn with_kernel_version(mut self, kernel_version: &str) -> Self {
+        self.kernel_version = kernel_version.to_string();
+        self
+    }
+
+    /// 添加插件信息（同一路径的旧信息会被替换）
     pub fn add_plugin(&mut self, plugin: PluginInfo) {
-        let deps = plugin.all_dependencies();
-        self.dependencies.insert(plugin.name.clone(), deps);
-        self.plugins.insert(plugin.name.clone(), plugin);
+        let candidates = self.plugins.entry(plugin.name.clone()).or_default();
+        candidates.retain(|candidate| candidate.path != plugin.path);
+        candidates.push(plugin);
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.semver()));
     }
 
     /// 添加多个插件
@@ -35,70 +94,211 @@ impl DependencyResolver {
         }
     }
 
-    /// 解析依赖顺序（拓扑排序）
-    pub fn resolve_order(&self, target_plugins: &[String]) -> Result<Vec<String>> {
-        let mut result = Vec::new();
-        let mut visited = HashSet::new();
-        let mut visiting = HashSet::new();
+    /// 标记插件已卸载（之后解析时可以选择其它版本）
+    pub fn mark_unloaded(&mut self, plugin_name: &str) {
+        for candidate in self.plugins.get_mut(plugin_name).into_iter().flatten() {
+            candidate.loaded = false;
+        }
+    }
 
-        for plugin_name in target_plugins {
-            if !visited.contains(plugin_name) {
-                self.visit_plugin(plugin_name, &mut visited, &mut visiting, &mut result)?;
-            }
+    /// 插件的候选版本：已加载的插件只能使用已加载的版本
+    fn candidates(&self, plugin_name: &str) -> Vec<&PluginInfo> {
+        let candidates = self
+            .plugins
+            .get(plugin_name)
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+        if candidates.iter().any(|candidate| candidate.loaded) {
+            candidates
+                .iter()
+                .filter(|candidate| candidate.loaded)
+                .collect()
+        } else {
+            candidates.iter().collect()
         }
+    }
 
-        Ok(result)
+    /// 插件当前使用的版本：已加载的版本，否则为兼容内核的最新版本
+    fn primary(&self, plugin_name: &str) -> Option<&PluginInfo> {
+        let candidates = self.candidates(plugin_name);
+        candidates
+            .iter()
+            .find(|candidate| candidate.is_compatible_with_kernel(&self.kernel_version))
+            .or(candidates.first())
+            .copied()
     }
 
-    /// 深度优先搜索访问插件（递归版本）
-    fn visit_plugin(
+    /// 为目标插件及其依赖选择版本，按加载顺序返回（依赖在前）
+    ///
+    /// 必需依赖缺失、版本要求冲突、没有兼容内核的版本或存在循环依赖时返回错误；
+    /// 可选依赖存在且能满足版本要求时才会被选中
+    pub fn resolve(&self, target_plugins: &[String]) -> Result<Vec<PluginInfo>> {
+        let pending = target_plugins
+            .iter()
+            .map(|name| Pending {
+                name: name.clone(),
+                constraint: Constraint {
+                    requirement: VersionReq::STAR,
+                    chain: Vec::new(),
+                },
+                optional: false,
+            })
+            .collect();
+
+        let resolution = self
+            .solve(Resolution::default(), pending)
+            .map_err(|e| anyhow!("依赖解析失败: {}", e))?;
+        let selected: HashMap<&str, &PluginInfo> = resolution
+            .selected
+            .iter()
+            .map(|(name, info)| (name.as_str(), info))
+            .collect();
+
+        Ok(topological_order(&selected, target_plugins, false)?
+            .into_iter()
+            .map(|name| resolution.selected[&name].clone())
+            .collect())
+    }
+
+    /// 依次满足待处理的依赖，版本选择失败时回溯尝试较旧的版本
+    fn solve(
         &self,
-        plugin_name: &str,
-        visited: &mut HashSet<String>,
-        visiting: &mut HashSet<String>,
-        result: &mut Vec<String>,
-    ) -> Result<()> {
-        if visiting.contains(plugin_name) {
-            return Err(anyhow!("发现循环依赖: {}", plugin_name));
+        mut state: Resolution,
+        mut pending: VecDeque<Pending>,
+    ) -> std::result::Result<Resolution, String> {
+        let Some(next) = pending.pop_front() else {
+            return Ok(state);
+        };
+        let name = next.name.as_str();
+        state
+            .constraints
+            .entry(name.to_string())
+            .or_default()
+            .push(next.constraint.clone());
+        let constraints = state.constraints[name].clone();
+
+        // 已选定的版本必须满足新的约束
+        if let Some(selected) = state.selected.get(name) {
+            if next.constraint.requirement.matches(&selected.semver()) {
+                return self.solve(state, pending);
+            }
+            return Err(self.conflict(name, &constraints, &[selected]));
         }
 
-        if visited.contains(plugin_name) {
-            return Ok(());
+        let candidates = self.candidates(name);
+        if candidates.is_empty() {
+            if next.optional {
+                return self.solve(state, pending);
+            }
+            return Err(format!(
+                "未找到插件 {}（{}）",
+                name,
+                next.constraint.describe(name)
+            ));
         }
 
-        visiting.insert(plugin_name.to_string());
+        let matching: Vec<&PluginInfo> = candidates
+            .iter()
+            .filter(|candidate| candidate.is_compatible_with_kernel(&self.kernel_version))
+            .filter(|candidate| {
+                let version = candidate.semver();
+                constraints
+                    .iter()
+                    .all(|constraint| constraint.requirement.matches(&version))
+            })
+            .copied()
+            .collect();
+
+        let mut first_error = None;
+        for candidate in matching {
+            let mut chain = next.constraint.chain.clone();
+            chain.push(format!("{} {}", candidate.name, candidate.version));
+
+            let mut next_pending = pending.clone();
+            let dependencies = candidate.dependencies.iter().map(|dep| (dep, false)).chain(
+                candidate
+                    .optional_dependencies
+                    .iter()
+                    .map(|dep| (dep, true)),
+            );
+            for (dep, optional) in dependencies {
+                next_pending.push_back(pending_dependency(dep, &chain, optional));
+            }
 
-        // 访问所有依赖
-        if let Some(deps) = self.dependencies.get(plugin_name) {
-            for dep in deps {
-                if !self.plugins.contains_key(dep) {
-                    tracing::warn!("未找到依赖插件: {} (需要 {})", dep, plugin_name);
-                    continue;
+            let mut next_state = state.clone();
+            next_state
+                .selected
+                .insert(name.to_string(), candidate.clone());
+            match self.solve(next_state, next_pending) {
+                Ok(resolution) => return Ok(resolution),
+                Err(e) => {
+                    first_error.get_or_insert(e);
                 }
-                self.visit_plugin(dep, visited, visiting, result)?;
             }
         }
 
-        visiting.remove(plugin_name);
-        visited.insert(plugin_name.to_string());
-        result.push(plugin_name.to_string());
+        // 可选依赖无法满足时不加载它
+        if next.optional {
+            tracing::debug!("跳过可选依赖 {}: 没有可用的版本", name);
+            return self.solve(state, pending);
+        }
 
-        Ok(())
+        Err(first_error.unwrap_or_else(|| self.conflict(name, &constraints, &candidates)))
     }
 
-    /// 检查是否存在循环依赖
-    pub fn check_circular_dependencies(&self) -> Result<()> {
-        let mut visited = HashSet::new();
-        let mut visiting = HashSet::new();
-        let mut dummy_result = Vec::new();
+    /// 描述没有版本能满足的约束
+    fn conflict(
+        &self,
+        name: &str,
+        constraints: &[Constraint],
+        candidates: &[&PluginInfo],
+    ) -> String {
+        let versions: Vec<String> = candidates
+            .iter()
+            .map(|candidate| {
+                if candidate.is_compatible_with_kernel(&self.kernel_version) {
+                    candidate.version.clone()
+                } else {
+                    format!(
+                        "{}（需要内核 {}，当前为 {}）",
+                        candidate.version,
+                        candidate.min_kernel_version.as_deref().unwrap_or_default(),
+                        self.kernel_version
+                    )
+                }
+            })
+            .collect();
+        let constraints: Vec<String> = constraints
+            .iter()
+            .map(|constraint| constraint.describe(name))
+            .collect();
+
+        format!(
+            "插件 {} 没有同时满足以下约束的版本: {}（可用版本: {}）",
+            name,
+            constraints.join("; "),
+            versions.join(", ")
+        )
+    }
 
-        for plugin_name in self.plugins.keys() {
-            if !visited.contains(plugin_name) {
-                self.visit_plugin(plugin_name, &mut visited, &mut visiting, &mut dummy_result)?;
-            }
-        }
+    /// 解析依赖顺序（拓扑排序）
+    ///
+    /// 每个插件使用当前版本（已加载的版本或最新版本），忽略缺失的依赖
+    pub fn resolve_order(&self, target_plugins: &[String]) -> Result<Vec<String>> {
+        let primary: HashMap<&str, &PluginInfo> = self
+            .plugins
+            .keys()
+            .filter_map(|name| Some((name.as_str(), self.primary(name)?)))
+            .collect();
 
-        Ok(())
+        topological_order(&primary, target_plugins, true)
+    }
+
+    /// 检查是否存在循环依赖
+    pub fn check_circular_dependencies(&self) -> Result<()> {
+        let mut names: Vec<String> = self.plugins.keys().cloned().collect();
+        names.sort();
+        self.resolve_order(&names).map(|_| ())
     }
 
     /// 获取指定插件的所有依赖（递归）
@@ -115,11 +315,11 @@ impl DependencyResolver {
             }
             visited.insert(current.clone());
 
-            if let Some(plugin_deps) = self.dependencies.get(&current) {
-                for dep in plugin_deps {
-                    if !visited.contains(dep) {
+            if let Some(plugin) = self.primary(&current) {
+                for dep in plugin.all_dependencies() {
+                    if !visited.contains(&dep) {
                         queue.push_back(dep.clone());
-                        deps.push(dep.clone());
+                        deps.push(dep);
                     }
                 }
             }
@@ -128,14 +328,17 @@ impl DependencyResolver {
         Ok(deps)
     }
 
-    /// 检查依赖是否满足
+    /// 检查必需依赖是否满足
     pub fn check_dependencies_satisfied(
         &self,
         plugin_name: &str,
         available_plugins: &[String],
     ) -> bool {
-        if let Some(deps) = self.dependencies.get(plugin_name) {
-            deps.iter().all(|dep| available_plugins.contains(dep))
+        if let Some(plugin) = self.primary(plugin_name) {
+            plugin
+                .dependencies
+                .iter()
+                .all(|dep| available_plugins.contains(&dep.name))
         } else {
             true // 没有依赖
         }
@@ -143,18 +346,100 @@ impl DependencyResolver {
 
     /// 获取插件统计信息
     pub fn get_stats(&self) -> (usize, usize, usize) {
-        let total_plugins = self.plugins.len();
-        let total_dependencies: usize = self.dependencies.values().map(|deps| deps.len()).sum();
-        let plugins_with_deps = self
-            .dependencies
-            .values()
-            .filter(|deps| !deps.is_empty())
+        let plugins: Vec<&PluginInfo> = self
+            .plugins
+            .keys()
+            .filter_map(|name| self.primary(name))
+            .collect();
+        let total_dependencies: usize = plugins
+            .iter()
+            .map(|plugin| plugin.all_dependencies().len())
+            .sum();
+        let plugins_with_deps = plugins
+            .iter()
+            .filter(|plugin| plugin.has_dependencies())
             .count();
 
-        (total_plugins, total_dependencies, plugins_with_deps)
+        (plugins.len(), total_dependencies, plugins_with_deps)
     }
 }
 
+fn pending_dependency(dep: &DependencySpec, chain: &[String], optional: bool) -> Pending {
+    Pending {
+        name: dep.name.clone(),
+        constraint: Constraint {
+            requirement: dep.version.clone(),
+            chain: chain.to_vec(),
+        },
+        optional,
+    }
+}
+
+/// 按依赖关系排序（依赖在前），只考虑 `plugins` 中存在的依赖
+fn topological_order(
+    plugins: &HashMap<&str, &PluginInfo>,
+    target_plugins: &[String],
+    warn_missing: bool,
+) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    let mut visited = HashSet::new();
+    let mut visiting = Vec::new();
+
+    for plugin_name in target_plugins {
+        visit_plugin(
+            plugins,
+            plugin_name,
+            warn_missing,
+            &mut visited,
+            &mut visiting,
+            &mut result,
+        )?;
+    }
+
+    Ok(result)
+}
+
+/// 深度优先搜索访问插件（递归版本）
+fn visit_plugin(
+    plugins: &HashMap<&str, &PluginInfo>,
+    plugin_name: &str,
+    warn_missing: bool,
+    visited: &mut HashSet<String>,
+    visiting: &mut Vec<String>,
+    result: &mut Vec<String>,
+) -> Result<()> {
+    if let Some(start) = visiting.iter().position(|name| name == plugin_name) {
+        let mut cycle = visiting[start..].to_vec();
+        cycle.push(plugin_name.to_string());
+        return Err(anyhow!("发现循环依赖: {}", cycle.join(" -> ")));
+    }
+
+    if visited.contains(plugin_name) {
+        return Ok(());
+    }
+
+    visiting.push(plugin_name.to_string());
+
+    // 访问所有依赖
+    if let Some(plugin) = plugins.get(plugin_name) {
+        for dep in plugin.all_dependencies() {
+            if !plugins.contains_key(dep.as_str()) {
+                if warn_missing {
+                    tracing::warn!("未找到依赖插件: {} (需要 {})", dep, plugin_name);
+                }
+                continue;
+            }
+            visit_plugin(plugins, &dep, warn_missing, visited, visiting, result)?;
+        }
+    }
+
+    visiting.pop();
+    visited.insert(plugin_name.to_string());
+    result.push(plugin_name.to_string());
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,19 +447,34 @@ mod tests {
     use std::time::SystemTime;
 
     fn create_test_plugin(name: &str, deps: Vec<String>) -> PluginInfo {
+        versioned_plugin(
+            name,
+            "1.0.0",
+            &deps
+                .iter()
+                .map(|dep| (dep.as_str(), "*"))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn versioned_plugin(name: &str, version: &str, deps: &[(&str, &str)]) -> PluginInfo {
         PluginInfo {
             name: name.to_string(),
-            path: PathBuf::from(format!("{}.wasm", name)),
+            path: PathBuf::from(format!("{}-{}.wasm", name, version)),
             file_size: 1024,
             modified: SystemTime::now(),
             loaded: false,
-            version: "1.0.0".to_string(),
+            version: version.to_string(),
             description: format!("{} 测试插件", name),
             author: None,
-            dependencies: deps,
+            dependencies: deps
+                .iter()
+                .map(|(dep, req)| DependencySpec::parse(dep, req).unwrap())
+                .collect(),
             optional_dependencies: Vec::new(),
             tags: Vec::new(),
             min_kernel_version: None,
+            permissions: None,
         }
     }
 
@@ -200,7 +500,7 @@ mod tests {
         resolver.add_plugin(create_test_plugin("B", vec!["A".to_string()]));
 
         let result = resolver.resolve_order(&["A".to_string()]);
-        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("A -> B -> A"));
     }
 
     #[test]
@@ -226,4 +526,110 @@ mod tests {
         assert!(base_pos < plugin1_pos);
         assert!(base_pos < plugin2_pos);
     }
+
+    #[test]
+    fn test_selects_newest_version_satisfying_all_constraints() {
+        let mut resolver = DependencyResolver::new();
+        resolver.add_plugin(versioned_plugin("storage-core", "1.1.0", &[]));
+        resolver.add_plugin(versioned_plugin("storage-core", "1.4.2", &[]));
+        resolver.add_plugin(versioned_plugin("storage-core", "2.0.0", &[]));
+        resolver.add_plugin(versioned_plugin(
+            "metrics",
+            "1.0.0",
+            &[("storage-core", ">=1.2")],
+        ));
+        resolver.add_plugin(versioned_plugin(
+            "app",
+            "1.0.0",
+            &[("storage-core", "^1.2"), ("metrics", "1")],
+        ));
+
+        let resolved = resolver.resolve(&["app".to_string()]).unwrap();
+        let versions: Vec<(&str, &str)> = resolved
+            .iter()
+            .map(|info| (info.name.as_str(), info.version.as_str()))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                ("storage-core", "1.4.2"),
+                ("metrics", "1.0.0"),
+                ("app", "1.0.0")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflict_names_constraint_chain() {
+        let mut resolver = DependencyResolver::new();
+        resolver.add_plugin(versioned_plugin("storage-core", "1.4.0", &[]));
+        resolver.add_plugin(versioned_plugin("storage-core", "2.1.0", &[]));
+        resolver.add_plugin(versioned_plugin(
+            "metrics",
+            "2.0.0",
+            &[("storage-core", "^2")],
+        ));
+        resolver.add_plugin(versioned_plugin(
+            "app",
+            "1.0.0",
+            &[("storage-core", "^1.2"), ("metrics", "^2")],
+        ));
+
+        let err = resolver
+            .resolve(&["app".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("app 1.0.0 要求 storage-core ^1.2"), "{err}");
+        assert!(
+            err.contains("app 1.0.0 -> metrics 2.0.0 要求 storage-core ^2"),
+            "{err}"
+        );
+
+        let err = resolver
+            .resolve(&["missing".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("未找到插件 missing"));
+    }
+
+    #[test]
+    fn test_rejects_incompatible_kernel() {
+        let mut resolver = DependencyResolver::new().with_kernel_version("0.9.0");
+        let mut newer = versioned_plugin("base", "2.0.0", &[]);
+        newer.min_kernel_version = Some("0.10.0".to_string());
+        resolver.add_plugin(newer);
+        resolver.add_plugin(versioned_plugin("base", "1.0.0", &[]));
+
+        // 跳过需要更高内核版本的候选
+        let resolved = resolver.resolve(&["base".to_string()]).unwrap();
+        assert_eq!(resolved[0].version, "1.0.0");
+
+        resolver.add_plugin(versioned_plugin("app", "1.0.0", &[("base", "^2")]));
+        let err = resolver
+            .resolve(&["app".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("需要内核 0.10.0，当前为 0.9.0"), "{err}");
+    }
+
+    #[test]
+    fn test_optional_dependencies() {
+        let mut resolver = DependencyResolver::new();
+        let mut app = versioned_plugin("app", "1.0.0", &[]);
+        app.optional_dependencies = vec![
+            DependencySpec::parse("theme", "^1").unwrap(),
+            DependencySpec::any("missing"),
+        ];
+        resolver.add_plugin(app);
+        resolver.add_plugin(versioned_plugin("theme", "2.0.0", &[]));
+
+        // 可选依赖缺失或版本不满足时不加载
+        let resolved = resolver.resolve(&["app".to_string()]).unwrap();
+        assert_eq!(resolved.len(), 1);
+
+        resolver.add_plugin(versioned_plugin("theme", "1.3.0", &[]));
+        let resolved = resolver.resolve(&["app".to_string()]).unwrap();
+        assert_eq!(resolved[0].name, "theme");
+        assert_eq!(resolved[0].version, "1.3.0");
+    }
 }
@@ -0,0 +1,332 @@
+//! 持久投递队列
+//!
+//! 持久消息在投递前写入 durable_messages 表（每个接收方一行），
+//! 接收方处理成功即确认并删除。处理失败按指数退避重投，
+//! 投递后超过 ack_timeout 未确认（如插件崩溃、内核重启）时同样会重投，
+//! 超过最大投递次数后移入 dead_letters 表，可查看并重放
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::message::Message;
+use super::message_bus::PluginChannels;
+use super::topic_index::topic_matches;
+use crate::config::DurableQueueConfig;
+use crate::storage::{DeadLetter, DurableMessage, Storage};
+
+/// 每轮最多重投的消息数
+const REDELIVERY_BATCH_SIZE: i64 = 100;
+
+/// 处理失败后的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NackOutcome {
+    /// 将在指定时间（毫秒时间戳）重投
+    Retry { next_attempt_at: i64 },
+    /// 已移入死信表
+    DeadLettered,
+    /// 消息已不在队列中（已确认或已移入死信表）
+    Unknown,
+}
+
+/// 持久投递队列（可克隆）
+#[derive(Clone)]
+pub struct DurableQueue {
+    storage: Arc<Storage>,
+    config: DurableQueueConfig,
+}
+
+impl DurableQueue {
+    pub fn new(storage: Arc<Storage>, config: DurableQueueConfig) -> Self {
+        Self { storage, config }
+    }
+
+    /// 检查消息是否需要持久投递（消息标记为 durable 或主题匹配配置）
+    pub fn is_durable(&self, message: &Message) -> bool {
+        message.durable
+            || message.topic.as_deref().is_some_and(|topic| {
+                self.config
+                    .topics
+                    .iter()
+                    .any(|pattern| topic_matches(pattern, topic))
+            })
+    }
+
+    /// 保存发往接收方的消息，`delivering` 表示即将投递（计入投递次数并等待确认）
+    pub async fn enqueue(
+        &self,
+        message: &Message,
+        recipient: &str,
+        delivering: bool,
+    ) -> Result<()> {
+        let json = serde_json::to_string(message)?;
+        let (attempts, next_attempt_at) = if delivering {
+            (1, now_millis() + self.config.ack_timeout_ms as i64)
+        } else {
+            (0, now_millis())
+        };
+
+        self.storage
+            .enqueue_durable_message(&message.id, recipient, &json, attempts, next_attempt_at)
+            .await?;
+        Ok(())
+    }
+
+    /// 投递未成功（通道已满或已关闭），稍后重试且不计入投递次数
+    pub async fn defer(&self, message_id: &str, recipient: &str) -> Result<()> {
+        let next_attempt_at = now_millis() + self.config.retry_initial_ms as i64;
+        self.storage
+            .reschedule_durable_message(message_id, recipient, 0, next_attempt_at, None)
+            .await?;
+        Ok(())
+    }
+
+    /// 接收方处理成功，删除消息
+    pub async fn ack(&self, message_id: &str, recipient: &str) -> Result<bool> {
+        self.storage
+            .ack_durable_message(message_id, recipient)
+            .await
+    }
+
+    /// 接收方处理失败：按退避时间安排重投，达到最大投递次数时移入死信表
+    pub async fn nack(
+        &self,
+        message_id: &str,
+        recipient: &str,
+        error: &str,
+    ) -> Result<NackOutcome> {
+        let Some(entry) = self
+            .storage
+            .get_durable_message(message_id, recipient)
+            .await?
+        else {
+            return Ok(NackOutcome::Unknown);
+        };
+
+        if entry.attempts >= self.config.max_attempts as i64 {
+            self.storage
+                .dead_letter_message(message_id, recipient, Some(error))
+                .await?;
+            tracing::warn!(
+                "消息 {} 投递给 {} 失败 {} 次，已移入死信表: {}",
+                message_id,
+                recipient,
+                entry.attempts,
+                error
+            );
+            return Ok(NackOutcome::DeadLettered);
+        }
+
+        let next_attempt_at = now_millis() + self.backoff(entry.attempts).as_millis() as i64;
+        self.storage
+            .reschedule_durable_message(
+                message_id,
+                recipient,
+                entry.attempts,
+                next_attempt_at,
+                Some(error),
+            )
+            .await?;
+        Ok(NackOutcome::Retry { next_attempt_at })
+    }
+
+    /// 第 `attempts` 次投递失败后的退避时间
+    pub fn backoff(&self, attempts: i64) -> Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 32) as u32;
+        let backoff = self
+            .config
+            .retry_initial_ms
+            .saturating_mul(1u64 << exponent)
+            .min(self.config.retry_max_ms);
+        Duration::from_millis(backoff)
+    }
+
+    /// 重投已到期的消息（只投递给已注册通道的插件），返回投递的条数
+    pub async fn redeliver_due(&self, channels: &PluginChannels) -> Result<usize> {
+        let recipients: Vec<String> = channels.read().keys().cloned().collect();
+        let due = self
+            .storage
+            .due_durable_messages(&recipients, now_millis(), REDELIVERY_BATCH_SIZE)
+            .await?;
+
+        let mut delivered = 0;
+        for entry in due {
+            if entry.attempts >= self.config.max_attempts as i64 {
+                let error = entry.last_error.as_deref().unwrap_or("等待确认超时");
+                self.storage
+                    .dead_letter_message(&entry.message_id, &entry.recipient, Some(error))
+                    .await?;
+                tracing::warn!(
+                    "消息 {} 投递给 {} 已达 {} 次仍未确认，已移入死信表",
+                    entry.message_id,
+                    entry.recipient,
+                    entry.attempts
+                );
+                continue;
+            }
+
+            let message = match decode(&entry) {
+                Ok(message) => message,
+                Err(e) => {
+                    self.storage
+                        .dead_letter_message(
+                            &entry.message_id,
+                            &entry.recipient,
+                            Some(&e.to_string()),
+                        )
+                        .await?;
+                    continue;
+                }
+            };
+
+            let Some(tx) = channels.read().get(&entry.recipient).cloned() else {
+                continue;
+            };
+            // 通道已满时留到下一轮，不计入投递次数
+            if tx.try_send(message).is_err() {
+                continue;
+            }
+
+            let next_attempt_at = now_millis() + self.config.ack_timeout_ms as i64;
+            self.storage
+                .reschedule_durable_message(
+                    &entry.message_id,
+                    &entry.recipient,
+                    entry.attempts + 1,
+                    next_attempt_at,
+                    None,
+                )
+                .await?;
+            delivered += 1;
+        }
+
+        Ok(delivered)
+    }
+
+    /// 查看死信
+    pub async fn dead_letters(
+        &self,
+        recipient: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<DeadLetter>> {
+        self.storage.list_dead_letters(recipient, limit).await
+    }
+
+    /// 重放死信（放回队列立即投递），死信不存在时返回 false
+    pub async fn replay_dead_letter(&self, id: i64) -> Result<bool> {
+        self.storage.replay_dead_letter(id, now_millis()).await
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.config.poll_interval_ms)
+    }
+}
+
+/// 还原保存的消息
+fn decode(entry: &DurableMessage) -> Result<Message> {
+    let mut message: Message = serde_json::from_str(&entry.message)
+        .map_err(|e| anyhow!("无法解析持久消息 {}: {}", entry.message_id, e))?;
+    message.durable = true;
+    Ok(message)
+}
+
+fn now_millis() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// 定期重投到期的消息，总线关闭后结束
+pub(super) async fn run_redelivery(
+    queue: DurableQueue,
+    channels: PluginChannels,
+    bus_sender: mpsc::Sender<Message>,
+) {
+    tracing::debug!("持久投递重投任务已启动");
+
+    let mut interval = tokio::time::interval(queue.poll_interval());
+    while !bus_sender.is_closed() {
+        interval.tick().await;
+        match queue.redeliver_due(&channels).await {
+            Ok(0) => {}
+            Ok(count) => tracing::debug!("已重投 {} 条持久消息", count),
+            Err(e) => tracing::warn!("重投持久消息失败: {}", e),
+        }
+    }
+
+    tracing::debug!("持久投递重投任务已结束");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::plugin_queue::{plugin_queue, PluginQueueConfig};
+
+    async fn queue(config: DurableQueueConfig) -> DurableQueue {
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        DurableQueue::new(storage, config)
+    }
+
+    #[tokio::test]
+    async fn test_backoff_and_durable_topics() {
+        let queue = queue(DurableQueueConfig {
+            topics: vec!["orders.#".to_string()],
+            retry_initial_ms: 100,
+            retry_max_ms: 1000,
+            ..Default::default()
+        })
+        .await;
+
+        assert_eq!(queue.backoff(1), Duration::from_millis(100));
+        assert_eq!(queue.backoff(2), Duration::from_millis(200));
+        assert_eq!(queue.backoff(4), Duration::from_millis(800));
+        assert_eq!(queue.backoff(10), Duration::from_millis(1000));
+
+        let order = Message::new_topic("shop".into(), "orders.created".into(), vec![]);
+        let stats = Message::new_topic("shop".into(), "system.stats".into(), vec![]);
+        assert!(queue.is_durable(&order));
+        assert!(!queue.is_durable(&stats));
+        assert!(queue.is_durable(&stats.with_durable()));
+    }
+
+    #[tokio::test]
+    async fn test_nack_until_dead_letter() {
+        let queue = queue(DurableQueueConfig {
+            max_attempts: 2,
+            retry_initial_ms: 0,
+            ..Default::default()
+        })
+        .await;
+        let channels = PluginChannels::default();
+        let (tx, mut rx) = plugin_queue(PluginQueueConfig::default());
+        channels.write().insert("worker".to_string(), tx);
+
+        let message = Message::new("app".into(), "worker".into(), b"job".to_vec()).with_durable();
+        queue.enqueue(&message, "worker", true).await.unwrap();
+
+        // 第一次失败后重投
+        let outcome = queue.nack(&message.id, "worker", "boom").await.unwrap();
+        assert!(matches!(outcome, NackOutcome::Retry { .. }));
+        assert_eq!(queue.redeliver_due(&channels).await.unwrap(), 1);
+        let redelivered = rx.try_recv().unwrap();
+        assert_eq!(redelivered.id, message.id);
+        assert!(redelivered.durable);
+
+        // 第二次失败后移入死信表
+        let outcome = queue.nack(&message.id, "worker", "boom").await.unwrap();
+        assert_eq!(outcome, NackOutcome::DeadLettered);
+        let dead = queue.dead_letters(Some("worker"), 10).await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 2);
+        assert_eq!(dead[0].last_error.as_deref(), Some("boom"));
+
+        // 重放后再次投递，处理成功即删除
+        assert!(queue.replay_dead_letter(dead[0].id).await.unwrap());
+        assert_eq!(queue.redeliver_due(&channels).await.unwrap(), 1);
+        assert!(queue.ack(&message.id, "worker").await.unwrap());
+        assert_eq!(
+            queue.nack(&message.id, "worker", "late").await.unwrap(),
+            NackOutcome::Unknown
+        );
+    }
+}
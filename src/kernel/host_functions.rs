@@ -3,23 +3,38 @@
 //! 提供给插件调用的函数
 
 use crate::identity::IdentityManager;
-use crate::kernel::message::Message;
+use crate::kernel::message::{Message, PluginMessage, DEFAULT_CONTENT_TYPE};
 use crate::kernel::message_bus::MessageBusHandle;
+use crate::kernel::permissions::{
+    Capability, PermissionDenied, PermissionSet, PERMISSION_DENIED_CODE,
+};
+use crate::kernel::plugin_config::{ConfigSpec, INVALID_CONFIG_CODE};
+use crate::kernel::topic_index::validate_topic_pattern;
 use crate::log_collector;
 use crate::storage::Storage;
 use extism::*;
 use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing;
 
-/// 共享应用状态
+/// `request_host` 未指定超时时的默认等待时间（毫秒）
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 5000;
+
+/// 主机函数上下文（每个插件一份）
 #[derive(Clone)]
 pub struct HostContext {
     pub storage: Option<Arc<Storage>>,
     pub msg_sender: mpsc::Sender<Message>,
     pub identity: Option<Arc<IdentityManager>>,
     pub message_bus: Option<MessageBusHandle>,
+    /// 调用方插件名称
+    pub plugin_id: String,
+    /// 调用方插件被授予的能力
+    pub permissions: PermissionSet,
+    /// 调用方插件的配置默认值与校验规则
+    pub config: ConfigSpec,
 }
 
 impl HostContext {
@@ -34,7 +49,93 @@ impl HostContext {
             msg_sender,
             identity,
             message_bus,
+            plugin_id: String::new(),
+            permissions: PermissionSet::default(),
+            config: ConfigSpec::default(),
+        }
+    }
+
+    /// 基于共享资源创建指定插件的上下文
+    pub fn for_plugin(&self, plugin_id: &str, permissions: PermissionSet) -> Self {
+        Self {
+            plugin_id: plugin_id.to_string(),
+            permissions,
+            ..self.clone()
+        }
+    }
+}
+
+/// 向总线提交消息，主通道已满时最多等待总线配置的 `send_timeout`
+///
+/// 启用签名时先以发送方的派生密钥为消息签名
+fn submit_message(ctx: &HostContext, mut message: Message) -> Result<(), String> {
+    let signer = ctx.message_bus.as_ref().and_then(MessageBusHandle::signer);
+    if let Some(signer) = signer.filter(|signer| signer.should_sign(&message)) {
+        tokio::runtime::Handle::current().block_on(signer.sign(&mut message));
+    }
+
+    let message = match ctx.msg_sender.try_send(message) {
+        Ok(()) => return Ok(()),
+        Err(mpsc::error::TrySendError::Full(message)) => message,
+        Err(e) => return Err(e.to_string()),
+    };
+    let Some(message_bus) = &ctx.message_bus else {
+        return Err("message bus is full".to_string());
+    };
+
+    tokio::runtime::Handle::current()
+        .block_on(
+            ctx.msg_sender
+                .send_timeout(message, message_bus.send_timeout()),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// 构造权限拒绝响应（plugin-sdk 会映射为 `PluginError::Permission`）
+fn permission_denied(denied: PermissionDenied) -> String {
+    tracing::warn!("拒绝插件调用: {}", denied);
+
+    serde_json::json!({
+        "success": false,
+        "error": denied.to_string(),
+        "code": PERMISSION_DENIED_CODE,
+        "capability": denied.capability.to_string()
+    })
+    .to_string()
+}
+
+/// 解析存储、签名等调用的目标插件
+///
+/// 空值或与调用方一致时作用于调用方自身（需要 `own` 能力，None 表示无需权限），
+/// 指定其它插件时需要 `cross` 对应的跨插件能力
+fn resolve_target(
+    ctx: &HostContext,
+    plugin_id: &str,
+    own: Option<Capability>,
+    cross: fn(String) -> Capability,
+) -> Result<String, PermissionDenied> {
+    if plugin_id.is_empty() || plugin_id == ctx.plugin_id {
+        if let Some(own) = own {
+            ctx.permissions.check(&ctx.plugin_id, own)?;
         }
+        Ok(ctx.plugin_id.clone())
+    } else {
+        ctx.permissions
+            .check(&ctx.plugin_id, cross(plugin_id.to_string()))?;
+        Ok(plugin_id.to_string())
+    }
+}
+
+/// 解析总线调用的发送方身份（以其它插件身份需要 `bus.impersonate` 能力）
+fn resolve_sender(ctx: &HostContext, plugin_id: &str) -> Result<String, PermissionDenied> {
+    if plugin_id.is_empty() || plugin_id == ctx.plugin_id {
+        Ok(ctx.plugin_id.clone())
+    } else {
+        ctx.permissions.check(
+            &ctx.plugin_id,
+            Capability::BusImpersonate(plugin_id.to_string()),
+        )?;
+        Ok(plugin_id.to_string())
     }
 }
 
@@ -49,6 +150,15 @@ host_fn!(store_data(user_data: ContextStore; plugin_id: String, key: String, val
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(
+            &ctx,
+            &plugin_id,
+            Some(Capability::Storage),
+            Capability::StorageOf,
+        ) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
         if let Some(storage) = &ctx.storage {
             // 解析 JSON 值
             let json_value: serde_json::Value = serde_json::from_str(&value)?;
@@ -75,6 +185,15 @@ host_fn!(get_data(user_data: ContextStore; plugin_id: String, key: String) -> St
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(
+            &ctx,
+            &plugin_id,
+            Some(Capability::Storage),
+            Capability::StorageOf,
+        ) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
         if let Some(storage) = &ctx.storage {
             let runtime = tokio::runtime::Handle::current();
             let value = runtime.block_on(async {
@@ -103,6 +222,15 @@ host_fn!(delete_data(user_data: ContextStore; plugin_id: String, key: String) ->
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(
+            &ctx,
+            &plugin_id,
+            Some(Capability::Storage),
+            Capability::StorageOf,
+        ) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
         if let Some(storage) = &ctx.storage {
             let runtime = tokio::runtime::Handle::current();
             let deleted = runtime.block_on(async {
@@ -131,6 +259,15 @@ host_fn!(list_keys(user_data: ContextStore; plugin_id: String) -> String {
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(
+            &ctx,
+            &plugin_id,
+            Some(Capability::Storage),
+            Capability::StorageOf,
+        ) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
         if let Some(storage) = &ctx.storage {
             let runtime = tokio::runtime::Handle::current();
             let keys = runtime.block_on(async {
@@ -159,16 +296,167 @@ host_fn!(send_message(user_data: ContextStore; from: String, to: String, payload
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let from = match resolve_sender(&ctx, &from) {
+            Ok(from) => from,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        let requested = Capability::BusSend(to.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
+
+        // SDK 会提交完整的 PluginMessage，沿用其优先级、过期时间与元数据；
+        // 其它内容按原样作为负载
+        let msg = match serde_json::from_str::<PluginMessage>(&payload) {
+            Ok(envelope) => Message::from_plugin_message(from, to, envelope),
+            Err(_) => Message::new(from, to, payload.into_bytes()),
+        };
+        let msg_id = msg.id.clone();
+
+        submit_message(&ctx, msg)
+            .map_err(|e| extism::Error::msg(format!("Failed to send message: {e}")))?;
+
+        let result = serde_json::json!({
+            "success": true,
+            "data": msg_id
+        });
+
+        Ok(result.to_string())
+    } else {
+        Err(extism::Error::msg("Context not found"))
+    }
+});
+
+/// 按插件声明的内容类型设置消息类型（空值或默认类型不设置）
+fn with_content_type(message: Message, content_type: String) -> Message {
+    if content_type.is_empty() || content_type == DEFAULT_CONTENT_TYPE {
+        message
+    } else {
+        message.with_type(content_type)
+    }
+}
+
+// 以原始字节发送点对点消息，内容类型（如 application/msgpack）写入消息类型
+host_fn!(send_bytes(user_data: ContextStore; from: String, to: String, content_type: String, payload: Vec<u8>) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        let from = match resolve_sender(&ctx, &from) {
+            Ok(from) => from,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        let requested = Capability::BusSend(to.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
 
-        // 将 payload 转换为字节
-        let payload_bytes = payload.into_bytes();
-        let msg = Message::new(from, to, payload_bytes);
+        let msg = with_content_type(Message::new(from, to, payload), content_type);
         let msg_id = msg.id.clone();
 
-        ctx.msg_sender.try_send(msg)
+        submit_message(&ctx, msg)
             .map_err(|e| extism::Error::msg(format!("Failed to send message: {e}")))?;
 
-        Ok(msg_id)
+        let result = serde_json::json!({
+            "success": true,
+            "data": msg_id
+        });
+
+        Ok(result.to_string())
+    } else {
+        Err(extism::Error::msg("Context not found"))
+    }
+});
+
+// 请求/响应：阻塞等待目标插件的回复
+host_fn!(request(user_data: ContextStore; to: String, payload: String, timeout_ms: String) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    // 等待回复期间不持有上下文锁
+    let (from, message_bus) = if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        let requested = Capability::BusSend(to.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
+        (ctx.plugin_id.clone(), ctx.message_bus.clone())
+    } else {
+        return Err(extism::Error::msg("Context not found"));
+    };
+    drop(inner_store);
+    drop(store);
+
+    let Some(message_bus) = message_bus else {
+        return Err(extism::Error::msg("Message bus not initialized"));
+    };
+    let timeout_ms = match timeout_ms.trim() {
+        "" | "0" => DEFAULT_REQUEST_TIMEOUT_MS,
+        value => value
+            .parse()
+            .map_err(|e| extism::Error::msg(format!("Invalid timeout_ms: {e}")))?,
+    };
+
+    let runtime = tokio::runtime::Handle::current();
+    let result = runtime.block_on(message_bus.request(
+        &from,
+        &to,
+        payload.into_bytes(),
+        Duration::from_millis(timeout_ms),
+    ));
+
+    let result = match result {
+        Ok(reply) => serde_json::json!({
+            "success": true,
+            "data": String::from_utf8_lossy(&reply)
+        }),
+        Err(e) => {
+            tracing::debug!("插件 {} 的请求失败: {}", from, e);
+            serde_json::json!({
+                "success": false,
+                "error": e.to_string(),
+                "code": e.code()
+            })
+        }
+    };
+
+    Ok(result.to_string())
+});
+
+// 回复请求：正在等待回复的请求目标无需 bus.send 权限
+host_fn!(reply(user_data: ContextStore; to: String, correlation_id: String, payload: String) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        let awaited = ctx
+            .message_bus
+            .as_ref()
+            .is_some_and(|bus| bus.is_pending_request(&correlation_id, &ctx.plugin_id));
+        if !awaited {
+            let requested = Capability::BusSend(to.clone());
+            if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+                return Ok(permission_denied(denied));
+            }
+        }
+
+        let msg = Message::response(ctx.plugin_id.clone(), to, correlation_id, payload.into_bytes());
+        let msg_id = msg.id.clone();
+
+        submit_message(&ctx, msg)
+            .map_err(|e| extism::Error::msg(format!("Failed to send reply: {e}")))?;
+
+        let result = serde_json::json!({
+            "success": true,
+            "data": msg_id
+        });
+
+        Ok(result.to_string())
     } else {
         Err(extism::Error::msg("Context not found"))
     }
@@ -197,6 +485,15 @@ host_fn!(sign_message(user_data: ContextStore; plugin_id: String, message: Strin
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(
+            &ctx,
+            &plugin_id,
+            Some(Capability::IdentitySign),
+            Capability::IdentitySignAs,
+        ) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
         if let Some(identity) = &ctx.identity {
             let runtime = tokio::runtime::Handle::current();
             let signature = runtime.block_on(async {
@@ -285,17 +582,44 @@ host_fn!(subscribe_topic(user_data: ContextStore; plugin_id: String, topic: Stri
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_sender(&ctx, &plugin_id) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        if let Err(e) = validate_topic_pattern(&topic) {
+            let result = serde_json::json!({
+                "success": false,
+                "plugin_id": plugin_id,
+                "topic": topic,
+                "message": e.to_string()
+            });
+            return Ok(result.to_string());
+        }
+        let requested = Capability::BusSubscribe(topic.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
         if let Some(bus) = &ctx.message_bus {
-            let success = bus.subscribe_topic(&plugin_id, &topic);
+            // 重启后订阅会被恢复，重复订阅同一主题视为成功
+            let subscribed = bus.subscribe_topic(&plugin_id, &topic);
+
+            // 写入存储，插件下次加载时恢复
+            if let Some(storage) = &ctx.storage {
+                let runtime = tokio::runtime::Handle::current();
+                if let Err(e) = runtime.block_on(storage.add_subscription(&plugin_id, &topic)) {
+                    tracing::warn!("保存订阅失败: {} -> {} ({})", plugin_id, topic, e);
+                }
+            }
 
             let result = serde_json::json!({
-                "success": success,
+                "success": true,
                 "plugin_id": plugin_id,
                 "topic": topic,
-                "message": if success {
+                "already_subscribed": !subscribed,
+                "message": if subscribed {
                     "订阅成功"
                 } else {
-                    "订阅失败，可能已经订阅过此主题"
+                    "已订阅过此主题"
                 }
             });
 
@@ -315,8 +639,21 @@ host_fn!(unsubscribe_topic(user_data: ContextStore; plugin_id: String, topic: St
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_sender(&ctx, &plugin_id) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
         if let Some(bus) = &ctx.message_bus {
-            let success = bus.unsubscribe_topic(&plugin_id, &topic);
+            let mut success = bus.unsubscribe_topic(&plugin_id, &topic);
+
+            // 同时删除持久化的订阅
+            if let Some(storage) = &ctx.storage {
+                let runtime = tokio::runtime::Handle::current();
+                match runtime.block_on(storage.remove_subscription(&plugin_id, &topic)) {
+                    Ok(removed) => success |= removed,
+                    Err(e) => tracing::warn!("删除订阅失败: {} -> {} ({})", plugin_id, topic, e),
+                }
+            }
 
             let result = serde_json::json!({
                 "success": success,
@@ -338,6 +675,45 @@ host_fn!(unsubscribe_topic(user_data: ContextStore; plugin_id: String, topic: St
     }
 });
 
+// 读取保留消息（不订阅）
+host_fn!(get_retained(user_data: ContextStore; topic: String) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        if let Err(e) = validate_topic_pattern(&topic) {
+            let result = serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            });
+            return Ok(result.to_string());
+        }
+        let requested = Capability::BusSubscribe(topic.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
+        let Some(bus) = &ctx.message_bus else {
+            return Err(extism::Error::msg("Message bus not initialized"));
+        };
+
+        let messages: Vec<PluginMessage> = bus
+            .retained_messages(&topic)
+            .iter()
+            .map(PluginMessage::from)
+            .collect();
+        let result = serde_json::json!({
+            "success": true,
+            "data": messages
+        });
+
+        Ok(result.to_string())
+    } else {
+        Err(extism::Error::msg("Context not found"))
+    }
+});
+
 host_fn!(publish_message(user_data: ContextStore; plugin_id: String, topic: String, payload: String) -> String {
     let store = user_data.get()?;
     let store = store.lock().unwrap();
@@ -345,19 +721,68 @@ host_fn!(publish_message(user_data: ContextStore; plugin_id: String, topic: Stri
 
     if let Some(ctx_arc) = inner_store.get("context") {
         let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_sender(&ctx, &plugin_id) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        let requested = Capability::BusPublish(topic.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
 
-        // 创建主题消息
-        let payload_bytes = payload.into_bytes();
-        let msg = Message::new_topic(plugin_id.clone(), topic.clone(), payload_bytes);
+        // SDK 的 publish_message 提交完整的 PluginMessage，沿用其优先级、过期时间与元数据；
+        // 其它内容按原样作为负载
+        let mut msg = Message::new_topic(plugin_id.clone(), topic.clone(), Vec::new());
+        match serde_json::from_str::<PluginMessage>(&payload) {
+            Ok(envelope) => msg.apply_plugin_message(envelope),
+            Err(_) => msg.payload = payload.into_bytes(),
+        }
         let msg_id = msg.id.clone();
 
         // 发送消息
-        ctx.msg_sender.try_send(msg)
+        submit_message(&ctx, msg)
+            .map_err(|e| extism::Error::msg(format!("Failed to send topic message: {e}")))?;
+
+        let result = serde_json::json!({
+            "success": true,
+            "data": msg_id
+        });
+
+        Ok(result.to_string())
+    } else {
+        Err(extism::Error::msg("Context not found"))
+    }
+});
+
+// 以原始字节发布主题消息，内容类型写入消息类型
+host_fn!(publish_bytes(user_data: ContextStore; plugin_id: String, topic: String, content_type: String, payload: Vec<u8>) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_sender(&ctx, &plugin_id) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        let requested = Capability::BusPublish(topic.clone());
+        if let Err(denied) = ctx.permissions.check(&ctx.plugin_id, requested) {
+            return Ok(permission_denied(denied));
+        }
+
+        let msg = with_content_type(
+            Message::new_topic(plugin_id.clone(), topic.clone(), payload),
+            content_type,
+        );
+        let msg_id = msg.id.clone();
+
+        submit_message(&ctx, msg)
             .map_err(|e| extism::Error::msg(format!("Failed to send topic message: {e}")))?;
 
         let result = serde_json::json!({
             "success": true,
-            "message_id": msg_id,
+            "data": msg_id,
             "topic": topic,
             "from": plugin_id
         });
@@ -368,6 +793,89 @@ host_fn!(publish_message(user_data: ContextStore; plugin_id: String, topic: Stri
     }
 });
 
+// 插件配置相关主机函数
+host_fn!(get_config(user_data: ContextStore; plugin_id: String) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(&ctx, &plugin_id, None, Capability::ConfigOf) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        if let Some(storage) = &ctx.storage {
+            let runtime = tokio::runtime::Handle::current();
+            let stored = runtime.block_on(async {
+                storage.get_plugin_config(&plugin_id).await
+            })?;
+
+            // 只有调用方自身的默认值在上下文中，其它插件返回已保存的配置
+            let config = if plugin_id == ctx.plugin_id {
+                ctx.config.resolve(stored)
+            } else {
+                stored.unwrap_or_default()
+            };
+
+            let result = serde_json::json!({
+                "success": true,
+                "data": config
+            });
+
+            Ok(result.to_string())
+        } else {
+            Err(extism::Error::msg("Storage not initialized"))
+        }
+    } else {
+        Err(extism::Error::msg("Context not found"))
+    }
+});
+
+host_fn!(set_config(user_data: ContextStore; plugin_id: String, config: String) -> String {
+    let store = user_data.get()?;
+    let store = store.lock().unwrap();
+    let inner_store = store.lock().unwrap();
+
+    if let Some(ctx_arc) = inner_store.get("context") {
+        let ctx = ctx_arc.lock().unwrap();
+        let plugin_id = match resolve_target(&ctx, &plugin_id, None, Capability::ConfigOf) {
+            Ok(plugin_id) => plugin_id,
+            Err(denied) => return Ok(permission_denied(denied)),
+        };
+        if let Some(storage) = &ctx.storage {
+            let config: serde_json::Value = serde_json::from_str(&config)?;
+
+            // 按调用方声明的 schema 校验合并默认值后的配置
+            if plugin_id == ctx.plugin_id {
+                if let Err(e) = ctx.config.validate(&ctx.config.resolve(Some(config.clone()))) {
+                    let result = serde_json::json!({
+                        "success": false,
+                        "error": e.to_string(),
+                        "code": INVALID_CONFIG_CODE
+                    });
+                    return Ok(result.to_string());
+                }
+            }
+
+            let runtime = tokio::runtime::Handle::current();
+            runtime.block_on(async {
+                storage.set_plugin_config(&plugin_id, &config).await
+            })?;
+
+            let result = serde_json::json!({
+                "success": true
+            });
+
+            Ok(result.to_string())
+        } else {
+            Err(extism::Error::msg("Storage not initialized"))
+        }
+    } else {
+        Err(extism::Error::msg("Context not found"))
+    }
+});
+
 // 时间相关主机函数 - 不需要用户数据
 host_fn!(get_timestamp_host() -> String {
     let timestamp = std::time::SystemTime::now()
@@ -403,21 +911,21 @@ pub fn build_plugin_with_host_functions(
         .with_wasi(true)
         .with_function(
             "store_data_host",
-            [PTR],
+            [PTR, PTR, PTR],
             [PTR],
             context_store.clone(),
             store_data,
         )
         .with_function(
             "get_data_host",
-            [PTR],
+            [PTR, PTR],
             [PTR],
             context_store.clone(),
             get_data,
         )
         .with_function(
             "delete_data_host",
-            [PTR],
+            [PTR, PTR],
             [PTR],
             context_store.clone(),
             delete_data,
@@ -431,28 +939,49 @@ pub fn build_plugin_with_host_functions(
         )
         .with_function(
             "send_message_host",
-            [PTR],
+            [PTR, PTR, PTR],
             [PTR],
             context_store.clone(),
             send_message,
         )
         .with_function(
-            "log_message_host",
+            "send_bytes_host",
+            [PTR, PTR, PTR, PTR],
             [PTR],
+            context_store.clone(),
+            send_bytes,
+        )
+        .with_function(
+            "request_host",
+            [PTR, PTR, PTR],
+            [PTR],
+            context_store.clone(),
+            request,
+        )
+        .with_function(
+            "reply_host",
+            [PTR, PTR, PTR],
+            [PTR],
+            context_store.clone(),
+            reply,
+        )
+        .with_function(
+            "log_message_host",
+            [PTR, PTR],
             [PTR],
             UserData::new(()),
             log_message,
         )
         .with_function(
             "sign_message_host",
-            [PTR],
+            [PTR, PTR],
             [PTR],
             context_store.clone(),
             sign_message,
         )
         .with_function(
             "verify_signature_host",
-            [PTR],
+            [PTR, PTR, PTR],
             [PTR],
             context_store.clone(),
             verify_signature,
@@ -466,25 +995,53 @@ pub fn build_plugin_with_host_functions(
         )
         .with_function(
             "subscribe_topic_host",
-            [PTR],
+            [PTR, PTR],
             [PTR],
             context_store.clone(),
             subscribe_topic,
         )
         .with_function(
             "unsubscribe_topic_host",
-            [PTR],
+            [PTR, PTR],
             [PTR],
             context_store.clone(),
             unsubscribe_topic,
         )
         .with_function(
             "publish_message_host",
-            [PTR],
+            [PTR, PTR, PTR],
             [PTR],
             context_store.clone(),
             publish_message,
         )
+        .with_function(
+            "publish_bytes_host",
+            [PTR, PTR, PTR, PTR],
+            [PTR],
+            context_store.clone(),
+            publish_bytes,
+        )
+        .with_function(
+            "get_retained_host",
+            [PTR],
+            [PTR],
+            context_store.clone(),
+            get_retained,
+        )
+        .with_function(
+            "get_config_host",
+            [PTR],
+            [PTR],
+            context_store.clone(),
+            get_config,
+        )
+        .with_function(
+            "set_config_host",
+            [PTR, PTR],
+            [PTR],
+            context_store.clone(),
+            set_config,
+        )
         .with_function(
             "get_timestamp_host",
             [],
@@ -0,0 +1,120 @@
+//! 插件热重载辅助
+//!
+//! 识别插件目录中哪些文件变更需要重载插件，并把连续的文件事件合并为一批，
+//! 避免一次构建或安装触发多次重载
+
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 插件源码目录中的构建输出目录
+pub const RELEASE_DIR: &str = "target/wasm32-unknown-unknown/release";
+
+/// 文件是否是插件目录中的插件：`<dir>/<name>.wasm`、`<dir>/<plugin>/<name>.wasm`
+/// 或 `<dir>/<plugin>/target/wasm32-unknown-unknown/release/<name>.wasm`
+///
+/// 其它构建输出以及以 `.` 开头的目录（如插件包安装时的临时目录）被忽略
+pub fn is_plugin_file(plugin_dir: &Path, path: &Path) -> bool {
+    if path.extension().is_none_or(|ext| ext != "wasm") {
+        return false;
+    }
+
+    let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let (plugin_dir, path) = (canonical(plugin_dir), canonical(path));
+    let Ok(relative) = path.strip_prefix(&plugin_dir) else {
+        return false;
+    };
+    let parts: Option<Vec<&str>> = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    let Some(parts) = parts else {
+        return false;
+    };
+    if parts.iter().any(|part| part.starts_with('.')) {
+        return false;
+    }
+
+    let release: Vec<&str> = RELEASE_DIR.split('/').collect();
+    match parts.len() {
+        1 | 2 => true,
+        len => len == release.len() + 2 && parts[1..len - 1] == release[..],
+    }
+}
+
+/// 等待下一批文件变更：收到第一个事件后继续收集，直到 `quiet` 时间内没有新事件
+///
+/// 通道关闭且没有未处理的变更时返回 None
+pub async fn next_batch(
+    rx: &mut mpsc::UnboundedReceiver<Vec<PathBuf>>,
+    quiet: Duration,
+) -> Option<Vec<PathBuf>> {
+    let mut changed: BTreeSet<PathBuf> = rx.recv().await?.into_iter().collect();
+    while let Ok(Some(paths)) = tokio::time::timeout(quiet, rx.recv()).await {
+        changed.extend(paths);
+    }
+    Some(changed.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_plugin_file() {
+        let dir = Path::new("/plugins");
+        for path in [
+            "/plugins/echo.wasm",
+            "/plugins/echo/echo.wasm",
+            "/plugins/echo/target/wasm32-unknown-unknown/release/echo.wasm",
+        ] {
+            assert!(is_plugin_file(dir, Path::new(path)), "{path}");
+        }
+        for path in [
+            "/plugins/echo/manifest.toml",
+            "/plugins/echo/target/wasm32-unknown-unknown/release/deps/echo.wasm",
+            "/plugins/echo/target/debug/echo.wasm",
+            "/plugins/.echo.installing/echo.wasm",
+            "/plugins/echo/src/nested/echo.wasm",
+            "/elsewhere/echo.wasm",
+        ] {
+            assert!(!is_plugin_file(dir, Path::new(path)), "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_next_batch_merges_events_until_quiet() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let quiet = Duration::from_millis(100);
+
+        tx.send(vec![PathBuf::from("b.wasm")]).unwrap();
+        let sender = {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                for path in ["a.wasm", "b.wasm"] {
+                    tokio::time::sleep(Duration::from_millis(30)).await;
+                    tx.send(vec![PathBuf::from(path)]).unwrap();
+                }
+            })
+        };
+
+        // 间隔短于静默时间的事件合并为一批并去重
+        let batch = next_batch(&mut rx, quiet).await.unwrap();
+        assert_eq!(
+            batch,
+            vec![PathBuf::from("a.wasm"), PathBuf::from("b.wasm")]
+        );
+        sender.await.unwrap();
+
+        // 静默之后的事件属于下一批，通道关闭后结束
+        tx.send(vec![PathBuf::from("c.wasm")]).unwrap();
+        drop(tx);
+        let batch = next_batch(&mut rx, quiet).await.unwrap();
+        assert_eq!(batch, vec![PathBuf::from("c.wasm")]);
+        assert!(next_batch(&mut rx, quiet).await.is_none());
+    }
+}
@@ -0,0 +1,456 @@
+//! 消息拦截器
+//!
+//! 拦截器在路由前拿到每条消息，可以放行、修改或拒绝，路由完成后收到投递结果，
+//! 用于校验负载、脱敏、限流、审计等横切逻辑。内核构造时注册在路由器上的拦截器先执行，
+//! 之后是持有 `bus.intercept` 权限并导出 `intercept_message` 函数的插件（按加载顺序），
+//! 最后由验证器（签名验证）检查其它拦截器修改后的最终消息
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+use super::lifecycle::PluginState;
+use super::message::{Message, MessageResult, PluginMessage};
+use super::message_bus::MessageBusHandle;
+use super::plugin_loader::{check_fault, convert_call_error, FaultMap, SharedPlugin};
+
+/// 插件拦截消息的导出函数名
+pub const INTERCEPT_FN: &str = "intercept_message";
+
+/// 拦截器返回的异步结果
+pub type InterceptFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 拦截器对消息的处理决定
+#[derive(Debug)]
+pub enum Interception {
+    /// 原样放行
+    Pass,
+    /// 用修改后的消息继续路由
+    Modify(Box<Message>),
+    /// 拒绝消息（附原因），消息不再路由
+    Reject(String),
+}
+
+/// 消息拦截器
+pub trait MessageInterceptor: Send + Sync {
+    /// 拦截器名称（用于日志与移除）
+    fn name(&self) -> &str;
+
+    /// 路由前处理消息
+    fn before_route<'a>(&'a self, message: &'a Message) -> InterceptFuture<'a, Interception>;
+
+    /// 路由完成后收到消息及其投递结果（默认忽略）
+    fn after_delivery<'a>(
+        &'a self,
+        message: &'a Message,
+        result: &'a MessageResult,
+    ) -> InterceptFuture<'a, ()> {
+        let _ = (message, result);
+        Box::pin(async {})
+    }
+}
+
+/// 被拦截器拒绝的消息
+#[derive(Debug)]
+pub struct Rejected {
+    /// 拒绝消息的拦截器
+    pub interceptor: String,
+    /// 拒绝原因
+    pub reason: String,
+    /// 被拒绝的消息（已经过之前拦截器的修改）
+    pub message: Message,
+}
+
+/// 拦截器链（可克隆，由 Handle 与 Router 共享）
+#[derive(Clone, Default)]
+pub struct InterceptorChain {
+    interceptors: Arc<RwLock<Vec<Arc<dyn MessageInterceptor>>>>,
+    /// 在所有拦截器之后执行的验证器
+    verifier: Arc<RwLock<Option<Arc<dyn MessageInterceptor>>>>,
+}
+
+impl InterceptorChain {
+    /// 在链尾添加拦截器（同名拦截器原位替换）
+    pub fn add(&self, interceptor: Arc<dyn MessageInterceptor>) {
+        let mut interceptors = self.interceptors.write();
+        match interceptors
+            .iter_mut()
+            .find(|existing| existing.name() == interceptor.name())
+        {
+            Some(existing) => *existing = interceptor,
+            None => interceptors.push(interceptor),
+        }
+    }
+
+    /// 设置验证器：始终在其它拦截器之后执行，检查修改后的最终消息
+    pub fn set_verifier(&self, verifier: Arc<dyn MessageInterceptor>) {
+        *self.verifier.write() = Some(verifier);
+    }
+
+    /// 移除拦截器
+    pub fn remove(&self, name: &str) -> bool {
+        let mut interceptors = self.interceptors.write();
+        let before = interceptors.len();
+        interceptors.retain(|interceptor| interceptor.name() != name);
+        interceptors.len() != before
+    }
+
+    /// 按执行顺序列出拦截器名称
+    pub fn names(&self) -> Vec<String> {
+        self.ordered()
+            .iter()
+            .map(|interceptor| interceptor.name().to_string())
+            .collect()
+    }
+
+    /// 是否没有拦截器
+    pub fn is_empty(&self) -> bool {
+        self.interceptors.read().is_empty() && self.verifier.read().is_none()
+    }
+
+    /// 按执行顺序排列的拦截器（验证器在最后）
+    fn ordered(&self) -> Vec<Arc<dyn MessageInterceptor>> {
+        let mut interceptors = self.interceptors.read().clone();
+        interceptors.extend(self.verifier.read().clone());
+        interceptors
+    }
+
+    /// 依次交给每个拦截器处理，任一拦截器拒绝时停止
+    pub async fn before_route(&self, mut message: Message) -> Result<Message, Rejected> {
+        let interceptors = self.ordered();
+        for interceptor in interceptors {
+            match interceptor.before_route(&message).await {
+                Interception::Pass => {}
+                Interception::Modify(modified) => message = *modified,
+                Interception::Reject(reason) => {
+                    return Err(Rejected {
+                        interceptor: interceptor.name().to_string(),
+                        reason,
+                        message,
+                    })
+                }
+            }
+        }
+        Ok(message)
+    }
+
+    /// 通知每个拦截器消息的投递结果
+    pub async fn after_delivery(&self, message: &Message, result: &MessageResult) {
+        let interceptors = self.ordered();
+        for interceptor in interceptors {
+            interceptor.after_delivery(message, result).await;
+        }
+    }
+}
+
+/// 拦截阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InterceptStage {
+    BeforeRoute,
+    AfterDelivery,
+}
+
+/// 传给插件 `intercept_message` 的输入
+#[derive(Debug, Serialize)]
+struct InterceptRequest {
+    stage: InterceptStage,
+    message: PluginMessage,
+    /// 投递结果（仅 after_delivery）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'static str>,
+}
+
+/// 插件 `intercept_message` 在 before_route 阶段的返回值
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum PluginVerdict {
+    Pass,
+    Modify { message: Box<PluginMessage> },
+    Reject { reason: String },
+}
+
+/// 投递结果的名称
+fn result_name(result: &MessageResult) -> &'static str {
+    match result {
+        MessageResult::Success => "delivered",
+        MessageResult::Queued => "queued",
+        MessageResult::PluginNotFound(_) => "not_found",
+        MessageResult::Expired => "expired",
+        MessageResult::Rejected(_) => "rejected",
+        MessageResult::Failed(_) => "failed",
+    }
+}
+
+/// 每个插件拦截器排队等待通知的投递结果上限，超出时丢弃
+const AFTER_DELIVERY_QUEUE: usize = 256;
+
+/// 调用插件的 intercept_message 函数
+#[derive(Clone)]
+struct InterceptCaller {
+    plugin_id: String,
+    plugin: SharedPlugin,
+    faults: FaultMap,
+    timeout_ms: u64,
+}
+
+impl InterceptCaller {
+    async fn call(&self, request: InterceptRequest) -> anyhow::Result<String> {
+        check_fault(&self.faults, &self.plugin_id)?;
+        let input = serde_json::to_string(&request)?;
+
+        let plugin = self.plugin.clone();
+        let faults = self.faults.clone();
+        let plugin_id = self.plugin_id.clone();
+        let timeout_ms = self.timeout_ms;
+        let call = tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
+            let mut plugin = plugin.lock().map_err(|_| anyhow::anyhow!("插件锁已中毒"))?;
+            let started = Instant::now();
+            plugin
+                .call::<&str, String>(INTERCEPT_FN, &input)
+                .map_err(|e| {
+                    let elapsed = started.elapsed();
+                    convert_call_error(&faults, &plugin_id, INTERCEPT_FN, timeout_ms, elapsed, e)
+                })
+        });
+
+        // 插件正忙于其它调用时不无限等待
+        let joined = if timeout_ms > 0 {
+            tokio::time::timeout(Duration::from_millis(timeout_ms), call)
+                .await
+                .map_err(|_| anyhow::anyhow!("等待插件超时（{timeout_ms}ms）"))?
+        } else {
+            call.await
+        };
+        joined?
+    }
+}
+
+/// 以插件作为拦截器
+///
+/// 插件只能修改消息内容（负载、类型、优先级、过期时间与元数据），不能改变收发方与主题。
+/// 插件自己发出的消息以及发给它的请求回复不经过它，插件未运行时放行消息；
+/// 调用失败、超时或返回值无效时拒绝消息，避免出错的校验或脱敏拦截器放过所有消息。
+/// 投递结果由每个拦截器一个后台任务按顺序通知，队列已满时丢弃。
+///
+/// 拦截器使用插件的独立实例，路由器不会等待插件处理消息或定时任务
+pub(super) struct PluginInterceptor {
+    name: String,
+    caller: InterceptCaller,
+    state: watch::Receiver<PluginState>,
+    /// 用于识别发给插件的请求回复
+    bus: MessageBusHandle,
+    /// 投递结果通知队列（无 Tokio 运行时时不通知）
+    after_delivery: Option<mpsc::Sender<InterceptRequest>>,
+}
+
+impl PluginInterceptor {
+    pub(super) fn new(
+        plugin_id: &str,
+        plugin: SharedPlugin,
+        state: watch::Receiver<PluginState>,
+        bus: MessageBusHandle,
+        faults: FaultMap,
+        timeout_ms: u64,
+    ) -> Self {
+        let caller = InterceptCaller {
+            plugin_id: plugin_id.to_string(),
+            plugin,
+            faults,
+            timeout_ms,
+        };
+
+        // 拦截器从链中移除（发送端被释放）后后台任务随之结束
+        let after_delivery = tokio::runtime::Handle::try_current().ok().map(|runtime| {
+            let (tx, mut rx) = mpsc::channel::<InterceptRequest>(AFTER_DELIVERY_QUEUE);
+            let caller = caller.clone();
+            runtime.spawn(async move {
+                while let Some(request) = rx.recv().await {
+                    if let Err(e) = caller.call(request).await {
+                        tracing::debug!("拦截器插件 {} 处理投递结果失败: {}", caller.plugin_id, e);
+                    }
+                }
+            });
+            tx
+        });
+
+        Self {
+            name: Self::name_for(plugin_id),
+            caller,
+            state,
+            bus,
+            after_delivery,
+        }
+    }
+
+    /// 插件拦截器在链中的名称
+    pub(super) fn name_for(plugin_id: &str) -> String {
+        format!("plugin:{plugin_id}")
+    }
+
+    fn applies_to(&self, message: &Message) -> bool {
+        message.from != self.caller.plugin_id
+            && !self.is_reply_to_plugin(message)
+            && *self.state.borrow() == PluginState::Running
+    }
+
+    /// 是否是插件发出的请求的回复（包括目标的投递错误回报）
+    fn is_reply_to_plugin(&self, message: &Message) -> bool {
+        message.to == self.caller.plugin_id
+            && !message.is_request()
+            && message
+                .correlation_id
+                .as_deref()
+                .is_some_and(|correlation_id| {
+                    self.bus.is_pending_request(correlation_id, &message.from)
+                })
+    }
+}
+
+impl MessageInterceptor for PluginInterceptor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn before_route<'a>(&'a self, message: &'a Message) -> InterceptFuture<'a, Interception> {
+        Box::pin(async move {
+            if !self.applies_to(message) {
+                return Interception::Pass;
+            }
+
+            let request = InterceptRequest {
+                stage: InterceptStage::BeforeRoute,
+                message: PluginMessage::from(message),
+                result: None,
+            };
+            let output = match self.caller.call(request).await {
+                Ok(output) => output,
+                Err(e) => {
+                    tracing::warn!(
+                        "拦截器插件 {} 调用失败，拒绝消息: {}",
+                        self.caller.plugin_id,
+                        e
+                    );
+                    return Interception::Reject(format!("拦截器调用失败: {e}"));
+                }
+            };
+
+            match serde_json::from_str::<PluginVerdict>(&output) {
+                Ok(PluginVerdict::Pass) => Interception::Pass,
+                Ok(PluginVerdict::Modify { message: envelope }) => {
+                    let mut modified = message.clone();
+                    modified.apply_plugin_message(*envelope);
+                    Interception::Modify(Box::new(modified))
+                }
+                Ok(PluginVerdict::Reject { reason }) => Interception::Reject(reason),
+                Err(e) => {
+                    tracing::warn!(
+                        "拦截器插件 {} 的返回值无效，拒绝消息: {}",
+                        self.caller.plugin_id,
+                        e
+                    );
+                    Interception::Reject(format!("拦截器返回值无效: {e}"))
+                }
+            }
+        })
+    }
+
+    fn after_delivery<'a>(
+        &'a self,
+        message: &'a Message,
+        result: &'a MessageResult,
+    ) -> InterceptFuture<'a, ()> {
+        Box::pin(async move {
+            if !self.applies_to(message) {
+                return;
+            }
+
+            let Some(queue) = &self.after_delivery else {
+                return;
+            };
+
+            // 交给后台任务通知插件，不阻塞路由
+            let request = InterceptRequest {
+                stage: InterceptStage::AfterDelivery,
+                message: PluginMessage::from(message),
+                result: Some(result_name(result)),
+            };
+            if queue.try_send(request).is_err() {
+                tracing::debug!(
+                    "拦截器插件 {} 的投递结果队列已满，丢弃消息 {} 的通知",
+                    self.caller.plugin_id,
+                    message.id
+                );
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用拦截器：按负载内容放行、修改或拒绝
+    struct Redactor;
+
+    impl MessageInterceptor for Redactor {
+        fn name(&self) -> &str {
+            "redactor"
+        }
+
+        fn before_route<'a>(&'a self, message: &'a Message) -> InterceptFuture<'a, Interception> {
+            Box::pin(async move {
+                match message.payload.as_slice() {
+                    b"secret" => {
+                        let mut redacted = message.clone();
+                        redacted.payload = b"***".to_vec();
+                        Interception::Modify(Box::new(redacted))
+                    }
+                    b"spam" => Interception::Reject("spam".to_string()),
+                    _ => Interception::Pass,
+                }
+            })
+        }
+    }
+
+    fn message(payload: &str) -> Message {
+        Message::new("a".into(), "b".into(), payload.as_bytes().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_chain_passes_modifies_and_rejects() {
+        let chain = InterceptorChain::default();
+        assert!(chain.is_empty());
+        chain.add(Arc::new(Redactor));
+        chain.add(Arc::new(Redactor));
+        assert_eq!(chain.names(), vec!["redactor"]);
+
+        let passed = chain.before_route(message("hello")).await.unwrap();
+        assert_eq!(passed.payload, b"hello");
+
+        let redacted = chain.before_route(message("secret")).await.unwrap();
+        assert_eq!(redacted.payload, b"***");
+
+        let rejected = chain.before_route(message("spam")).await.unwrap_err();
+        assert_eq!(
+            (rejected.interceptor.as_str(), rejected.reason.as_str()),
+            ("redactor", "spam")
+        );
+
+        assert!(chain.remove("redactor"));
+        assert!(chain.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_verdict_format() {
+        let verdict: PluginVerdict =
+            serde_json::from_str(r#"{"action":"reject","reason":"bad schema"}"#).unwrap();
+        assert!(matches!(verdict, PluginVerdict::Reject { reason } if reason == "bad schema"));
+        let verdict: PluginVerdict = serde_json::from_str(r#"{"action":"pass"}"#).unwrap();
+        assert!(matches!(verdict, PluginVerdict::Pass));
+    }
+}
@@ -0,0 +1,183 @@
+//! 消息日志
+//!
+//! 路由器把每条消息及其投递结果交给后台任务写入 message_log 表，
+//! 写入不阻塞路由；队列已满时丢弃日志记录而不是拖慢总线。
+//! 后台任务同时按保留策略定期清理旧记录
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::message::Message;
+use crate::config::JournalConfig;
+use crate::storage::Storage;
+
+/// 等待写入的日志事件上限
+const JOURNAL_QUEUE_SIZE: usize = 1024;
+
+/// 消息投递状态（对应 message_log.status）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// 已进入路由
+    Sent,
+    /// 已投递到接收方的通道
+    Delivered,
+    /// 已持久化，等待投递
+    Queued,
+    /// 已过期，未投递
+    Expired,
+    /// 主题没有订阅者
+    DroppedNoSubscriber,
+    /// 被拦截器拒绝
+    Rejected,
+    /// 投递失败
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// 状态名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Expired => "expired",
+            DeliveryStatus::DroppedNoSubscriber => "dropped_no_subscriber",
+            DeliveryStatus::Rejected => "rejected",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// 日志事件
+enum JournalEvent {
+    /// 消息进入路由
+    Routed(Box<Message>),
+    /// 消息路由完成
+    Status {
+        message_id: String,
+        status: DeliveryStatus,
+        error: Option<String>,
+    },
+}
+
+/// 消息日志句柄（可克隆）
+#[derive(Clone)]
+pub struct MessageJournal {
+    tx: mpsc::Sender<JournalEvent>,
+    /// 因队列已满而丢弃的事件数
+    dropped: Arc<AtomicU64>,
+}
+
+impl MessageJournal {
+    /// 启动后台写入任务
+    pub fn spawn(storage: Arc<Storage>, config: &JournalConfig) -> Self {
+        let (tx, rx) = mpsc::channel(JOURNAL_QUEUE_SIZE);
+        tokio::spawn(run_journal(storage, config.clone(), rx));
+
+        Self {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 记录进入路由的消息
+    pub fn record(&self, message: &Message) {
+        self.push(JournalEvent::Routed(Box::new(message.clone())));
+    }
+
+    /// 记录消息的投递结果
+    pub fn update(&self, message_id: &str, status: DeliveryStatus, error: Option<String>) {
+        self.push(JournalEvent::Status {
+            message_id: message_id.to_string(),
+            status,
+            error,
+        });
+    }
+
+    /// 因队列已满而丢弃的日志事件数
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn push(&self, event: JournalEvent) {
+        if self.tx.try_send(event).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                tracing::warn!("消息日志队列已满，已丢弃 {} 条记录", dropped);
+            }
+        }
+    }
+}
+
+/// 写入日志事件并定期清理，所有句柄释放后结束
+async fn run_journal(
+    storage: Arc<Storage>,
+    config: JournalConfig,
+    mut rx: mpsc::Receiver<JournalEvent>,
+) {
+    tracing::info!("消息日志已启用");
+
+    let mut prune = tokio::time::interval(Duration::from_secs(config.prune_interval_secs));
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else {
+                    break;
+                };
+                if let Err(e) = write_event(&storage, &config, event).await {
+                    tracing::warn!("写入消息日志失败: {}", e);
+                }
+            }
+            _ = prune.tick() => {
+                match storage.prune_message_log(config.max_age_secs, config.max_entries).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::debug!("已清理 {} 条消息日志", deleted),
+                    Err(e) => tracing::warn!("清理消息日志失败: {}", e),
+                }
+            }
+        }
+    }
+
+    tracing::debug!("消息日志任务已结束");
+}
+
+async fn write_event(
+    storage: &Storage,
+    config: &JournalConfig,
+    event: JournalEvent,
+) -> anyhow::Result<()> {
+    match event {
+        JournalEvent::Routed(message) => {
+            let payload = config.record_payloads.then_some(message.payload.as_slice());
+            storage
+                .log_message(
+                    &message.id,
+                    &message.from,
+                    &message.to,
+                    payload,
+                    message.msg_type.as_deref(),
+                    message.topic.as_deref(),
+                )
+                .await?;
+            match &message.signature {
+                Some(signature) => {
+                    storage
+                        .set_message_signature(&message.id, signature, message.verified_from)
+                        .await
+                }
+                None => Ok(()),
+            }
+        }
+        JournalEvent::Status {
+            message_id,
+            status,
+            error,
+        } => {
+            storage
+                .update_message_status(&message_id, status.as_str(), error.as_deref())
+                .await
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! 插件生命周期
+//!
+//! 加载 -> 运行 <-> 暂停 -> 关闭，任一阶段失败进入错误状态
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 插件导出的生命周期函数
+pub const INITIALIZE_FN: &str = "initialize";
+pub const SHUTDOWN_FN: &str = "shutdown";
+pub const PAUSE_FN: &str = "pause";
+pub const RESUME_FN: &str = "resume";
+
+/// 热重载时交接状态的导出函数（可选）
+pub const EXPORT_STATE_FN: &str = "export_state";
+pub const IMPORT_STATE_FN: &str = "import_state";
+
+/// 插件生命周期状态（持久化在 `plugin_metadata.state`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginState {
+    /// 已实例化，尚未初始化
+    Loaded,
+    /// 运行中，接收消息与定时任务
+    Running,
+    /// 已暂停，消息保留到恢复后投递
+    Paused,
+    /// 已关闭
+    Shutdown,
+    /// 初始化或运行失败
+    Error,
+}
+
+impl PluginState {
+    /// 状态名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginState::Loaded => "loaded",
+            PluginState::Running => "running",
+            PluginState::Paused => "paused",
+            PluginState::Shutdown => "shutdown",
+            PluginState::Error => "error",
+        }
+    }
+
+    /// 是否应暂缓投递消息（尚未初始化或已暂停）
+    pub fn holds_messages(&self) -> bool {
+        matches!(self, PluginState::Loaded | PluginState::Paused)
+    }
+
+    /// 是否处于活动状态（需要在卸载时调用 shutdown）
+    pub fn is_active(&self) -> bool {
+        matches!(self, PluginState::Running | PluginState::Paused)
+    }
+
+    /// 检查状态转换是否合法
+    pub fn can_transition_to(&self, next: PluginState) -> bool {
+        use PluginState::*;
+
+        matches!(
+            (self, next),
+            (Loaded, Running | Error | Shutdown)
+                | (Running, Paused | Error | Shutdown)
+                | (Paused, Running | Error | Shutdown)
+                | (Error, Shutdown)
+        )
+    }
+}
+
+impl fmt::Display for PluginState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PluginState {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "loaded" => Ok(PluginState::Loaded),
+            "running" => Ok(PluginState::Running),
+            "paused" => Ok(PluginState::Paused),
+            "shutdown" => Ok(PluginState::Shutdown),
+            "error" => Ok(PluginState::Error),
+            _ => Err(anyhow!("未知的插件状态: {}", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        use PluginState::*;
+
+        assert!(Loaded.can_transition_to(Running));
+        assert!(Running.can_transition_to(Paused));
+        assert!(Paused.can_transition_to(Running));
+        assert!(Paused.can_transition_to(Shutdown));
+        assert!(Error.can_transition_to(Shutdown));
+
+        assert!(!Loaded.can_transition_to(Paused));
+        assert!(!Running.can_transition_to(Loaded));
+        assert!(!Shutdown.can_transition_to(Running));
+        assert!(!Error.can_transition_to(Running));
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        for state in [
+            PluginState::Loaded,
+            PluginState::Running,
+            PluginState::Paused,
+            PluginState::Shutdown,
+            PluginState::Error,
+        ] {
+            assert_eq!(state.as_str().parse::<PluginState>().unwrap(), state);
+        }
+        assert!("stopped".parse::<PluginState>().is_err());
+    }
+}
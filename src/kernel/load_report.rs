@@ -0,0 +1,67 @@
+//! 插件加载报告
+//!
+//! 按依赖顺序加载插件后，记录哪些插件已加载、哪些因依赖未满足而跳过、哪些加载失败
+
+use serde::{Deserialize, Serialize};
+
+/// 未加载的插件及原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadIssue {
+    /// 插件名称
+    pub plugin: String,
+    /// 原因
+    pub reason: String,
+}
+
+/// 插件加载报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// 已加载的插件（按加载顺序）
+    pub loaded: Vec<String>,
+    /// 因必需依赖未加载而跳过的插件
+    pub skipped: Vec<LoadIssue>,
+    /// 依赖解析或加载失败的插件
+    pub failed: Vec<LoadIssue>,
+}
+
+impl LoadReport {
+    /// 记录跳过的插件
+    pub fn skip(&mut self, plugin: &str, reason: impl Into<String>) {
+        self.skipped.push(LoadIssue {
+            plugin: plugin.to_string(),
+            reason: reason.into(),
+        });
+    }
+
+    /// 记录失败的插件
+    pub fn fail(&mut self, plugin: &str, reason: impl Into<String>) {
+        self.failed.push(LoadIssue {
+            plugin: plugin.to_string(),
+            reason: reason.into(),
+        });
+    }
+
+    /// 插件是否被跳过或加载失败
+    pub fn is_missing(&self, plugin: &str) -> bool {
+        self.skipped
+            .iter()
+            .chain(&self.failed)
+            .any(|issue| issue.plugin == plugin)
+    }
+
+    /// 是否所有插件都已加载
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty() && self.failed.is_empty()
+    }
+
+    /// 在日志中输出报告
+    pub fn log(&self) {
+        tracing::info!("已加载 {} 个插件: {:?}", self.loaded.len(), self.loaded);
+        for issue in &self.skipped {
+            tracing::warn!("已跳过插件 {}: {}", issue.plugin, issue.reason);
+        }
+        for issue in &self.failed {
+            tracing::error!("插件 {} 加载失败: {}", issue.plugin, issue.reason);
+        }
+    }
+}
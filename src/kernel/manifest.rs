@@ -2,7 +2,9 @@
 //!
 //! 支持 manifest.toml 格式的插件元数据
 
+use crate::config::OverflowPolicy;
 use anyhow::{anyhow, Result};
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -18,6 +20,21 @@ pub struct PluginManifest {
     /// 元数据
     #[serde(default)]
     pub metadata: Metadata,
+    /// 运行时资源限制（覆盖内核默认值）
+    #[serde(default)]
+    pub limits: Limits,
+    /// 能力权限声明（未声明时使用内核 `plugins.default_permissions` 配置）
+    #[serde(default)]
+    pub permissions: Option<Permissions>,
+    /// 插件配置默认值
+    #[serde(default)]
+    pub config: toml::Table,
+    /// 定时任务（内核按计划调用插件的 tick 函数）
+    #[serde(default)]
+    pub schedule: Schedule,
+    /// 插件发布的主题及其负载 schema
+    #[serde(default)]
+    pub topics: Vec<TopicDeclaration>,
 }
 
 /// 插件基本信息
@@ -36,14 +53,162 @@ pub struct PluginInfo {
 }
 
 /// 依赖信息
+///
+/// 依赖可以写成插件名列表（接受任意版本），或插件名到 semver 版本要求的表：
+///
+/// ```toml
+/// [dependencies.requires]
+/// storage-core = "^1.2"
+/// ```
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Dependencies {
     /// 必需依赖
+    #[serde(default, with = "dependency_list")]
+    pub requires: Vec<DependencySpec>,
+    /// 可选依赖（存在时同样需要满足版本要求）
+    #[serde(default, with = "dependency_list")]
+    pub optional: Vec<DependencySpec>,
+}
+
+/// 对某个插件的依赖
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencySpec {
+    /// 插件名称
+    pub name: String,
+    /// 版本要求
+    pub version: VersionReq,
+}
+
+impl DependencySpec {
+    /// 接受任意版本的依赖
+    pub fn any(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: VersionReq::STAR,
+        }
+    }
+
+    /// 解析版本要求（如 `^1.2`、`>=1.0, <2`）
+    pub fn parse(name: &str, version: &str) -> Result<Self> {
+        let version = VersionReq::parse(version)
+            .map_err(|e| anyhow!("依赖 '{}' 的版本要求 '{}' 无效: {}", name, version, e))?;
+        Ok(Self {
+            name: name.to_string(),
+            version,
+        })
+    }
+
+    /// 版本是否满足要求
+    pub fn matches(&self, version: &Version) -> bool {
+        self.version.matches(version)
+    }
+}
+
+impl std::fmt::Display for DependencySpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// 依赖列表的序列化：读取名称列表或名称到版本要求的表，写出为表
+mod dependency_list {
+    use super::DependencySpec;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDependencies {
+        Names(Vec<String>),
+        Constraints(BTreeMap<String, String>),
+    }
+
+    pub fn serialize<S: Serializer>(
+        specs: &[DependencySpec],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            specs
+                .iter()
+                .map(|spec| (&spec.name, spec.version.to_string())),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<DependencySpec>, D::Error> {
+        match RawDependencies::deserialize(deserializer)? {
+            RawDependencies::Names(names) => {
+                Ok(names.iter().map(|name| DependencySpec::any(name)).collect())
+            }
+            RawDependencies::Constraints(constraints) => constraints
+                .iter()
+                .map(|(name, version)| {
+                    DependencySpec::parse(name, version).map_err(D::Error::custom)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 运行时资源限制
+///
+/// 未设置的字段使用内核 `plugins.timeout_ms` / `plugins.max_memory_mb` /
+/// `plugins.queue_depth` / `plugins.queue_overflow` 配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Limits {
+    /// 单次调用超时（毫秒）
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// 最大内存（MB）
+    #[serde(default)]
+    pub max_memory_mb: Option<u32>,
+    /// 消息队列深度
+    #[serde(default)]
+    pub queue_depth: Option<usize>,
+    /// 消息队列已满时的处理策略
+    #[serde(default)]
+    pub queue_overflow: Option<OverflowPolicy>,
+}
+
+/// 能力权限声明
+///
+/// 格式见 [`crate::kernel::permissions::Capability`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Permissions {
+    /// 允许的能力，如 `storage`、`identity.sign`、`bus.publish:health.*`
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+/// 定时任务计划
+///
+/// `tick_interval_ms` 与 `cron` 二选一，都未设置时不调用 tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    /// 固定调用间隔（毫秒）
     #[serde(default)]
-    pub requires: Vec<String>,
-    /// 可选依赖
+    pub tick_interval_ms: Option<u64>,
+    /// cron 表达式（秒 分 时 日 月 周 [年]，UTC）
     #[serde(default)]
-    pub optional: Vec<String>,
+    pub cron: Option<String>,
+    /// 每次调用前的随机延迟上限（毫秒，默认为间隔的 10%，cron 默认不延迟）
+    #[serde(default)]
+    pub jitter_ms: Option<u64>,
+}
+
+/// 主题声明
+///
+/// 负载的 JSON Schema 以 TOML 表书写，加载插件时注册到总线的 schema 注册表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicDeclaration {
+    /// 主题或主题模式（支持通配符）
+    pub topic: String,
+    /// 描述
+    #[serde(default)]
+    pub description: String,
+    /// 负载的 JSON Schema
+    pub schema: toml::Table,
 }
 
 /// 元数据
@@ -85,24 +250,45 @@ impl PluginManifest {
             },
             dependencies: Dependencies::default(),
             metadata: Metadata::default(),
+            limits: Limits::default(),
+            permissions: None,
+            config: toml::Table::new(),
+            schedule: Schedule::default(),
+            topics: Vec::new(),
         }
     }
 
-    /// 获取所有依赖（必需 + 可选）
+    /// 获取所有依赖的名称（必需 + 可选）
     pub fn all_dependencies(&self) -> Vec<String> {
-        let mut deps = self.dependencies.requires.clone();
-        deps.extend(self.dependencies.optional.clone());
-        deps
+        self.dependencies
+            .requires
+            .iter()
+            .chain(&self.dependencies.optional)
+            .map(|dep| dep.name.clone())
+            .collect()
     }
 
     /// 检查是否兼容指定的内核版本
     pub fn is_compatible_with_kernel(&self, kernel_version: &str) -> bool {
-        if let Some(min_version) = &self.metadata.min_kernel_version {
-            // 这里应该使用 semver 比较，为简单起见使用字符串比较
-            min_version.as_str() <= kernel_version
-        } else {
-            true // 没有版本要求，认为兼容
-        }
+        kernel_satisfies(self.metadata.min_kernel_version.as_deref(), kernel_version)
+    }
+}
+
+/// 内核版本是否满足插件的 `min_kernel_version`
+///
+/// 要求可以是版本号（表示不低于该版本）或 semver 版本要求（如 `>=0.2, <0.4`），
+/// 没有要求时总是兼容，版本号无法解析时视为不兼容
+pub fn kernel_satisfies(min_kernel_version: Option<&str>, kernel_version: &str) -> bool {
+    let Some(requirement) = min_kernel_version else {
+        return true;
+    };
+    let Ok(kernel_version) = Version::parse(kernel_version) else {
+        return false;
+    };
+
+    match Version::parse(requirement) {
+        Ok(min_version) => kernel_version >= min_version,
+        Err(_) => VersionReq::parse(requirement).is_ok_and(|req| req.matches(&kernel_version)),
     }
 }
 
@@ -165,21 +351,59 @@ description = "{plugin_name} 插件的简要描述"
 author = "Your Name"
 
 [dependencies]
-# 必需的插件依赖
+# 必需的插件依赖：插件名列表（任意版本），或写成表以指定 semver 版本要求
 requires = []
-# 可选的插件依赖
+# 可选的插件依赖（存在时同样需要满足版本要求）
 optional = []
+# [dependencies.requires]
+# storage-core = "^1.2"
 
 [metadata]
 # 插件标签，用于分类和搜索
 tags = ["example", "demo"]
-# 支持的最小内核版本
+# 支持的最小内核版本（也可以写 semver 版本要求，如 ">=0.1, <0.3"）
 min_kernel_version = "0.1.0"
 
 # 自定义元数据字段
 [metadata.custom]
 license = "MIT"
 homepage = "https://github.com/your-username/{plugin_name}"
+
+# 运行时资源限制（可选，未设置时使用内核配置）
+[limits]
+# 单次调用超时（毫秒）
+# timeout_ms = 5000
+# 最大内存（MB）
+# max_memory_mb = 128
+
+# 插件需要的能力（未声明时使用内核配置的默认权限）
+[permissions]
+# storage: 读写自身存储；identity.sign: 使用派生密钥签名
+# bus.publish:<主题模式> / bus.subscribe:<主题模式> / bus.send:<插件名>
+# 访问其它插件：storage:<插件名> / config:<插件名> / identity.sign:<插件名> / bus.impersonate:<插件名>
+allow = ["storage", "bus.publish:{plugin_name}.*"]
+
+# 插件配置默认值（插件通过 host::config::get 读取，保存的配置会覆盖同名字段）
+[config]
+# interval_ms = 1000
+
+# 定时任务（可选，内核按计划调用插件的 tick 函数，不会重叠执行）
+[schedule]
+# 固定间隔（毫秒）
+# tick_interval_ms = 1000
+# 或 cron 表达式（秒 分 时 日 月 周，UTC）
+# cron = "0 */5 * * * *"
+# 随机延迟上限（毫秒，默认为间隔的 10%）
+# jitter_ms = 100
+
+# 发布的主题及其负载 JSON Schema（可选，内核按 message_bus.schema_validation 校验）
+# [[topics]]
+# topic = "{plugin_name}.status"
+# description = "插件状态"
+# [topics.schema]
+# type = "object"
+# required = ["ok"]
+# properties.ok = {{ type = "boolean" }}
 "#
     )
 }
@@ -212,9 +436,150 @@ min_kernel_version = "0.1.0"
 
         assert_eq!(manifest.plugin.name, "test-plugin");
         assert_eq!(manifest.plugin.version, "1.0.0");
-        assert_eq!(manifest.dependencies.requires, vec!["base-plugin"]);
-        assert_eq!(manifest.dependencies.optional, vec!["extra-plugin"]);
+        assert_eq!(
+            manifest.dependencies.requires,
+            vec![DependencySpec::any("base-plugin")]
+        );
+        assert_eq!(
+            manifest.all_dependencies(),
+            vec!["base-plugin", "extra-plugin"]
+        );
         assert_eq!(manifest.metadata.tags, vec!["test", "example"]);
+        assert!(manifest.limits.timeout_ms.is_none());
+        assert!(manifest.permissions.is_none());
+        assert!(manifest.config.is_empty());
+        assert!(manifest.schedule.tick_interval_ms.is_none());
+        assert!(manifest.schedule.cron.is_none());
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let manifest_content = r#"
+[plugin]
+name = "system-stats-collector"
+version = "1.0.0"
+
+[schedule]
+tick_interval_ms = 2000
+jitter_ms = 50
+"#;
+
+        let manifest = PluginManifest::parse_manifest(manifest_content).unwrap();
+        assert_eq!(manifest.schedule.tick_interval_ms, Some(2000));
+        assert_eq!(manifest.schedule.jitter_ms, Some(50));
+        assert!(manifest.schedule.cron.is_none());
+    }
+
+    #[test]
+    fn test_parse_config_defaults() {
+        let manifest_content = r#"
+[plugin]
+name = "sensor"
+version = "1.0.0"
+
+[config]
+interval_ms = 1000
+unit = "celsius"
+"#;
+
+        let manifest = PluginManifest::parse_manifest(manifest_content).unwrap();
+        assert_eq!(manifest.config["interval_ms"].as_integer(), Some(1000));
+        assert_eq!(manifest.config["unit"].as_str(), Some("celsius"));
+    }
+
+    #[test]
+    fn test_parse_permissions() {
+        let manifest_content = r#"
+[plugin]
+name = "health-monitor"
+version = "1.0.0"
+
+[permissions]
+allow = ["storage", "bus.publish:health.*", "bus.send:echo"]
+"#;
+
+        let manifest = PluginManifest::parse_manifest(manifest_content).unwrap();
+        let permissions = manifest.permissions.unwrap();
+        assert_eq!(
+            permissions.allow,
+            vec!["storage", "bus.publish:health.*", "bus.send:echo"]
+        );
+    }
+
+    #[test]
+    fn test_parse_topics() {
+        let manifest_content = r#"
+[plugin]
+name = "system-stats-collector"
+version = "1.0.0"
+
+[[topics]]
+topic = "system.stats"
+description = "系统资源统计"
+
+[topics.schema]
+type = "object"
+required = ["cpu_usage"]
+properties.cpu_usage = { type = "number", minimum = 0 }
+"#;
+
+        let manifest = PluginManifest::parse_manifest(manifest_content).unwrap();
+        assert_eq!(manifest.topics.len(), 1);
+        let topic = &manifest.topics[0];
+        assert_eq!(topic.topic, "system.stats");
+        assert_eq!(topic.schema["type"].as_str(), Some("object"));
+        assert_eq!(
+            topic.schema["properties"]["cpu_usage"]["type"].as_str(),
+            Some("number")
+        );
+    }
+
+    #[test]
+    fn test_parse_dependency_constraints() {
+        let manifest_content = r#"
+[plugin]
+name = "dashboard"
+version = "1.0.0"
+
+[dependencies.requires]
+storage-core = "^1.2"
+metrics = ">=2.0, <3"
+
+[dependencies]
+optional = ["theme"]
+"#;
+
+        let manifest = PluginManifest::parse_manifest(manifest_content).unwrap();
+        let requires = &manifest.dependencies.requires;
+        assert_eq!(requires[1].name, "storage-core");
+        assert!(requires[1].matches(&Version::new(1, 4, 0)));
+        assert!(!requires[1].matches(&Version::new(2, 0, 0)));
+        assert!(requires[0].matches(&Version::new(2, 9, 1)));
+        assert_eq!(
+            manifest.dependencies.optional,
+            vec![DependencySpec::any("theme")]
+        );
+
+        let invalid = manifest_content.replace("^1.2", "one point two");
+        let err = PluginManifest::parse_manifest(&invalid).unwrap_err();
+        assert!(err.to_string().contains("storage-core"));
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let manifest_content = r#"
+[plugin]
+name = "slow-plugin"
+version = "1.0.0"
+
+[limits]
+timeout_ms = 250
+max_memory_mb = 32
+"#;
+
+        let manifest = PluginManifest::parse_manifest(manifest_content).unwrap();
+        assert_eq!(manifest.limits.timeout_ms, Some(250));
+        assert_eq!(manifest.limits.max_memory_mb, Some(32));
     }
 
     #[test]
@@ -263,5 +628,17 @@ min_kernel_version = "0.1.0"
         assert!(manifest.is_compatible_with_kernel("0.1.0"));
         assert!(manifest.is_compatible_with_kernel("0.2.0"));
         assert!(!manifest.is_compatible_with_kernel("0.0.9"));
+
+        // 按 semver 而不是字符串比较
+        manifest.metadata.min_kernel_version = Some("0.9.0".to_string());
+        assert!(manifest.is_compatible_with_kernel("0.10.0"));
+        manifest.metadata.min_kernel_version = Some("0.10.0".to_string());
+        assert!(!manifest.is_compatible_with_kernel("0.9.0"));
+
+        // 版本要求
+        manifest.metadata.min_kernel_version = Some(">=0.2, <0.4".to_string());
+        assert!(manifest.is_compatible_with_kernel("0.3.1"));
+        assert!(!manifest.is_compatible_with_kernel("0.4.0"));
+        assert!(!manifest.is_compatible_with_kernel("not-a-version"));
     }
 }
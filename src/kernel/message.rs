@@ -4,6 +4,41 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 投递失败回报的消息类型
+pub const DELIVERY_ERROR_TYPE: &str = "delivery_error";
+
+/// 请求消息类型（等待目标回复）
+pub const REQUEST_TYPE: &str = "request";
+
+/// 回复消息类型
+pub const RESPONSE_TYPE: &str = "response";
+
+/// 未指定消息类型时传给插件的默认类型
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// 保留消息投递给新订阅者时带有的元数据键名
+pub const RETAINED_KEY: &str = "retained";
+
+/// 传给插件的元数据中关联 ID 的键名
+pub const CORRELATION_ID_KEY: &str = "correlation_id";
+
+/// 消息优先级（路由器优先处理高优先级消息）
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum MessagePriority {
+    /// 低优先级
+    Low = 0,
+    /// 正常优先级
+    #[default]
+    Normal = 1,
+    /// 高优先级
+    High = 2,
+    /// 紧急优先级
+    Critical = 3,
+}
 
 /// 插件间消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +63,33 @@ pub struct Message {
 
     /// 时间戳
     pub timestamp: DateTime<Utc>,
+
+    /// 关联 ID（请求与回复使用同一个 ID）
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+
+    /// 持久投递（保存到 SQLite，接收方确认前按退避策略重投）
+    #[serde(default)]
+    pub durable: bool,
+    /// 优先级
+    #[serde(default)]
+    pub priority: MessagePriority,
+
+    /// 过期时间（过期后不再投递）
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// 元数据
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+
+    /// 内核以发送方派生密钥生成的签名（十六进制）
+    #[serde(default)]
+    pub signature: Option<String>,
+
+    /// 签名已通过验证，`from` 确为发送方
+    #[serde(default)]
+    pub verified_from: bool,
 }
 
 impl Message {
@@ -41,6 +103,13 @@ impl Message {
             msg_type: None,
             topic: None,
             timestamp: Utc::now(),
+            correlation_id: None,
+            durable: false,
+            priority: MessagePriority::Normal,
+            expires_at: None,
+            metadata: HashMap::new(),
+            signature: None,
+            verified_from: false,
         }
     }
 
@@ -54,9 +123,79 @@ impl Message {
             msg_type: None,
             topic: Some(topic),
             timestamp: Utc::now(),
+            correlation_id: None,
+            durable: false,
+            priority: MessagePriority::Normal,
+            expires_at: None,
+            metadata: HashMap::new(),
+            signature: None,
+            verified_from: false,
         }
     }
 
+    /// 创建请求消息（关联 ID 与消息 ID 相同）
+    pub fn request(from: String, to: String, payload: Vec<u8>) -> Self {
+        let message = Self::new(from, to, payload).with_type(REQUEST_TYPE.to_string());
+        let id = message.id.clone();
+        message.with_correlation_id(id)
+    }
+
+    /// 创建对请求的回复
+    pub fn response(from: String, to: String, correlation_id: String, payload: Vec<u8>) -> Self {
+        Self::new(from, to, payload)
+            .with_type(RESPONSE_TYPE.to_string())
+            .with_correlation_id(correlation_id)
+    }
+
+    /// 设置关联 ID
+    pub fn with_correlation_id(mut self, correlation_id: String) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
+    /// 标记为持久投递
+    pub fn with_durable(mut self) -> Self {
+        self.durable = true;
+        self
+    }
+
+    /// 设置优先级
+    pub fn with_priority(mut self, priority: MessagePriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 设置过期时间
+    pub fn with_expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// 设置生存时间（从消息时间戳起算）
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.expires_at = chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| self.timestamp.checked_add_signed(ttl));
+        self
+    }
+
+    /// 添加元数据
+    pub fn with_metadata(mut self, key: String, value: String) -> Self {
+        self.metadata.insert(key, value);
+        self
+    }
+
+    /// 检查消息是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Utc::now() > expires_at)
+    }
+
+    /// 检查是否为等待回复的请求
+    pub fn is_request(&self) -> bool {
+        self.msg_type.as_deref() == Some(REQUEST_TYPE) && self.correlation_id.is_some()
+    }
+
     /// 设置消息类型
     pub fn with_type(mut self, msg_type: String) -> Self {
         self.msg_type = Some(msg_type);
@@ -75,15 +214,191 @@ impl Message {
     }
 }
 
+/// 传给 WASM 插件 `handle_message` 的消息格式
+///
+/// 与 plugin-sdk 中 `PluginMessage` 的 JSON 结构保持一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginMessage {
+    /// 消息ID
+    pub id: String,
+    /// 发送者插件名称
+    pub from: String,
+    /// 接收者插件名称
+    pub to: String,
+    /// 消息主题
+    pub topic: String,
+    /// 消息负载
+    pub payload: Vec<u8>,
+    /// 消息类型
+    pub message_type: String,
+    /// 消息元数据
+    pub metadata: HashMap<String, String>,
+    /// 消息时间戳（毫秒）
+    pub timestamp: u64,
+    /// 消息过期时间戳（毫秒）
+    pub expires_at: Option<u64>,
+    /// 消息优先级
+    #[serde(default)]
+    pub priority: MessagePriority,
+    /// 消息签名（十六进制，未签名时为 None）
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// 内核已验证签名，`from` 可信
+    #[serde(default)]
+    pub verified_from: bool,
+}
+
+impl From<&Message> for PluginMessage {
+    fn from(message: &Message) -> Self {
+        let mut metadata = message.metadata.clone();
+        if let Some(correlation_id) = &message.correlation_id {
+            metadata.insert(CORRELATION_ID_KEY.to_string(), correlation_id.clone());
+        }
+
+        Self {
+            id: message.id.clone(),
+            from: message.from.clone(),
+            to: message.to.clone(),
+            topic: message.topic.clone().unwrap_or_default(),
+            payload: message.payload.clone(),
+            message_type: message
+                .msg_type
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string()),
+            metadata,
+            timestamp: message.timestamp.timestamp_millis() as u64,
+            expires_at: message
+                .expires_at
+                .map(|expires_at| expires_at.timestamp_millis() as u64),
+            priority: message.priority,
+            signature: message.signature.clone(),
+            verified_from: message.verified_from,
+        }
+    }
+}
+
+impl Message {
+    /// 从插件提交的 `PluginMessage` 创建发往 `to` 的消息
+    ///
+    /// 沿用其负载、类型、元数据、优先级与过期时间，消息 ID 与时间戳由内核重新生成
+    pub fn from_plugin_message(from: String, to: String, envelope: PluginMessage) -> Self {
+        let mut message = Self::new(from, to, Vec::new());
+        message.correlation_id = envelope.metadata.get(CORRELATION_ID_KEY).cloned();
+        message.apply_plugin_message(envelope);
+        message
+    }
+
+    /// 用 `PluginMessage` 的内容（负载、类型、元数据、优先级与过期时间）覆盖本消息
+    ///
+    /// 消息 ID、收发方、主题与关联 ID 保持不变，签名与验证结果只由内核设置
+    pub fn apply_plugin_message(&mut self, envelope: PluginMessage) {
+        self.payload = envelope.payload;
+        self.msg_type =
+            (envelope.message_type != DEFAULT_CONTENT_TYPE).then_some(envelope.message_type);
+        self.priority = envelope.priority;
+        self.expires_at = envelope
+            .expires_at
+            .and_then(|millis| DateTime::from_timestamp_millis(millis as i64));
+        self.metadata = envelope.metadata;
+        self.metadata.remove(CORRELATION_ID_KEY);
+    }
+}
+
 /// 消息发送结果
 #[derive(Debug, Serialize, Deserialize)]
 pub enum MessageResult {
     /// 成功发送
     Success,
 
+    /// 已持久化，等待接收方加载或通道空闲后投递
+    Queued,
+
     /// 目标插件不存在
     PluginNotFound(String),
 
+    /// 消息已过期，未投递
+    Expired,
+
+    /// 被拦截器拒绝
+    Rejected(String),
+
     /// 发送失败
     Failed(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_message_conversion() {
+        let message = Message::new_topic(
+            "sender".to_string(),
+            "system.stats".to_string(),
+            b"{}".to_vec(),
+        );
+
+        let json = serde_json::to_value(PluginMessage::from(&message)).unwrap();
+        assert_eq!(json["id"], message.id.as_str());
+        assert_eq!(json["from"], "sender");
+        assert_eq!(json["to"], "");
+        assert_eq!(json["topic"], "system.stats");
+        assert_eq!(json["message_type"], "application/octet-stream");
+        assert_eq!(json["priority"], "Normal");
+        assert_eq!(json["payload"], serde_json::json!([123, 125]));
+    }
+
+    #[test]
+    fn test_priority_ttl_and_metadata_round_trip() {
+        let message = Message::new("a".to_string(), "b".to_string(), b"hi".to_vec())
+            .with_priority(MessagePriority::Critical)
+            .with_ttl(std::time::Duration::from_secs(60))
+            .with_metadata("trace".to_string(), "42".to_string());
+        assert!(!message.is_expired());
+        assert!(MessagePriority::Critical > MessagePriority::Low);
+
+        let json = serde_json::to_value(PluginMessage::from(&message)).unwrap();
+        assert_eq!(json["priority"], "Critical");
+        assert_eq!(json["metadata"]["trace"], "42");
+        assert_eq!(
+            json["expires_at"],
+            message.expires_at.unwrap().timestamp_millis()
+        );
+
+        let envelope: PluginMessage = serde_json::from_value(json).unwrap();
+        let forwarded = Message::from_plugin_message("a".to_string(), "c".to_string(), envelope);
+        assert_eq!(forwarded.to, "c");
+        assert_eq!(forwarded.payload, b"hi");
+        assert_eq!(forwarded.priority, MessagePriority::Critical);
+        assert_eq!(forwarded.metadata["trace"], "42");
+        assert_eq!(
+            forwarded.expires_at.unwrap().timestamp_millis(),
+            message.expires_at.unwrap().timestamp_millis()
+        );
+        assert_eq!(forwarded.msg_type, None);
+
+        let stale = Message::new("a".to_string(), "b".to_string(), Vec::new())
+            .with_expires_at(Utc::now() - chrono::Duration::seconds(1));
+        assert!(stale.is_expired());
+    }
+
+    #[test]
+    fn test_request_carries_correlation_id() {
+        let request = Message::request("a".to_string(), "b".to_string(), b"ping".to_vec());
+        assert!(request.is_request());
+        assert_eq!(request.correlation_id.as_deref(), Some(request.id.as_str()));
+
+        let json = serde_json::to_value(PluginMessage::from(&request)).unwrap();
+        assert_eq!(json["message_type"], REQUEST_TYPE);
+        assert_eq!(json["metadata"][CORRELATION_ID_KEY], request.id.as_str());
+
+        let response = Message::response(
+            "b".to_string(),
+            "a".to_string(),
+            request.id.clone(),
+            b"pong".to_vec(),
+        );
+        assert!(!response.is_request());
+        assert_eq!(response.correlation_id, request.correlation_id);
+    }
+}
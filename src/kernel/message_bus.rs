@@ -5,12 +5,81 @@
 //! - MessageRouter: 独占的接收端
 
 use anyhow::Result;
-use parking_lot::RwLock;
-use std::collections::{HashMap, HashSet};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
 
-use super::message::{Message, MessageResult};
+use super::bus_metrics::{BusMetrics, BusStats, DeliveryOutcome};
+use super::durable_queue::{run_redelivery, DurableQueue, NackOutcome};
+use super::interceptor::{InterceptorChain, MessageInterceptor};
+use super::journal::{DeliveryStatus, MessageJournal};
+use super::message::{Message, MessageResult, DELIVERY_ERROR_TYPE};
+use super::plugin_queue::{
+    plugin_queue, BlockingSend, PluginQueueConfig, PluginQueueStats, PluginReceiver, PluginSender,
+    WaitOutcome,
+};
+use super::priority_queue::PriorityQueue;
+use super::retained::RetainedCache;
+use super::schema_registry::SchemaRegistry;
+use super::signing::MessageSigner;
+use super::topic_index::{validate_topic_pattern, TopicIndex};
+use crate::config::OverflowPolicy;
+
+/// 插件通道映射：插件名称 -> 插件消息队列
+pub(super) type PluginChannels = Arc<RwLock<HashMap<String, PluginSender>>>;
+
+/// 持久投递队列（启用后由 Handle 与 Router 共享）
+type SharedDurableQueue = Arc<RwLock<Option<DurableQueue>>>;
+
+/// 保留消息缓存（配置了保留主题时由 Handle 与 Router 共享）
+type SharedRetainedCache = Arc<RwLock<Option<RetainedCache>>>;
+
+/// 消息签名器（启用签名后由所有 Handle 共享）
+type SharedSigner = Arc<RwLock<Option<MessageSigner>>>;
+
+/// 主通道已满时插件发送消息的默认等待时间
+const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 等待回复的请求：关联 ID -> 请求
+type PendingRequests = Arc<Mutex<HashMap<String, PendingRequest>>>;
+
+/// 等待回复的请求
+struct PendingRequest {
+    /// 请求的目标插件（只接受它的回复）
+    target: String,
+    /// 回复通道（Err 为目标处理失败的原因）
+    reply: oneshot::Sender<Result<Vec<u8>, String>>,
+}
+
+/// 请求/响应调用错误
+#[derive(thiserror::Error, Debug)]
+pub enum RequestError {
+    #[error("Request to '{to}' timed out after {timeout_ms}ms")]
+    Timeout { to: String, timeout_ms: u64 },
+
+    #[error("Request target '{0}' not found")]
+    TargetNotFound(String),
+
+    #[error("Request to '{to}' failed: {reason}")]
+    Failed { to: String, reason: String },
+
+    #[error("Message bus is closed")]
+    BusClosed,
+}
+
+impl RequestError {
+    /// 返回给插件的结构化错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            RequestError::Timeout { .. } => "timeout",
+            RequestError::TargetNotFound(_) => "not_found",
+            RequestError::Failed { .. } | RequestError::BusClosed => "request_failed",
+        }
+    }
+}
 
 /// 消息总线句柄 - 可克隆，用于发送消息和管理插件通道
 #[derive(Clone)]
@@ -18,9 +87,25 @@ pub struct MessageBusHandle {
     /// 主消息发送器
     sender: mpsc::Sender<Message>,
     /// 插件通道映射
-    plugin_channels: Arc<RwLock<HashMap<String, mpsc::Sender<Message>>>>,
-    /// 主题订阅映射
-    topic_subscriptions: Arc<RwLock<HashMap<String, HashSet<String>>>>,
+    plugin_channels: PluginChannels,
+    /// 主题订阅索引（支持通配符）
+    topic_subscriptions: Arc<RwLock<TopicIndex>>,
+    /// 等待回复的请求
+    pending_requests: PendingRequests,
+    /// 持久投递队列
+    durable_queue: SharedDurableQueue,
+    /// 保留消息缓存
+    retained: SharedRetainedCache,
+    /// 总线指标
+    metrics: BusMetrics,
+    /// 消息拦截器链
+    interceptors: InterceptorChain,
+    /// 消息签名器
+    signer: SharedSigner,
+    /// 主题 schema 注册表
+    schemas: SchemaRegistry,
+    /// 主通道已满时插件发送消息的最长等待时间
+    send_timeout: Duration,
     /// 关闭信号发送器
     shutdown_tx: mpsc::Sender<()>,
 }
@@ -30,9 +115,23 @@ pub struct MessageRouter {
     /// 主消息接收器
     receiver: mpsc::Receiver<Message>,
     /// 插件通道映射（与 Handle 共享）
-    plugin_channels: Arc<RwLock<HashMap<String, mpsc::Sender<Message>>>>,
-    /// 主题订阅映射（与 Handle 共享）
-    topic_subscriptions: Arc<RwLock<HashMap<String, HashSet<String>>>>,
+    plugin_channels: PluginChannels,
+    /// 主题订阅索引（与 Handle 共享）
+    topic_subscriptions: Arc<RwLock<TopicIndex>>,
+    /// 等待回复的请求（与 Handle 共享）
+    pending_requests: PendingRequests,
+    /// 持久投递队列（与 Handle 共享）
+    durable_queue: SharedDurableQueue,
+    /// 保留消息缓存（与 Handle 共享）
+    retained: SharedRetainedCache,
+    /// 总线指标（与 Handle 共享）
+    metrics: BusMetrics,
+    /// 消息拦截器链（与 Handle 共享）
+    interceptors: InterceptorChain,
+    /// 优先级队列容量（超出时留在通道中，保持背压）
+    capacity: usize,
+    /// 消息日志（未启用时为 None）
+    journal: Option<MessageJournal>,
     /// 关闭信号接收器
     shutdown_rx: mpsc::Receiver<()>,
 }
@@ -42,12 +141,25 @@ pub fn create_message_bus(buffer_size: usize) -> (MessageBusHandle, MessageRoute
     let (sender, receiver) = mpsc::channel(buffer_size);
     let (shutdown_tx, shutdown_rx) = mpsc::channel(1);
     let plugin_channels = Arc::new(RwLock::new(HashMap::new()));
-    let topic_subscriptions = Arc::new(RwLock::new(HashMap::new()));
+    let topic_subscriptions = Arc::new(RwLock::new(TopicIndex::new()));
+    let pending_requests = Arc::new(Mutex::new(HashMap::new()));
+    let durable_queue = SharedDurableQueue::default();
+    let retained = SharedRetainedCache::default();
+    let metrics = BusMetrics::new();
+    let interceptors = InterceptorChain::default();
 
     let handle = MessageBusHandle {
         sender,
         plugin_channels: plugin_channels.clone(),
         topic_subscriptions: topic_subscriptions.clone(),
+        pending_requests: pending_requests.clone(),
+        durable_queue: durable_queue.clone(),
+        retained: retained.clone(),
+        metrics: metrics.clone(),
+        interceptors: interceptors.clone(),
+        signer: SharedSigner::default(),
+        schemas: SchemaRegistry::new(),
+        send_timeout: DEFAULT_SEND_TIMEOUT,
         shutdown_tx,
     };
 
@@ -55,6 +167,13 @@ pub fn create_message_bus(buffer_size: usize) -> (MessageBusHandle, MessageRoute
         receiver,
         plugin_channels,
         topic_subscriptions,
+        pending_requests,
+        durable_queue,
+        retained,
+        metrics,
+        interceptors,
+        capacity: buffer_size.max(1),
+        journal: None,
         shutdown_rx,
     };
 
@@ -72,59 +191,147 @@ impl MessageBusHandle {
         self.shutdown_tx.clone()
     }
 
-    /// 为插件注册通道
-    pub fn register_plugin(&self, plugin_id: String) -> mpsc::Receiver<Message> {
-        let (tx, rx) = mpsc::channel(100);
+    /// 设置主通道已满时插件发送消息的最长等待时间
+    pub fn with_send_timeout(mut self, send_timeout: Duration) -> Self {
+        self.send_timeout = send_timeout;
+        self
+    }
+
+    /// 主通道已满时插件发送消息的最长等待时间
+    pub fn send_timeout(&self) -> Duration {
+        self.send_timeout
+    }
+
+    /// 为插件注册通道（使用默认的队列配置）
+    pub fn register_plugin(&self, plugin_id: String) -> PluginReceiver {
+        self.register_plugin_with_queue(plugin_id, PluginQueueConfig::default())
+    }
+
+    /// 为插件注册指定深度与溢出策略的消息队列
+    pub fn register_plugin_with_queue(
+        &self,
+        plugin_id: String,
+        config: PluginQueueConfig,
+    ) -> PluginReceiver {
+        let (tx, rx) = plugin_queue(config);
         self.plugin_channels.write().insert(plugin_id, tx);
         rx
     }
 
+    /// 替换插件消息队列的配置（保留已排队的消息），插件未注册时返回 false
+    pub fn reconfigure_plugin_queue(&self, plugin_id: &str, config: PluginQueueConfig) -> bool {
+        match self.plugin_channels.read().get(plugin_id) {
+            Some(tx) => {
+                tx.reconfigure(config);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 获取插件消息队列的状态
+    pub fn queue_stats(&self, plugin_id: &str) -> Option<PluginQueueStats> {
+        self.plugin_channels
+            .read()
+            .get(plugin_id)
+            .map(PluginSender::stats)
+    }
+
+    /// 获取所有插件消息队列的状态
+    pub fn all_queue_stats(&self) -> HashMap<String, PluginQueueStats> {
+        self.plugin_channels
+            .read()
+            .iter()
+            .map(|(plugin_id, tx)| (plugin_id.clone(), tx.stats()))
+            .collect()
+    }
+
     /// 注销插件
     pub fn unregister_plugin(&self, plugin_id: &str) {
         self.plugin_channels.write().remove(plugin_id);
 
         // 从所有主题订阅中移除该插件
-        let mut subscriptions = self.topic_subscriptions.write();
-        for (_, subscribers) in subscriptions.iter_mut() {
-            subscribers.remove(plugin_id);
-        }
-        // 清理空的主题
-        subscriptions.retain(|_, subscribers| !subscribers.is_empty());
+        self.topic_subscriptions
+            .write()
+            .remove_subscriber(plugin_id);
     }
 
-    /// 订阅主题
+    /// 订阅主题，支持 `+`/`*`（单层）与 `#`（多层）通配符
+    ///
+    /// 新订阅会立即收到匹配主题的保留消息。模式无效或已订阅时返回 false
+    ///
+    /// 只修改内存中的订阅，不会持久化：插件的订阅由 `subscribe_topic_host`
+    /// 写入存储并在插件加载时恢复，主机端（如 UI 桥接）的订阅在内核重启后
+    /// 需由调用方重新订阅
     pub fn subscribe_topic(&self, plugin_id: &str, topic: &str) -> bool {
-        let mut subscriptions = self.topic_subscriptions.write();
-        let subscribers = subscriptions.entry(topic.to_string()).or_default();
-        subscribers.insert(plugin_id.to_string())
+        if let Err(e) = validate_topic_pattern(topic) {
+            tracing::warn!("插件 {} 订阅失败: {}", plugin_id, e);
+            return false;
+        }
+        let subscribed = self.topic_subscriptions.write().subscribe(topic, plugin_id);
+        if subscribed {
+            self.deliver_retained(plugin_id, topic);
+        }
+        subscribed
+    }
+
+    /// 将匹配订阅模式的保留消息放入插件的队列（队列已满时跳过）
+    fn deliver_retained(&self, plugin_id: &str, pattern: &str) {
+        let retained = self.retained_messages(pattern);
+        if retained.is_empty() {
+            return;
+        }
+        let Some(tx) = self.plugin_channels.read().get(plugin_id).cloned() else {
+            return;
+        };
+        for message in retained {
+            if tx.try_send(message).is_err() {
+                tracing::debug!("插件 {} 的消息队列已满，跳过剩余的保留消息", plugin_id);
+                break;
+            }
+        }
+    }
+
+    /// 启用保留消息缓存
+    pub fn set_retained_cache(&self, cache: RetainedCache) {
+        *self.retained.write() = Some(cache);
+    }
+
+    /// 读取匹配主题模式的保留消息（不订阅）
+    pub fn retained_messages(&self, pattern: &str) -> Vec<Message> {
+        self.retained
+            .read()
+            .as_ref()
+            .map(|cache| cache.get(pattern))
+            .unwrap_or_default()
     }
 
     /// 取消订阅主题
     pub fn unsubscribe_topic(&self, plugin_id: &str, topic: &str) -> bool {
-        let mut subscriptions = self.topic_subscriptions.write();
-        if let Some(subscribers) = subscriptions.get_mut(topic) {
-            let removed = subscribers.remove(plugin_id);
-            // 如果该主题没有订阅者了，就删除这个主题
-            if subscribers.is_empty() {
-                subscriptions.remove(topic);
-            }
-            removed
-        } else {
-            false
-        }
+        self.topic_subscriptions
+            .write()
+            .unsubscribe(topic, plugin_id)
     }
 
-    /// 获取主题的订阅者列表
+    /// 获取订阅了指定主题模式的订阅者列表（按模式精确匹配）
     pub fn get_topic_subscribers(&self, topic: &str) -> Vec<String> {
-        let subscriptions = self.topic_subscriptions.read();
-        subscriptions
-            .get(topic)
-            .map(|subscribers| subscribers.iter().cloned().collect())
-            .unwrap_or_default()
+        self.topic_subscriptions.read().pattern_subscribers(topic)
     }
 
-    /// 发送消息到消息总线
-    pub async fn send_message(&self, message: Message) -> Result<()> {
+    /// 获取会收到指定主题消息的订阅者列表（含通配符订阅，已去重）
+    pub fn match_topic_subscribers(&self, topic: &str) -> Vec<String> {
+        self.topic_subscriptions
+            .read()
+            .matching_subscribers(topic)
+            .into_iter()
+            .collect()
+    }
+
+    /// 发送消息到消息总线（启用签名时以 `from` 的派生密钥签名）
+    pub async fn send_message(&self, mut message: Message) -> Result<()> {
+        if let Some(signer) = self.signer() {
+            signer.sign(&mut message).await;
+        }
         self.sender
             .send(message)
             .await
@@ -132,6 +339,170 @@ impl MessageBusHandle {
         Ok(())
     }
 
+    /// 向插件发送请求并等待回复，返回回复的负载
+    ///
+    /// 目标插件通过 `reply_host` 以相同的关联 ID 回复；目标处理失败时立即返回错误
+    pub async fn request(
+        &self,
+        from: &str,
+        to: &str,
+        payload: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, RequestError> {
+        if from == to {
+            return Err(RequestError::Failed {
+                to: to.to_string(),
+                reason: "不能向自身发送请求".to_string(),
+            });
+        }
+        if !self.plugin_channels.read().contains_key(to) {
+            return Err(RequestError::TargetNotFound(to.to_string()));
+        }
+
+        let mut message = Message::request(from.to_string(), to.to_string(), payload);
+        if let Some(signer) = self.signer() {
+            signer.sign(&mut message).await;
+        }
+        let correlation_id = message.id.clone();
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending_requests.lock().insert(
+            correlation_id.clone(),
+            PendingRequest {
+                target: to.to_string(),
+                reply: reply_tx,
+            },
+        );
+
+        if self.sender.send(message).await.is_err() {
+            self.pending_requests.lock().remove(&correlation_id);
+            return Err(RequestError::BusClosed);
+        }
+
+        match tokio::time::timeout(timeout, reply_rx).await {
+            Ok(Ok(Ok(payload))) => Ok(payload),
+            Ok(Ok(Err(reason))) => Err(RequestError::Failed {
+                to: to.to_string(),
+                reason,
+            }),
+            Ok(Err(_)) => Err(RequestError::BusClosed),
+            Err(_) => {
+                self.pending_requests.lock().remove(&correlation_id);
+                Err(RequestError::Timeout {
+                    to: to.to_string(),
+                    timeout_ms: timeout.as_millis() as u64,
+                })
+            }
+        }
+    }
+
+    /// 启用持久投递队列，并启动重投任务
+    pub fn set_durable_queue(&self, queue: DurableQueue) {
+        *self.durable_queue.write() = Some(queue.clone());
+        tokio::spawn(run_redelivery(
+            queue,
+            self.plugin_channels.clone(),
+            self.sender.clone(),
+        ));
+    }
+
+    /// 启用消息签名
+    pub fn set_signer(&self, signer: MessageSigner) {
+        *self.signer.write() = Some(signer);
+    }
+
+    /// 获取消息签名器（未启用时为 None）
+    pub fn signer(&self) -> Option<MessageSigner> {
+        self.signer.read().clone()
+    }
+
+    /// 主题 schema 注册表
+    pub fn schemas(&self) -> &SchemaRegistry {
+        &self.schemas
+    }
+
+    /// 获取持久投递队列（未启用时为 None）
+    pub fn durable_queue(&self) -> Option<DurableQueue> {
+        self.durable_queue.read().clone()
+    }
+
+    /// 确认持久消息已被接收方处理
+    pub async fn ack_durable(&self, message_id: &str, recipient: &str) {
+        let Some(queue) = self.durable_queue() else {
+            return;
+        };
+        if let Err(e) = queue.ack(message_id, recipient).await {
+            tracing::warn!("确认持久消息 {} 失败: {}", message_id, e);
+        }
+    }
+
+    /// 报告持久消息处理失败，返回重投安排（未启用持久投递时为 None）
+    pub async fn nack_durable(
+        &self,
+        message_id: &str,
+        recipient: &str,
+        error: &str,
+    ) -> Option<NackOutcome> {
+        let queue = self.durable_queue()?;
+        match queue.nack(message_id, recipient, error).await {
+            Ok(outcome) => Some(outcome),
+            Err(e) => {
+                tracing::warn!("记录持久消息 {} 的失败失败: {}", message_id, e);
+                None
+            }
+        }
+    }
+
+    /// 因过期而丢弃的消息数
+    pub fn expired_messages(&self) -> u64 {
+        self.metrics.totals().expired
+    }
+
+    /// 记录一条在投递给插件前过期的消息
+    pub fn record_expired(&self, plugin_id: &str) {
+        self.metrics.record_expired(plugin_id);
+    }
+
+    /// 记录一条插件处理失败的消息
+    pub fn record_failed(&self, plugin_id: &str) {
+        self.metrics
+            .record_delivery(plugin_id, DeliveryOutcome::Failed);
+    }
+
+    /// 消息拦截器链
+    pub fn interceptors(&self) -> &InterceptorChain {
+        &self.interceptors
+    }
+
+    /// 总线指标
+    pub fn metrics(&self) -> &BusMetrics {
+        &self.metrics
+    }
+
+    /// 汇总总线统计：按主题与插件的消息计数、队列状态与路由延迟
+    pub fn stats(&self) -> BusStats {
+        let mut subscriptions: HashMap<String, usize> = HashMap::new();
+        for (_, subscribers) in self.topic_subscriptions.read().patterns() {
+            for subscriber in subscribers {
+                *subscriptions.entry(subscriber).or_default() += 1;
+            }
+        }
+        BusStats::collect(
+            &self.metrics,
+            self.all_queue_stats(),
+            subscriptions,
+            self.sender.max_capacity() - self.sender.capacity(),
+            self.pending_requests.lock().len(),
+        )
+    }
+
+    /// 检查是否有发给指定插件、仍在等待回复的请求
+    pub fn is_pending_request(&self, correlation_id: &str, target: &str) -> bool {
+        self.pending_requests
+            .lock()
+            .get(correlation_id)
+            .is_some_and(|pending| pending.target == target)
+    }
+
     /// 发送关闭信号
     pub async fn shutdown(&self) -> Result<()> {
         let _ = self.shutdown_tx.send(()).await;
@@ -140,42 +511,50 @@ impl MessageBusHandle {
 }
 
 impl MessageRouter {
+    /// 启用消息日志，记录每条路由的消息及投递结果
+    pub fn set_journal(&mut self, journal: MessageJournal) {
+        self.journal = Some(journal);
+    }
+
+    /// 注册消息拦截器（按注册顺序执行）
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn MessageInterceptor>) {
+        self.interceptors.add(interceptor);
+    }
+
+    /// 设置验证器，在所有拦截器（包括插件拦截器）之后执行
+    pub fn set_verifier(&mut self, verifier: Arc<dyn MessageInterceptor>) {
+        self.interceptors.set_verifier(verifier);
+    }
+
     /// 运行消息路由（消耗 self）
+    ///
+    /// 已到达的消息先放入优先级队列，高优先级消息优先路由
     pub async fn run(mut self) {
         tracing::info!("消息路由器开始运行");
 
+        let mut queue = PriorityQueue::new();
         loop {
+            if self.shutdown_rx.try_recv().is_ok() {
+                tracing::info!("收到关闭信号，停止消息路由");
+                break;
+            }
+
+            while queue.len() < self.capacity {
+                match self.receiver.try_recv() {
+                    Ok(message) => queue.push(message),
+                    Err(_) => break,
+                }
+            }
+            if let Some(message) = queue.pop() {
+                self.handle_message(message).await;
+                continue;
+            }
+
             tokio::select! {
                 // 监听消息
                 msg = self.receiver.recv() => {
                     match msg {
-                        Some(message) => {
-                            if message.is_topic_message() {
-                                let topic = message.topic.as_ref().unwrap();
-                                tracing::debug!("收到主题消息: from={}, topic={}", message.from, topic);
-                            } else {
-                                tracing::debug!("收到点对点消息: from={}, to={}", message.from, message.to);
-                            }
-
-                            // 路由消息
-                            let result = if message.is_topic_message() {
-                                self.route_topic_message(message).await
-                            } else {
-                                self.route_direct_message(message).await
-                            };
-
-                            match result {
-                                MessageResult::Success => {
-                                    tracing::trace!("消息路由成功");
-                                }
-                                MessageResult::PluginNotFound(ref target) => {
-                                    tracing::warn!("目标不存在: {}", target);
-                                }
-                                MessageResult::Failed(ref reason) => {
-                                    tracing::error!("消息路由失败: {}", reason);
-                                }
-                            }
-                        }
+                        Some(message) => queue.push(message),
                         None => {
                             tracing::info!("消息通道已关闭");
                             break;
@@ -193,21 +572,207 @@ impl MessageRouter {
         tracing::info!("消息路由器已停止");
     }
 
+    /// 路由一条消息
+    async fn handle_message(&mut self, message: Message) {
+        self.metrics.record_published(&message);
+
+        // 先交给拦截器，日志记录的是拦截器处理后的消息
+        let (message, rejection) = match self.interceptors.before_route(message).await {
+            Ok(message) => (message, None),
+            Err(rejected) => {
+                tracing::warn!(
+                    "消息 {} 被拦截器 {} 拒绝: {}",
+                    rejected.message.id,
+                    rejected.interceptor,
+                    rejected.reason
+                );
+                let reason = format!(
+                    "消息被拦截器 {} 拒绝: {}",
+                    rejected.interceptor, rejected.reason
+                );
+                (rejected.message, Some(reason))
+            }
+        };
+
+        let journaled = self.journal.as_ref().map(|journal| {
+            journal.record(&message);
+            message.id.clone()
+        });
+        let is_topic = message.is_topic_message();
+
+        // 请求的回复直接交给等待方，不再路由
+        let message = match rejection {
+            Some(_) => message,
+            None => match self.complete_request(message) {
+                Some(message) => message,
+                None => {
+                    self.journal_result(journaled, is_topic, &MessageResult::Success);
+                    return;
+                }
+            },
+        };
+        // 被拒绝的回复同样通知等待方，不必等到超时
+        let request_id = message.correlation_id.clone().filter(|correlation_id| {
+            message.is_request()
+                || (rejection.is_some() && self.awaits_reply(correlation_id, &message.from))
+        });
+
+        if message.is_topic_message() {
+            let topic = message.topic.as_ref().unwrap();
+            tracing::debug!("收到主题消息: from={}, topic={}", message.from, topic);
+        } else {
+            tracing::debug!("收到点对点消息: from={}, to={}", message.from, message.to);
+        }
+
+        // 路由消息
+        let (topic, published_at) = (message.topic.clone(), message.timestamp);
+        let delivered = (!self.interceptors.is_empty()).then(|| message.clone());
+        let result = if let Some(reason) = rejection {
+            MessageResult::Rejected(reason)
+        } else if message.is_expired() {
+            if !is_topic {
+                self.metrics
+                    .record_delivery(&message.to, DeliveryOutcome::Expired);
+            }
+            MessageResult::Expired
+        } else if message.is_topic_message() {
+            self.route_topic_message(message).await
+        } else {
+            self.route_direct_message(message).await
+        };
+        self.metrics
+            .record_routed(topic.as_deref(), published_at, &result);
+        self.journal_result(journaled, is_topic, &result);
+        if let Some(message) = delivered {
+            self.interceptors.after_delivery(&message, &result).await;
+        }
+
+        match result {
+            MessageResult::Success => {
+                tracing::trace!("消息路由成功");
+            }
+            MessageResult::Queued => {
+                tracing::trace!("消息已持久化，等待投递");
+            }
+            MessageResult::Expired => {
+                tracing::debug!("消息已过期，不再投递");
+                self.fail_request(request_id, "消息已过期".to_string());
+            }
+            MessageResult::Rejected(ref reason) => {
+                self.fail_request(request_id, reason.clone());
+            }
+            MessageResult::PluginNotFound(ref target) => {
+                tracing::warn!("目标不存在: {}", target);
+                self.fail_request(request_id, format!("目标不存在: {target}"));
+            }
+            MessageResult::Failed(ref reason) => {
+                tracing::error!("消息路由失败: {}", reason);
+                self.fail_request(request_id, reason.clone());
+            }
+        }
+    }
+
+    /// 将回复（或目标的投递错误回报）交给等待中的请求，返回无需处理的其它消息
+    fn complete_request(&self, message: Message) -> Option<Message> {
+        let Some(correlation_id) = &message.correlation_id else {
+            return Some(message);
+        };
+        if message.is_request() {
+            return Some(message);
+        }
+
+        // 只接受请求目标发出的回复，其它同 ID 消息按普通消息路由
+        let pending = {
+            let mut pending_requests = self.pending_requests.lock();
+            match pending_requests.get(correlation_id) {
+                Some(pending) if pending.target == message.from => {
+                    pending_requests.remove(correlation_id)
+                }
+                _ => None,
+            }
+        };
+        let Some(pending) = pending else {
+            return Some(message);
+        };
+
+        let result = if message.msg_type.as_deref() == Some(DELIVERY_ERROR_TYPE) {
+            Err(delivery_error_reason(&message.payload))
+        } else {
+            Ok(message.payload)
+        };
+        if pending.reply.send(result).is_err() {
+            tracing::debug!("请求 {} 的等待方已取消", correlation_id);
+        }
+        None
+    }
+
+    /// 在消息日志中记录路由结果
+    fn journal_result(&self, message_id: Option<String>, is_topic: bool, result: &MessageResult) {
+        let (Some(journal), Some(message_id)) = (&self.journal, message_id) else {
+            return;
+        };
+
+        let (status, error) = match result {
+            MessageResult::Success => (DeliveryStatus::Delivered, None),
+            MessageResult::Queued => (DeliveryStatus::Queued, None),
+            MessageResult::Expired => (DeliveryStatus::Expired, None),
+            MessageResult::PluginNotFound(_) if is_topic => {
+                (DeliveryStatus::DroppedNoSubscriber, None)
+            }
+            MessageResult::PluginNotFound(target) => (
+                DeliveryStatus::Failed,
+                Some(format!("目标不存在: {target}")),
+            ),
+            MessageResult::Rejected(reason) => (DeliveryStatus::Rejected, Some(reason.clone())),
+            MessageResult::Failed(reason) => (DeliveryStatus::Failed, Some(reason.clone())),
+        };
+        journal.update(&message_id, status, error);
+    }
+
+    /// 是否有发给 `target`、以该关联 ID 等待回复的请求
+    fn awaits_reply(&self, correlation_id: &str, target: &str) -> bool {
+        self.pending_requests
+            .lock()
+            .get(correlation_id)
+            .is_some_and(|pending| pending.target == target)
+    }
+
+    /// 请求无法送达时通知等待方
+    fn fail_request(&self, correlation_id: Option<String>, reason: String) {
+        let Some(correlation_id) = correlation_id else {
+            return;
+        };
+        if let Some(pending) = self.pending_requests.lock().remove(&correlation_id) {
+            let _ = pending.reply.send(Err(reason));
+        }
+    }
+
     /// 路由点对点消息
     async fn route_direct_message(&self, message: Message) -> MessageResult {
+        if let Some(queue) = self.durable_queue_for(&message) {
+            let recipient = message.to.clone();
+            return self.route_durable(&queue, message, vec![recipient]).await;
+        }
+
         // 在 await 之前获取发送器的克隆，避免跨 await 持有锁
         let tx_opt = {
             let channels = self.plugin_channels.read();
             channels.get(&message.to).cloned()
         };
 
-        if let Some(tx) = tx_opt {
-            match tx.send(message).await {
-                Ok(_) => MessageResult::Success,
-                Err(_) => MessageResult::Failed("通道已关闭".to_string()),
+        let Some(tx) = tx_opt else {
+            self.metrics
+                .record_delivery(&message.to, DeliveryOutcome::Failed);
+            return MessageResult::PluginNotFound(message.to.clone());
+        };
+        let recipient = message.to.clone();
+        match self.deliver(&tx, &recipient, message).await {
+            Delivery::Delivered => MessageResult::Success,
+            Delivery::Pending | Delivery::Spilled => MessageResult::Queued,
+            Delivery::Dropped => {
+                MessageResult::Failed(format!("插件 {recipient} 的消息队列已满，消息已丢弃"))
             }
-        } else {
-            MessageResult::PluginNotFound(message.to.clone())
+            Delivery::Closed => MessageResult::Failed("通道已关闭".to_string()),
         }
     }
 
@@ -215,19 +780,28 @@ impl MessageRouter {
     async fn route_topic_message(&self, message: Message) -> MessageResult {
         let topic = message.topic.as_ref().expect("主题消息必须有topic字段");
 
-        // 获取订阅者列表
-        let subscribers = {
-            let subscriptions = self.topic_subscriptions.read();
-            subscriptions
-                .get(topic)
-                .map(|subs| subs.iter().cloned().collect::<Vec<_>>())
-                .unwrap_or_default()
-        };
+        // 没有订阅者时同样保留，供之后的订阅者读取
+        let retained = self.retained.read().clone();
+        if let Some(cache) = retained {
+            cache.retain(&message).await;
+        }
+
+        // 获取匹配的订阅者（多个模式命中同一订阅者时只投递一次）
+        let subscribers: Vec<String> = self
+            .topic_subscriptions
+            .read()
+            .matching_subscribers(topic)
+            .into_iter()
+            .collect();
 
         if subscribers.is_empty() {
             return MessageResult::PluginNotFound(format!("主题 '{topic}' 没有订阅者"));
         }
 
+        if let Some(queue) = self.durable_queue_for(&message) {
+            return self.route_durable(&queue, message, subscribers).await;
+        }
+
         // 在 await 之前收集所有需要的发送器
         let senders: Vec<_> = {
             let channels = self.plugin_channels.read();
@@ -242,27 +816,263 @@ impl MessageRouter {
         };
 
         let mut successful_sends = 0;
-        let mut failed_sends = 0;
+        let mut queued_sends = 0;
 
-        // 发送消息给所有订阅者
-        for (_subscriber, tx) in senders {
-            match tx.send(message.clone()).await {
-                Ok(_) => successful_sends += 1,
-                Err(_) => failed_sends += 1,
+        // 逐个订阅者入队，队列已满时按各自的溢出策略处理，不等待慢订阅者
+        for (subscriber, tx) in senders {
+            match self.deliver(&tx, &subscriber, message.clone()).await {
+                Delivery::Delivered => successful_sends += 1,
+                Delivery::Pending | Delivery::Spilled => queued_sends += 1,
+                Delivery::Dropped | Delivery::Closed => {}
             }
         }
 
-        // 统计没有找到通道的订阅者
-        let total_attempted = successful_sends + failed_sends;
-        let missing_channels = subscribers.len().saturating_sub(total_attempted);
-        failed_sends += missing_channels;
-
+        let failed_sends = subscribers.len() - successful_sends - queued_sends;
         if successful_sends > 0 {
             MessageResult::Success
+        } else if queued_sends > 0 {
+            MessageResult::Queued
         } else {
             MessageResult::Failed(format!("所有订阅者都发送失败 ({failed_sends})"))
         }
     }
+
+    /// 将消息放入接收方的队列并记录投递结果
+    async fn deliver(&self, tx: &PluginSender, recipient: &str, message: Message) -> Delivery {
+        let delivery = self.enqueue(tx, recipient, message).await;
+        let outcome = match delivery {
+            Delivery::Delivered => DeliveryOutcome::Delivered,
+            Delivery::Spilled => DeliveryOutcome::Queued,
+            Delivery::Dropped => DeliveryOutcome::Dropped,
+            Delivery::Closed => DeliveryOutcome::Failed,
+            // 由后台等待任务记录
+            Delivery::Pending => return delivery,
+        };
+        self.metrics.record_delivery(recipient, outcome);
+        delivery
+    }
+
+    /// 将消息放入接收方的队列，队列已满时按其溢出策略处理
+    async fn enqueue(&self, tx: &PluginSender, recipient: &str, message: Message) -> Delivery {
+        // 等待中的消息之后到达的消息不能越过它们直接入队
+        if tx.config().overflow == OverflowPolicy::Block {
+            return self.enqueue_or_wait(tx, recipient, message);
+        }
+
+        let message = match tx.try_send(message) {
+            Ok(()) => return Delivery::Delivered,
+            Err(TrySendError::Closed(_)) => return Delivery::Closed,
+            Err(TrySendError::Full(message)) => message,
+        };
+
+        let config = tx.config();
+        match config.overflow {
+            OverflowPolicy::DropNewest => {
+                tx.record_dropped();
+                tracing::warn!("插件 {} 的消息队列已满，丢弃消息 {}", recipient, message.id);
+                Delivery::Dropped
+            }
+            OverflowPolicy::DropOldest => match tx.send_displacing_oldest(message) {
+                Ok(displaced) => {
+                    if let Some(displaced) = displaced {
+                        tracing::warn!(
+                            "插件 {} 的消息队列已满，丢弃最旧的消息 {}",
+                            recipient,
+                            displaced.id
+                        );
+                    }
+                    Delivery::Delivered
+                }
+                Err(_) => Delivery::Closed,
+            },
+            OverflowPolicy::Block => self.enqueue_or_wait(tx, recipient, message),
+            OverflowPolicy::Spill => self.spill(tx, recipient, message).await,
+        }
+    }
+
+    /// `Block` 策略：队列已满时放入接收方的溢出缓冲，由该接收方唯一的等待任务按顺序入队
+    fn enqueue_or_wait(&self, tx: &PluginSender, recipient: &str, message: Message) -> Delivery {
+        match tx.send_or_wait(message) {
+            BlockingSend::Queued => Delivery::Delivered,
+            BlockingSend::Closed(_) => Delivery::Closed,
+            BlockingSend::Full(message) => {
+                tx.record_dropped();
+                tracing::warn!(
+                    "插件 {} 的消息队列与等待缓冲都已满，丢弃消息 {}",
+                    recipient,
+                    message.id
+                );
+                Delivery::Dropped
+            }
+            BlockingSend::Waiting { start_waiter } => {
+                if start_waiter {
+                    self.spawn_waiter(tx.clone(), recipient.to_string());
+                }
+                Delivery::Pending
+            }
+        }
+    }
+
+    /// 启动接收方的等待任务，记录等待消息的最终投递结果
+    fn spawn_waiter(&self, tx: PluginSender, recipient: String) {
+        let metrics = self.metrics.clone();
+        let journal = self.journal.clone();
+        let block_timeout = tx.config().block_timeout;
+        tokio::spawn(async move {
+            tx.drain_waiting(|message, outcome| {
+                let (outcome, status, error) = match outcome {
+                    WaitOutcome::Queued => {
+                        (DeliveryOutcome::Delivered, DeliveryStatus::Delivered, None)
+                    }
+                    WaitOutcome::TimedOut => {
+                        tracing::warn!(
+                            "插件 {} 的消息队列持续已满（{:?}），丢弃消息 {}",
+                            recipient,
+                            block_timeout,
+                            message.id
+                        );
+                        let error = format!("插件 {recipient} 的消息队列已满，消息已丢弃");
+                        (
+                            DeliveryOutcome::Dropped,
+                            DeliveryStatus::Failed,
+                            Some(error),
+                        )
+                    }
+                    WaitOutcome::Closed => (
+                        DeliveryOutcome::Failed,
+                        DeliveryStatus::Failed,
+                        Some("通道已关闭".to_string()),
+                    ),
+                };
+                metrics.record_delivery(&recipient, outcome);
+                // 主题消息的日志状态覆盖所有订阅者，只更新点对点消息
+                if let Some(journal) = journal.as_ref().filter(|_| !message.is_topic_message()) {
+                    journal.update(&message.id, status, error);
+                }
+            })
+            .await;
+        });
+    }
+
+    /// 将消息写入持久投递队列，等接收方队列有空位时由重投任务投递
+    async fn spill(&self, tx: &PluginSender, recipient: &str, mut message: Message) -> Delivery {
+        let queue = self.durable_queue.read().clone();
+        let Some(queue) = queue else {
+            tx.record_dropped();
+            tracing::warn!(
+                "插件 {} 的消息队列已满且未启用持久投递，丢弃消息 {}",
+                recipient,
+                message.id
+            );
+            return Delivery::Dropped;
+        };
+
+        message.durable = true;
+        match queue.enqueue(&message, recipient, false).await {
+            Ok(()) => {
+                tx.record_spilled();
+                Delivery::Spilled
+            }
+            Err(e) => {
+                tx.record_dropped();
+                tracing::warn!(
+                    "插件 {} 的消息 {} 写入磁盘失败: {}",
+                    recipient,
+                    message.id,
+                    e
+                );
+                Delivery::Dropped
+            }
+        }
+    }
+}
+
+/// 消息放入接收方队列的结果
+enum Delivery {
+    /// 已入队
+    Delivered,
+    /// 队列已满，已放入溢出缓冲等待空位
+    Pending,
+    /// 队列已满，已写入持久投递队列
+    Spilled,
+    /// 队列已满，已丢弃
+    Dropped,
+    /// 接收方已关闭
+    Closed,
+}
+
+impl MessageRouter {
+    /// 消息需要持久投递时返回队列
+    fn durable_queue_for(&self, message: &Message) -> Option<DurableQueue> {
+        self.durable_queue
+            .read()
+            .as_ref()
+            .filter(|queue| queue.is_durable(message))
+            .cloned()
+    }
+
+    /// 持久投递：先保存再尝试投递，接收方未加载或通道已满时留给重投任务
+    async fn route_durable(
+        &self,
+        queue: &DurableQueue,
+        mut message: Message,
+        recipients: Vec<String>,
+    ) -> MessageResult {
+        message.durable = true;
+
+        let mut delivered = 0;
+        let mut queued = 0;
+        let mut errors = Vec::new();
+        for recipient in recipients {
+            let tx = self.plugin_channels.read().get(&recipient).cloned();
+            if let Err(e) = queue.enqueue(&message, &recipient, tx.is_some()).await {
+                // 保存失败时仍尽力投递一次
+                tracing::warn!("保存持久消息 {} 失败: {}", message.id, e);
+                if tx.is_some_and(|tx| tx.try_send(message.clone()).is_ok()) {
+                    delivered += 1;
+                    self.metrics
+                        .record_delivery(&recipient, DeliveryOutcome::Delivered);
+                } else {
+                    errors.push(format!("{recipient}: {e}"));
+                    self.metrics
+                        .record_delivery(&recipient, DeliveryOutcome::Failed);
+                }
+                continue;
+            }
+
+            let outcome = match tx {
+                Some(tx) if tx.try_send(message.clone()).is_ok() => DeliveryOutcome::Delivered,
+                Some(_) => {
+                    if let Err(e) = queue.defer(&message.id, &recipient).await {
+                        tracing::warn!("推迟持久消息 {} 失败: {}", message.id, e);
+                    }
+                    DeliveryOutcome::Queued
+                }
+                None => DeliveryOutcome::Queued,
+            };
+            match outcome {
+                DeliveryOutcome::Delivered => delivered += 1,
+                _ => queued += 1,
+            }
+            self.metrics.record_delivery(&recipient, outcome);
+        }
+
+        if queued > 0 {
+            MessageResult::Queued
+        } else if delivered > 0 {
+            MessageResult::Success
+        } else {
+            MessageResult::Failed(format!("持久消息投递失败: {}", errors.join("; ")))
+        }
+    }
+}
+
+/// 从投递错误回报的负载中取出错误原因
+fn delivery_error_reason(payload: &[u8]) -> String {
+    serde_json::from_slice::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|report| report["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(payload).into_owned())
 }
 
 #[cfg(test)]
@@ -311,4 +1121,441 @@ mod tests {
         let subscribers = handle.get_topic_subscribers("topic1");
         assert_eq!(subscribers.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_request_response() {
+        let (handle, router) = create_message_bus(100);
+        let mut responder_rx = handle.register_plugin("responder".to_string());
+        tokio::spawn(router.run());
+
+        // 回显请求负载
+        let responder = handle.clone();
+        tokio::spawn(async move {
+            while let Some(request) = responder_rx.recv().await {
+                assert!(request.is_request());
+                assert!(responder
+                    .is_pending_request(request.correlation_id.as_deref().unwrap(), "responder"));
+                let mut payload = b"re: ".to_vec();
+                payload.extend(request.payload);
+                let reply = Message::response(
+                    "responder".to_string(),
+                    request.from,
+                    request.correlation_id.unwrap(),
+                    payload,
+                );
+                responder.send_message(reply).await.unwrap();
+            }
+        });
+
+        let reply = handle
+            .request(
+                "host",
+                "responder",
+                b"ping".to_vec(),
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply, b"re: ping");
+        assert!(handle.pending_requests.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_and_missing_target() {
+        let (handle, router) = create_message_bus(100);
+        let _silent_rx = handle.register_plugin("silent".to_string());
+        tokio::spawn(router.run());
+
+        let err = handle
+            .request("host", "missing", Vec::new(), Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::TargetNotFound(_)));
+        assert_eq!(err.code(), "not_found");
+
+        let err = handle
+            .request("host", "silent", Vec::new(), Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::Timeout { timeout_ms: 50, .. }));
+        assert!(handle.pending_requests.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_records_delivery_status() {
+        use crate::config::JournalConfig;
+        use crate::storage::{MessageLogQuery, Storage};
+
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let (handle, mut router) = create_message_bus(100);
+        router.set_journal(MessageJournal::spawn(
+            storage.clone(),
+            &JournalConfig::default(),
+        ));
+        let _rx = handle.register_plugin("receiver".to_string());
+        tokio::spawn(router.run());
+
+        let delivered = Message::new("app".into(), "receiver".into(), b"hi".to_vec());
+        let missing = Message::new("app".into(), "missing".into(), Vec::new());
+        let dropped = Message::new_topic("app".into(), "health.cpu".into(), Vec::new());
+        let ids = [
+            (delivered.id.clone(), "delivered"),
+            (missing.id.clone(), "failed"),
+            (dropped.id.clone(), "dropped_no_subscriber"),
+        ];
+        for message in [delivered, missing, dropped] {
+            handle.send_message(message).await.unwrap();
+        }
+
+        // 日志由后台任务异步写入
+        let mut entries = Vec::new();
+        for _ in 0..50 {
+            entries = storage
+                .search_message_log(&MessageLogQuery {
+                    plugin_id: Some("app".to_string()),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            if entries.len() == 3 && entries.iter().all(|entry| entry.status != "sent") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        for (id, status) in ids {
+            let entry = entries.iter().find(|entry| entry.message_id == id).unwrap();
+            assert_eq!(entry.status, status);
+        }
+        let topic_entry = entries.iter().find(|entry| entry.topic.is_some()).unwrap();
+        assert_eq!(topic_entry.topic.as_deref(), Some("health.cpu"));
+        let failed = entries
+            .iter()
+            .find(|entry| entry.status == "failed")
+            .unwrap();
+        assert!(failed.error.as_deref().unwrap().contains("missing"));
+    }
+
+    #[tokio::test]
+    async fn test_durable_message_waits_for_recipient() {
+        use crate::config::DurableQueueConfig;
+        use crate::storage::Storage;
+
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let (handle, router) = create_message_bus(100);
+        handle.set_durable_queue(DurableQueue::new(
+            storage,
+            DurableQueueConfig {
+                poll_interval_ms: 10,
+                ..Default::default()
+            },
+        ));
+        tokio::spawn(router.run());
+
+        // 接收方尚未加载，消息保存在队列中
+        let message = Message::new("app".into(), "late".into(), b"job".to_vec()).with_durable();
+        handle.send_message(message.clone()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut rx = handle.register_plugin("late".to_string());
+        let received = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.id, message.id);
+        assert!(received.durable);
+
+        handle.ack_durable(&message.id, "late").await;
+        let queue = handle.durable_queue().unwrap();
+        assert_eq!(
+            queue.nack(&message.id, "late", "late").await.unwrap(),
+            NackOutcome::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_priority_order_and_expired_messages() {
+        use super::super::message::MessagePriority;
+
+        let (handle, router) = create_message_bus(100);
+        let mut rx = handle.register_plugin("receiver".to_string());
+
+        // 路由器启动前积压的消息按优先级投递，过期消息被丢弃
+        let message = |payload: &str, priority| {
+            Message::new("app".into(), "receiver".into(), payload.as_bytes().to_vec())
+                .with_priority(priority)
+        };
+        handle
+            .send_message(message("low", MessagePriority::Low))
+            .await
+            .unwrap();
+        handle
+            .send_message(
+                message("stale", MessagePriority::Critical)
+                    .with_expires_at(chrono::Utc::now() - chrono::Duration::seconds(1)),
+            )
+            .await
+            .unwrap();
+        handle
+            .send_message(message("normal", MessagePriority::Normal))
+            .await
+            .unwrap();
+        handle
+            .send_message(message("critical", MessagePriority::Critical))
+            .await
+            .unwrap();
+        tokio::spawn(router.run());
+
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            let received = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            order.push(String::from_utf8(received.payload).unwrap());
+        }
+        assert_eq!(order, vec!["critical", "normal", "low"]);
+        assert_eq!(handle.expired_messages(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_full_subscriber_does_not_block_fan_out() {
+        let (handle, router) = create_message_bus(100);
+        let mut slow_rx = handle.register_plugin_with_queue(
+            "slow".to_string(),
+            PluginQueueConfig {
+                depth: 2,
+                overflow: OverflowPolicy::Block,
+                block_timeout: Duration::from_secs(5),
+            },
+        );
+        let _lossy_rx = handle.register_plugin_with_queue(
+            "lossy".to_string(),
+            PluginQueueConfig {
+                depth: 1,
+                overflow: OverflowPolicy::DropNewest,
+                ..Default::default()
+            },
+        );
+        let mut fast_rx = handle.register_plugin("fast".to_string());
+        for plugin in ["slow", "lossy", "fast"] {
+            handle.subscribe_topic(plugin, "metrics.#");
+        }
+        tokio::spawn(router.run());
+
+        // slow 与 lossy 都不读取消息，fast 仍能收到全部消息
+        for i in 0..4 {
+            let message = Message::new_topic("app".into(), "metrics.cpu".into(), vec![i]);
+            handle.send_message(message).await.unwrap();
+        }
+        for i in 0..4 {
+            let received = tokio::time::timeout(Duration::from_secs(1), fast_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(received.payload, vec![i]);
+        }
+
+        let lossy = handle.queue_stats("lossy").unwrap();
+        assert_eq!((lossy.depth, lossy.dropped), (1, 3));
+        assert_eq!(handle.queue_stats("slow").unwrap().depth, 2);
+        assert_eq!(handle.all_queue_stats().len(), 3);
+
+        // slow 取走消息后，等待的消息按发布顺序入队
+        let mut payloads = Vec::new();
+        for _ in 0..4 {
+            let received = tokio::time::timeout(Duration::from_secs(1), slow_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            payloads.extend(received.payload);
+        }
+        assert_eq!(payloads, vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_new_subscriber_receives_retained_message() {
+        use crate::config::RetainedTopicConfig;
+        use crate::kernel::message::RETAINED_KEY;
+
+        let (handle, router) = create_message_bus(100);
+        handle.set_retained_cache(RetainedCache::new(vec![RetainedTopicConfig {
+            pattern: "system.stats".to_string(),
+            ..Default::default()
+        }]));
+        tokio::spawn(router.run());
+
+        // 没有订阅者时消息仍被保留
+        for payload in [b"old".to_vec(), b"new".to_vec()] {
+            let message = Message::new_topic("monitor".into(), "system.stats".into(), payload);
+            handle.send_message(message).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(handle.retained_messages("system.#").len(), 1);
+
+        let mut rx = handle.register_plugin("widget".to_string());
+        handle.subscribe_topic("widget", "system.+");
+        let received = rx.try_recv().unwrap();
+        assert_eq!(received.payload, b"new");
+        assert_eq!(received.metadata[RETAINED_KEY], "true");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stats_track_topics_plugins_and_queues() {
+        let (handle, router) = create_message_bus(100);
+        let _rx = handle.register_plugin_with_queue(
+            "dashboard".to_string(),
+            PluginQueueConfig {
+                depth: 1,
+                overflow: OverflowPolicy::DropNewest,
+                ..Default::default()
+            },
+        );
+        handle.subscribe_topic("dashboard", "system.#");
+        tokio::spawn(router.run());
+
+        for _ in 0..2 {
+            let message = Message::new_topic("monitor".into(), "system.stats".into(), vec![]);
+            handle.send_message(message).await.unwrap();
+        }
+        let message = Message::new("monitor".into(), "missing".into(), vec![]);
+        handle.send_message(message).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let stats = handle.stats();
+        assert_eq!(stats.totals.published, 3);
+        assert_eq!(stats.totals.failed, 2);
+        let topic = stats.topics["system.stats"];
+        assert_eq!((topic.published, topic.delivered, topic.failed), (2, 1, 1));
+
+        let dashboard = &stats.plugins["dashboard"];
+        assert_eq!(
+            (dashboard.counters.delivered, dashboard.counters.dropped),
+            (1, 1)
+        );
+        assert_eq!(dashboard.queue.unwrap().depth, 1);
+        assert_eq!(dashboard.subscriptions, 1);
+        assert_eq!(stats.plugins["monitor"].counters.published, 3);
+        assert_eq!(stats.plugins["missing"].counters.failed, 1);
+        assert_eq!(stats.latency.count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_modify_reject_and_observe_delivery() {
+        use crate::kernel::interceptor::{InterceptFuture, Interception, MessageInterceptor};
+
+        /// 脱敏 `patient_id` 元数据，拒绝没有负载的消息，并记录投递结果
+        #[derive(Default)]
+        struct Guard {
+            delivered: Mutex<Vec<String>>,
+        }
+
+        impl MessageInterceptor for Guard {
+            fn name(&self) -> &str {
+                "guard"
+            }
+
+            fn before_route<'a>(
+                &'a self,
+                message: &'a Message,
+            ) -> InterceptFuture<'a, Interception> {
+                Box::pin(async move {
+                    if message.payload.is_empty() {
+                        return Interception::Reject("empty payload".to_string());
+                    }
+                    if !message.metadata.contains_key("patient_id") {
+                        return Interception::Pass;
+                    }
+                    let redacted = message
+                        .clone()
+                        .with_metadata("patient_id".to_string(), "***".to_string());
+                    Interception::Modify(Box::new(redacted))
+                })
+            }
+
+            fn after_delivery<'a>(
+                &'a self,
+                message: &'a Message,
+                result: &'a MessageResult,
+            ) -> InterceptFuture<'a, ()> {
+                Box::pin(async move {
+                    if matches!(result, MessageResult::Success) {
+                        self.delivered.lock().push(message.id.clone());
+                    }
+                })
+            }
+        }
+
+        let (handle, mut router) = create_message_bus(100);
+        let guard = Arc::new(Guard::default());
+        router.add_interceptor(guard.clone());
+        let mut rx = handle.register_plugin("recorder".to_string());
+        tokio::spawn(router.run());
+
+        let message = Message::new("app".into(), "recorder".into(), b"bpm=72".to_vec())
+            .with_metadata("patient_id".to_string(), "p-42".to_string());
+        let id = message.id.clone();
+        handle.send_message(message).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.metadata["patient_id"], "***");
+
+        // 被拒绝的请求立即失败，不投递给接收方
+        let err = handle
+            .request("app", "recorder", vec![], Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("empty payload"));
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(*guard.delivered.lock(), vec![id]);
+        assert_eq!(handle.stats().totals.rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_signed_messages_on_protected_topics() {
+        use crate::config::SigningConfig;
+        use crate::identity::IdentityManager;
+
+        let (handle, mut router) = create_message_bus(100);
+        let config = SigningConfig {
+            enabled: false,
+            protected_topics: vec!["health.#".to_string()],
+        };
+        let signer =
+            MessageSigner::new(Arc::new(IdentityManager::new().unwrap()), &config).unwrap();
+        handle.set_signer(signer.clone());
+        router.add_interceptor(Arc::new(signer));
+        let mut rx = handle.register_plugin("recorder".to_string());
+        handle.subscribe_topic("recorder", "#");
+        tokio::spawn(router.run());
+
+        // 经 Handle 发送的消息由内核签名，接收方看到已验证的来源
+        let signed = Message::new_topic("sensor".into(), "health.bpm".into(), b"72".to_vec());
+        handle.send_message(signed).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(received.signature.is_some());
+        assert!(received.verified_from);
+
+        // 绕过签名直接写入通道的消息在受保护主题上被拒绝，其它主题照常投递但未验证
+        let sender = handle.get_sender();
+        let forged = Message::new_topic("sensor".into(), "health.bpm".into(), b"180".to_vec());
+        sender.send(forged).await.unwrap();
+        let plain = Message::new_topic("sensor".into(), "system.stats".into(), b"{}".to_vec());
+        sender.send(plain).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.topic.as_deref(), Some("system.stats"));
+        assert!(!received.verified_from);
+        assert!(rx.try_recv().is_err());
+        assert_eq!(handle.stats().totals.rejected, 1);
+    }
 }
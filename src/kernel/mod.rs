@@ -2,21 +2,52 @@
 //!
 //! 负责插件管理和消息总线
 
+pub mod bus_metrics;
 pub mod dependency_resolver;
+pub mod durable_queue;
 pub mod host_functions;
+pub mod hot_reload;
+pub mod interceptor;
+pub mod journal;
+pub mod lifecycle;
+pub mod load_report;
 pub mod manifest;
 pub mod message;
 pub mod message_bus;
+pub mod package;
+pub mod permissions;
+pub mod plugin_config;
 pub mod plugin_loader;
+pub mod plugin_queue;
+pub mod priority_queue;
+pub mod registry;
+pub mod retained;
+pub mod scheduler;
+pub mod schema_registry;
+pub mod signing;
+pub mod topic_index;
 
-pub use plugin_loader::PluginInfo;
+pub use plugin_loader::{PluginCallError, PluginInfo};
 
-use crate::config::Config;
+/// 内核版本（插件以 `min_kernel_version` 声明兼容的版本）
+pub const KERNEL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+use crate::config::{Config, SchemaValidation};
 use crate::identity::IdentityManager;
-use crate::storage::Storage;
+use crate::storage::{DeadLetter, MessageLogEntry, MessageLogQuery, Storage};
 use anyhow::{anyhow, Result};
+use bus_metrics::{run_stats_snapshots, BusStats};
+use durable_queue::DurableQueue;
+use interceptor::MessageInterceptor;
+use journal::MessageJournal;
+use lifecycle::PluginState;
+use load_report::LoadReport;
 use message_bus::{create_message_bus, MessageBusHandle, MessageRouter};
-use plugin_loader::PluginLoader;
+use permissions::PermissionSet;
+use plugin_loader::{PluginLoader, RuntimeLimits};
+use retained::RetainedCache;
+use schema_registry::TopicSchema;
+use signing::MessageSigner;
 use std::sync::Arc;
 
 pub struct Kernel {
@@ -30,10 +61,22 @@ pub struct Kernel {
     storage: Arc<Storage>,
     /// 身份管理器
     identity: Arc<IdentityManager>,
+    /// 保存总线统计快照的间隔（秒，0 表示不保存）
+    stats_snapshot_secs: u64,
+    /// 启动时自动加载插件的报告
+    load_report: LoadReport,
 }
 
 impl Kernel {
     pub async fn new(config: Config) -> Result<Self> {
+        Self::with_interceptors(config, Vec::new()).await
+    }
+
+    /// 创建 Kernel，并在路由器上注册消息拦截器（在签名验证与 schema 校验之后、插件拦截器之前执行）
+    pub async fn with_interceptors(
+        config: Config,
+        interceptors: Vec<Arc<dyn MessageInterceptor>>,
+    ) -> Result<Self> {
         tracing::info!("正在初始化内核...");
 
         // 创建存储实例
@@ -83,7 +126,56 @@ impl Kernel {
 
         // 创建新的消息系统
         tracing::info!("正在创建消息总线...");
-        let (message_bus_handle, message_router) = create_message_bus(1000);
+        let (message_bus_handle, mut message_router) =
+            create_message_bus(config.message_bus.buffer_size);
+        let message_bus_handle = message_bus_handle.with_send_timeout(
+            std::time::Duration::from_millis(config.message_bus.send_timeout_ms),
+        );
+
+        // 启用消息日志
+        if config.message_bus.journal.enabled {
+            message_router.set_journal(MessageJournal::spawn(
+                storage.clone(),
+                &config.message_bus.journal,
+            ));
+        }
+
+        // 启用消息签名，签名验证在所有拦截器之后执行，检查修改后的最终消息
+        let signing = &config.message_bus.signing;
+        if signing.enabled || !signing.protected_topics.is_empty() {
+            let signer = MessageSigner::new(identity.clone(), signing)?;
+            message_bus_handle.set_signer(signer.clone());
+            message_router.set_verifier(Arc::new(signer));
+        }
+
+        // 按插件声明的主题 schema 校验负载
+        if config.message_bus.schema_validation != SchemaValidation::Off {
+            message_router.add_interceptor(Arc::new(
+                message_bus_handle
+                    .schemas()
+                    .validator(config.message_bus.schema_validation),
+            ));
+        }
+
+        // 注册消息拦截器
+        for interceptor in interceptors {
+            message_router.add_interceptor(interceptor);
+        }
+
+        // 启用持久投递
+        if config.message_bus.durable.enabled {
+            message_bus_handle.set_durable_queue(DurableQueue::new(
+                storage.clone(),
+                config.message_bus.durable.clone(),
+            ));
+        }
+
+        // 启用保留消息
+        if !config.message_bus.retained.is_empty() {
+            let cache =
+                RetainedCache::load(storage.clone(), config.message_bus.retained.clone()).await?;
+            message_bus_handle.set_retained_cache(cache);
+        }
 
         // 获取消息发送器用于插件加载器
         let msg_sender = message_bus_handle.get_sender();
@@ -96,16 +188,27 @@ impl Kernel {
         // 为插件加载器设置消息总线句柄
         plugin_loader.set_message_bus(message_bus_handle.clone());
 
-        // 自动加载插件
-        if config.plugins.auto_load {
+        // 应用插件调用超时、内存限制与默认权限
+        plugin_loader.set_default_limits(RuntimeLimits::from(&config.plugins));
+        plugin_loader
+            .set_default_permissions(PermissionSet::parse(&config.plugins.default_permissions)?);
+
+        // 按依赖顺序自动加载插件
+        let load_report = if config.plugins.auto_load {
             tracing::info!("正在扫描并加载插件...");
-            let loaded_plugins = plugin_loader
-                .load_plugins_from_config(&config.plugins.directory, &config.plugins.enabled)?;
-            tracing::info!(
-                "已自动加载 {} 个插件: {:?}",
-                loaded_plugins.len(),
-                loaded_plugins
-            );
+            let report = plugin_loader.load_plugins_with_dependencies(
+                &config.plugins.directory,
+                &config.plugins.enabled,
+            )?;
+            report.log();
+            report
+        } else {
+            LoadReport::default()
+        };
+
+        // 清理已卸载插件遗留的主题订阅
+        if let Err(e) = plugin_loader.reconcile_subscriptions(&config.plugins.directory) {
+            tracing::warn!("清理插件订阅失败: {}", e);
         }
 
         tracing::info!("内核初始化完成");
@@ -115,6 +218,8 @@ impl Kernel {
             message_router: Some(message_router),
             storage,
             identity,
+            stats_snapshot_secs: config.message_bus.stats_snapshot_secs,
+            load_report,
         })
     }
 
@@ -131,9 +236,6 @@ impl Kernel {
         // 获取关闭信号发送器（从新的 handle 获取）
         let shutdown_tx = self.message_bus_handle.get_shutdown_sender();
 
-        // 保存必要的信息以供后续使用
-        let plugin_count = self.plugin_count();
-
         // 取出消息路由器
         let router = self
             .message_router
@@ -145,6 +247,15 @@ impl Kernel {
             router.run().await;
         });
 
+        // 定期保存总线统计，供 `bus stats` 命令读取
+        let stats_task = (self.stats_snapshot_secs > 0).then(|| {
+            tokio::spawn(run_stats_snapshots(
+                self.message_bus_handle.clone(),
+                self.storage.clone(),
+                std::time::Duration::from_secs(self.stats_snapshot_secs),
+            ))
+        });
+
         // 等待关闭信号
         tokio::select! {
             _ = tokio::signal::ctrl_c() => {
@@ -155,16 +266,18 @@ impl Kernel {
             }
         }
 
+        // 先关闭插件（插件关闭时仍可能使用消息总线）
+        self.shutdown().await?;
+
+        if let Some(stats_task) = stats_task {
+            stats_task.abort();
+        }
+
         // 发送关闭信号给消息总线
         if let Err(e) = shutdown_tx.send(()).await {
             tracing::warn!("发送关闭信号失败: {}", e);
         }
 
-        // 执行关闭清理
-        tracing::info!("正在关闭内核...");
-        tracing::info!("已卸载 {} 个插件", plugin_count);
-        tracing::info!("内核已关闭");
-
         // 等待消息总线任务完成
         match tokio::time::timeout(std::time::Duration::from_secs(5), message_bus_handle).await {
             Ok(Ok(_)) => tracing::info!("消息总线已正常关闭"),
@@ -237,6 +350,21 @@ impl Kernel {
         self.plugin_loader.discover_plugins(plugin_dir)
     }
 
+    /// 按依赖顺序加载插件（`target_plugins` 为空时加载目录中的所有插件）
+    pub fn load_plugins_with_dependencies(
+        &mut self,
+        plugin_dir: &std::path::Path,
+        target_plugins: &[String],
+    ) -> Result<LoadReport> {
+        self.plugin_loader
+            .load_plugins_with_dependencies(plugin_dir, target_plugins)
+    }
+
+    /// 启动时自动加载插件的报告
+    pub fn load_report(&self) -> &LoadReport {
+        &self.load_report
+    }
+
     /// 扫描并加载插件
     pub fn scan_and_load_plugins(&mut self, plugin_dir: &std::path::Path) -> Result<Vec<String>> {
         self.plugin_loader.scan_and_load_plugins(plugin_dir)
@@ -252,6 +380,57 @@ impl Kernel {
         self.plugin_loader.unload_plugin(plugin_name)
     }
 
+    /// 热重载插件（新实例初始化失败时保留旧实例）
+    pub fn reload_plugin(&mut self, plugin_name: &str) -> Result<()> {
+        self.plugin_loader.reload_plugin(plugin_name)
+    }
+
+    /// 从指定文件加载的插件
+    pub fn plugin_for_path(&self, path: &std::path::Path) -> Option<&str> {
+        self.plugin_loader.plugin_for_path(path)
+    }
+
+    /// 暂停插件
+    pub fn pause_plugin(&mut self, plugin_name: &str) -> Result<()> {
+        self.plugin_loader.pause_plugin(plugin_name)
+    }
+
+    /// 恢复插件
+    pub fn resume_plugin(&mut self, plugin_name: &str) -> Result<()> {
+        self.plugin_loader.resume_plugin(plugin_name)
+    }
+
+    /// 获取插件生命周期状态
+    pub fn plugin_state(&self, plugin_name: &str) -> Option<PluginState> {
+        self.plugin_loader.plugin_state(plugin_name)
+    }
+
+    /// 获取插件的定时任务统计
+    pub fn tick_stats(&self, plugin_name: &str) -> Option<scheduler::TickStats> {
+        self.plugin_loader.tick_stats(plugin_name)
+    }
+
+    /// 查询消息日志
+    pub async fn search_messages(&self, query: &MessageLogQuery) -> Result<Vec<MessageLogEntry>> {
+        self.storage.search_message_log(query).await
+    }
+
+    /// 查看死信
+    pub async fn dead_letters(
+        &self,
+        recipient: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<DeadLetter>> {
+        self.storage.list_dead_letters(recipient, limit).await
+    }
+
+    /// 重放死信，死信不存在时返回 false
+    pub async fn replay_dead_letter(&self, id: i64) -> Result<bool> {
+        self.storage
+            .replay_dead_letter(id, chrono::Utc::now().timestamp_millis())
+            .await
+    }
+
     /// 获取存储引用
     pub fn get_storage(&self) -> &Arc<Storage> {
         &self.storage
@@ -262,6 +441,16 @@ impl Kernel {
         &self.identity
     }
 
+    /// 消息总线统计
+    pub fn bus_stats(&self) -> BusStats {
+        self.message_bus_handle.stats()
+    }
+
+    /// 已加载插件声明的主题 schema
+    pub fn topic_schemas(&self) -> Vec<TopicSchema> {
+        self.message_bus_handle.schemas().list()
+    }
+
     /// 获取消息总线句柄
     pub fn get_message_bus_handle(&self) -> &MessageBusHandle {
         &self.message_bus_handle
@@ -276,8 +465,9 @@ impl Kernel {
     pub async fn shutdown(&mut self) -> Result<()> {
         tracing::info!("正在关闭内核...");
 
-        // 这里可以添加资源清理逻辑
-        // 比如关闭数据库连接、停止消息总线等
+        // 按依赖的逆序关闭插件
+        let unloaded = self.plugin_loader.shutdown_all();
+        tracing::info!("已卸载 {} 个插件: {:?}", unloaded.len(), unloaded);
 
         tracing::info!("内核已关闭");
         Ok(())
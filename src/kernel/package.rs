@@ -0,0 +1,517 @@
+//! 插件包（.mkpkg）
+//!
+//! 插件包是 gzip 压缩的 tar 归档，包含：
+//! - `package.toml`：包索引（名称、版本、发布者地址及每个文件的 keccak256 哈希）
+//! - `package.sig`：发布者对 `package.toml` 的签名（EIP-191，十六进制）
+//! - `manifest.toml`、`<name>.wasm` 以及 `ui/` 下的界面资源
+//!
+//! 安装前校验文件哈希、签名以及发布者是否在受信任列表中
+
+use alloy::primitives::{keccak256, Address};
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::manifest::PluginManifest;
+use crate::config::TrustedPublisher;
+use crate::identity::IdentityManager;
+
+/// 插件包扩展名
+pub const PACKAGE_EXTENSION: &str = "mkpkg";
+/// 包索引文件名
+pub const INDEX_FILE: &str = "package.toml";
+/// 签名文件名
+pub const SIGNATURE_FILE: &str = "package.sig";
+/// 插件清单文件名
+const MANIFEST_FILE: &str = "manifest.toml";
+/// 界面资源目录
+const UI_DIR: &str = "ui";
+
+/// 包索引
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageIndex {
+    pub package: PackageMetadata,
+    /// 包内文件路径 -> keccak256 哈希（十六进制）
+    pub files: BTreeMap<String, String>,
+}
+
+/// 包元数据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageMetadata {
+    /// 插件名称
+    pub name: String,
+    /// 插件版本
+    pub version: String,
+    /// 发布者地址
+    pub publisher: String,
+}
+
+/// 插件包
+#[derive(Debug, Clone)]
+pub struct PluginPackage {
+    /// 包索引
+    pub index: PackageIndex,
+    /// `package.toml` 原始内容（签名覆盖的字节）
+    index_bytes: Vec<u8>,
+    /// 发布者签名
+    signature: Vec<u8>,
+    /// 包内文件（不含索引与签名）
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl PluginPackage {
+    /// 从插件目录打包并以身份主密钥签名
+    ///
+    /// 打包 `manifest.toml`、插件 wasm（目录下或 cargo 的 release 输出）以及 `ui/` 目录
+    pub fn build(plugin_dir: &Path, identity: &IdentityManager) -> Result<Self> {
+        let manifest_path = plugin_dir.join(MANIFEST_FILE);
+        let manifest = PluginManifest::from_file(&manifest_path)?;
+        let name = manifest.plugin.name.clone();
+
+        let mut files = BTreeMap::new();
+        files.insert(MANIFEST_FILE.to_string(), fs::read(&manifest_path)?);
+
+        let wasm_path = find_wasm(plugin_dir, &name)
+            .ok_or_else(|| anyhow!("未找到插件 {} 的 wasm 文件: {}", name, plugin_dir.display()))?;
+        files.insert(format!("{name}.wasm"), fs::read(&wasm_path)?);
+
+        let ui_dir = plugin_dir.join(UI_DIR);
+        for entry in WalkDir::new(&ui_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let relative = entry.path().strip_prefix(plugin_dir)?;
+            let path = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(path, fs::read(entry.path())?);
+        }
+
+        let index = PackageIndex {
+            package: PackageMetadata {
+                name,
+                version: manifest.plugin.version,
+                publisher: identity.get_master_address().to_string(),
+            },
+            files: files
+                .iter()
+                .map(|(path, data)| (path.clone(), hex::encode(keccak256(data))))
+                .collect(),
+        };
+        let index_bytes = toml::to_string_pretty(&index)?.into_bytes();
+        let signature = identity.sign_message(keccak256(&index_bytes).as_slice())?;
+
+        Ok(Self {
+            index,
+            index_bytes,
+            signature,
+            files,
+        })
+    }
+
+    /// 读取插件包（不校验，校验见 [`PluginPackage::verify`]）
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            fs::File::open(path).with_context(|| format!("无法打开插件包: {}", path.display()))?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+        let mut index_bytes = None;
+        let mut signature = None;
+        let mut files = BTreeMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let entry_path = entry.path()?.into_owned();
+            let path = package_path(&entry_path)?;
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+
+            match path.as_str() {
+                INDEX_FILE => index_bytes = Some(data),
+                SIGNATURE_FILE => {
+                    let signature_hex = String::from_utf8(data)?;
+                    signature = Some(hex::decode(signature_hex.trim())?);
+                }
+                _ => {
+                    files.insert(path, data);
+                }
+            }
+        }
+
+        let index_bytes = index_bytes.ok_or_else(|| anyhow!("插件包缺少 {INDEX_FILE}"))?;
+        let signature = signature.ok_or_else(|| anyhow!("插件包缺少 {SIGNATURE_FILE}"))?;
+        let index: PackageIndex = toml::from_str(std::str::from_utf8(&index_bytes)?)
+            .with_context(|| format!("无效的 {INDEX_FILE}"))?;
+
+        Ok(Self {
+            index,
+            index_bytes,
+            signature,
+            files,
+        })
+    }
+
+    /// 写入插件包
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("无法创建插件包: {}", path.display()))?;
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        let signature_hex = hex::encode(&self.signature);
+        let entries = [
+            (INDEX_FILE, self.index_bytes.as_slice()),
+            (SIGNATURE_FILE, signature_hex.as_bytes()),
+        ]
+        .into_iter()
+        .chain(self.files.iter().map(|(p, d)| (p.as_str(), d.as_slice())));
+        for (path, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, data)?;
+        }
+
+        builder.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    /// 插件名称
+    pub fn name(&self) -> &str {
+        &self.index.package.name
+    }
+
+    /// 插件版本
+    pub fn version(&self) -> &str {
+        &self.index.package.version
+    }
+
+    /// 包内的插件清单
+    pub fn manifest(&self) -> Result<PluginManifest> {
+        let manifest = self
+            .files
+            .get(MANIFEST_FILE)
+            .ok_or_else(|| anyhow!("插件包缺少 {MANIFEST_FILE}"))?;
+        PluginManifest::parse_manifest(std::str::from_utf8(manifest)?)
+    }
+
+    /// 包内文件路径
+    pub fn file_paths(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// 校验文件哈希、发布者签名和清单，返回签名的受信任发布者
+    pub fn verify<'a>(&self, trusted: &'a [TrustedPublisher]) -> Result<&'a TrustedPublisher> {
+        validate_plugin_name(self.name())?;
+
+        // 文件与索引一一对应且哈希一致
+        for (path, hash) in &self.index.files {
+            let data = self
+                .files
+                .get(path)
+                .ok_or_else(|| anyhow!("插件包缺少文件: {path}"))?;
+            if hex::encode(keccak256(data)) != hash.trim_start_matches("0x").to_lowercase() {
+                bail!("文件哈希不匹配: {path}");
+            }
+        }
+        if let Some(path) = self
+            .files
+            .keys()
+            .find(|path| !self.index.files.contains_key(*path))
+        {
+            bail!("插件包包含未列入索引的文件: {path}");
+        }
+
+        // 签名出自索引声明的发布者
+        let publisher: Address = self
+            .index
+            .package
+            .publisher
+            .parse()
+            .map_err(|_| anyhow!("无效的发布者地址: {}", self.index.package.publisher))?;
+        let signer = IdentityManager::recover_signer(
+            keccak256(&self.index_bytes).as_slice(),
+            &self.signature,
+        )?;
+        if signer != publisher {
+            bail!("签名与发布者 {publisher} 不符（签名者为 {signer}）");
+        }
+
+        // 发布者受信任
+        let trusted_publisher = trusted
+            .iter()
+            .find(|trusted| trusted.address.parse::<Address>().ok() == Some(publisher))
+            .ok_or_else(|| anyhow!("发布者 {publisher} 不在受信任列表中"))?;
+
+        // 清单与包索引一致
+        let manifest = self.manifest()?;
+        if manifest.plugin.name != self.name() || manifest.plugin.version != self.version() {
+            bail!(
+                "清单 {} {} 与包索引 {} {} 不一致",
+                manifest.plugin.name,
+                manifest.plugin.version,
+                self.name(),
+                self.version()
+            );
+        }
+        if !self.files.contains_key(&format!("{}.wasm", self.name())) {
+            bail!("插件包缺少 {}.wasm", self.name());
+        }
+
+        Ok(trusted_publisher)
+    }
+
+    /// 校验后安装到插件目录（`<plugin_dir>/<name>/`），替换已安装的版本
+    pub fn install(&self, plugin_dir: &Path, trusted: &[TrustedPublisher]) -> Result<PathBuf> {
+        self.verify(trusted)?;
+
+        // 先写入临时目录，完成后替换
+        let target = plugin_dir.join(self.name());
+        let staging = plugin_dir.join(format!(".{}.installing", self.name()));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        for (path, data) in &self.files {
+            let file_path = staging.join(path);
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&file_path, data)?;
+        }
+
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(&staging, &target)?;
+
+        tracing::info!(
+            "已安装插件 {} {} 到 {}",
+            self.name(),
+            self.version(),
+            target.display()
+        );
+        Ok(target)
+    }
+}
+
+/// 卸载插件目录中安装的插件，返回删除的目录
+pub fn uninstall(plugin_dir: &Path, name: &str) -> Result<PathBuf> {
+    validate_plugin_name(name)?;
+    let target = plugin_dir.join(name);
+    let manifest = PluginManifest::from_file(target.join(MANIFEST_FILE))
+        .map_err(|_| anyhow!("插件 {} 未安装: {}", name, target.display()))?;
+    if manifest.plugin.name != name {
+        bail!(
+            "{} 中安装的是插件 {}",
+            target.display(),
+            manifest.plugin.name
+        );
+    }
+
+    fs::remove_dir_all(&target)?;
+    tracing::info!("已卸载插件 {} ({})", name, target.display());
+    Ok(target)
+}
+
+/// 插件名称只能是单个路径段，由字母、数字、`_` 和 `-` 组成
+fn validate_plugin_name(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    let single_segment = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    );
+    if !single_segment
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        bail!("无效的插件名称: {name:?}");
+    }
+    Ok(())
+}
+
+/// 查找插件目录中的 wasm 文件
+fn find_wasm(plugin_dir: &Path, name: &str) -> Option<PathBuf> {
+    let release_dir = plugin_dir.join("target/wasm32-unknown-unknown/release");
+    [
+        plugin_dir.join(format!("{name}.wasm")),
+        release_dir.join(format!("{name}.wasm")),
+        release_dir.join(format!("{}.wasm", name.replace('-', "_"))),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// 规范化包内路径，拒绝绝对路径和 `..`
+fn package_path(path: &Path) -> Result<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => bail!("插件包包含不安全的路径: {}", path.display()),
+        }
+    }
+    if parts.is_empty() {
+        bail!("插件包包含空路径");
+    }
+    Ok(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PUBLISHER_KEY: &str =
+        "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    fn identity() -> IdentityManager {
+        IdentityManager::from_private_key(PUBLISHER_KEY).unwrap()
+    }
+
+    fn trusted(identity: &IdentityManager) -> Vec<TrustedPublisher> {
+        vec![TrustedPublisher {
+            name: "tester".to_string(),
+            address: identity.get_master_address().to_string(),
+        }]
+    }
+
+    /// 创建包含清单、wasm 和界面资源的插件目录
+    fn plugin_source(dir: &Path) {
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            "[plugin]\nname = \"demo\"\nversion = \"1.2.0\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("demo.wasm"), "(module)").unwrap();
+        fs::create_dir_all(dir.join("ui/assets")).unwrap();
+        fs::write(dir.join("ui/index.html"), "<html></html>").unwrap();
+        fs::write(dir.join("ui/assets/app.js"), "console.log(1)").unwrap();
+    }
+
+    #[test]
+    fn test_build_write_read_and_install() {
+        let source = TempDir::new().unwrap();
+        plugin_source(source.path());
+        let identity = identity();
+
+        let package = PluginPackage::build(source.path(), &identity).unwrap();
+        let out = TempDir::new().unwrap();
+        let package_path = out.path().join("demo.mkpkg");
+        package.write(&package_path).unwrap();
+
+        let package = PluginPackage::read(&package_path).unwrap();
+        assert_eq!(package.name(), "demo");
+        assert_eq!(package.version(), "1.2.0");
+        assert_eq!(
+            package.file_paths().collect::<Vec<_>>(),
+            vec![
+                "demo.wasm",
+                "manifest.toml",
+                "ui/assets/app.js",
+                "ui/index.html"
+            ]
+        );
+        let trusted = trusted(&identity);
+        assert_eq!(package.verify(&trusted).unwrap().name, "tester");
+
+        let plugins = TempDir::new().unwrap();
+        let target = package.install(plugins.path(), &trusted).unwrap();
+        assert_eq!(target, plugins.path().join("demo"));
+        assert!(target.join("demo.wasm").is_file());
+        assert!(target.join("ui/assets/app.js").is_file());
+
+        uninstall(plugins.path(), "demo").unwrap();
+        assert!(!target.exists());
+        assert!(uninstall(plugins.path(), "demo").is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering_and_untrusted_publishers() {
+        let source = TempDir::new().unwrap();
+        plugin_source(source.path());
+        let identity = identity();
+        let trusted = trusted(&identity);
+        let package = PluginPackage::build(source.path(), &identity).unwrap();
+
+        // 未受信任的发布者
+        let err = package.verify(&[]).unwrap_err();
+        assert!(err.to_string().contains("不在受信任列表中"));
+
+        // 文件被篡改
+        let mut tampered = package.clone();
+        tampered
+            .files
+            .insert("demo.wasm".to_string(), b"(module $evil)".to_vec());
+        let err = tampered.verify(&trusted).unwrap_err();
+        assert!(err.to_string().contains("文件哈希不匹配"));
+
+        // 夹带未列入索引的文件
+        let mut tampered = package.clone();
+        tampered
+            .files
+            .insert("ui/extra.js".to_string(), b"".to_vec());
+        assert!(tampered.verify(&trusted).is_err());
+
+        // 索引被改写后签名不再匹配
+        let mut tampered = package.clone();
+        tampered.index_bytes = String::from_utf8(tampered.index_bytes)
+            .unwrap()
+            .replace("1.2.0", "9.9.9")
+            .into_bytes();
+        let err = tampered.verify(&trusted).unwrap_err();
+        assert!(err.to_string().contains("签名与发布者"));
+
+        // 失败的安装不写入插件目录
+        let plugins = TempDir::new().unwrap();
+        assert!(package.install(plugins.path(), &[]).is_err());
+        assert!(!plugins.path().join("demo").exists());
+    }
+
+    #[test]
+    fn test_rejects_malicious_plugin_names() {
+        let source = TempDir::new().unwrap();
+        plugin_source(source.path());
+        let identity = identity();
+        let trusted = trusted(&identity);
+        let package = PluginPackage::build(source.path(), &identity).unwrap();
+
+        let plugins = TempDir::new().unwrap();
+        let installed = plugins.path().join("other");
+        fs::create_dir_all(&installed).unwrap();
+
+        for name in ["", "..", ".", "../other", "a/b", "/tmp", "demo.1"] {
+            let mut malicious = package.clone();
+            malicious.index.package.name = name.to_string();
+            let err = malicious.install(plugins.path(), &trusted).unwrap_err();
+            assert!(err.to_string().contains("无效的插件名称"), "{name}: {err}");
+            assert!(uninstall(plugins.path(), name).is_err());
+        }
+        assert!(plugins.path().is_dir());
+        assert!(installed.is_dir());
+        assert_eq!(fs::read_dir(plugins.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_package_path_rejects_traversal() {
+        assert_eq!(
+            package_path(Path::new("./ui/index.html")).unwrap(),
+            "ui/index.html"
+        );
+        assert!(package_path(Path::new("../evil.wasm")).is_err());
+        assert!(package_path(Path::new("/etc/passwd")).is_err());
+    }
+}
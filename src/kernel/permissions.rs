@@ -0,0 +1,271 @@
+//! 插件权限模型
+//!
+//! 基于能力（capability）的权限声明，来自 manifest.toml 的 [permissions] 段：
+//!
+//! ```toml
+//! [permissions]
+//! allow = ["storage", "identity.sign", "bus.publish:health.*", "bus.send:echo"]
+//! ```
+//!
+//! 主机函数总是以调用方插件的身份执行；访问其它插件的存储、密钥或以其身份
+//! 使用总线，需要显式声明 `storage:<插件>`、`config:<插件>`、`identity.sign:<插件>`、
+//! `bus.impersonate:<插件>`。`bus.intercept` 允许插件作为拦截器处理经过总线的所有消息
+
+use anyhow::{anyhow, Result};
+use glob::Pattern;
+use std::fmt;
+
+/// 权限拒绝时返回给插件的错误码（plugin-sdk 会映射为 `PluginError::Permission`）
+pub const PERMISSION_DENIED_CODE: &str = "permission_denied";
+
+/// 插件能力
+///
+/// 授予的能力中 `bus.*` 的参数是通配模式，请求的能力中则是具体的主题或插件名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Capability {
+    /// 读写自身的键值存储
+    Storage,
+    /// 读写匹配插件的键值存储
+    StorageOf(String),
+    /// 读写匹配插件的配置（读写自身配置不需要权限）
+    ConfigOf(String),
+    /// 使用派生密钥签名
+    IdentitySign,
+    /// 使用匹配插件的派生密钥签名
+    IdentitySignAs(String),
+    /// 向匹配的主题发布消息
+    BusPublish(String),
+    /// 订阅匹配的主题
+    BusSubscribe(String),
+    /// 向匹配的插件发送点对点消息
+    BusSend(String),
+    /// 以匹配插件的身份收发消息
+    BusImpersonate(String),
+    /// 作为拦截器处理经过总线的所有消息
+    BusIntercept,
+}
+
+impl Capability {
+    /// 解析能力声明字符串
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (name, arg) = match value.split_once(':') {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (value, None),
+        };
+
+        let capability = match (name, arg) {
+            ("storage", None) => Capability::Storage,
+            ("storage", Some(arg)) if !arg.is_empty() => Capability::StorageOf(arg.into()),
+            ("config", Some(arg)) if !arg.is_empty() => Capability::ConfigOf(arg.into()),
+            ("identity.sign", None) => Capability::IdentitySign,
+            ("identity.sign", Some(arg)) if !arg.is_empty() => {
+                Capability::IdentitySignAs(arg.into())
+            }
+            ("bus.publish", Some(arg)) if !arg.is_empty() => Capability::BusPublish(arg.into()),
+            ("bus.subscribe", Some(arg)) if !arg.is_empty() => Capability::BusSubscribe(arg.into()),
+            ("bus.send", Some(arg)) if !arg.is_empty() => Capability::BusSend(arg.into()),
+            ("bus.impersonate", Some(arg)) if !arg.is_empty() => {
+                Capability::BusImpersonate(arg.into())
+            }
+            ("bus.intercept", None) => Capability::BusIntercept,
+            _ => return Err(anyhow!("无效的权限声明: {}", value)),
+        };
+
+        // 提前校验通配模式
+        if let Some(pattern) = capability.pattern() {
+            Pattern::new(pattern).map_err(|e| anyhow!("无效的权限模式 '{}': {}", value, e))?;
+        }
+
+        Ok(capability)
+    }
+
+    /// 获取能力参数（主题或插件名）
+    fn pattern(&self) -> Option<&str> {
+        match self {
+            Capability::Storage | Capability::IdentitySign | Capability::BusIntercept => None,
+            Capability::StorageOf(p)
+            | Capability::ConfigOf(p)
+            | Capability::IdentitySignAs(p)
+            | Capability::BusPublish(p)
+            | Capability::BusSubscribe(p)
+            | Capability::BusSend(p)
+            | Capability::BusImpersonate(p) => Some(p),
+        }
+    }
+
+    /// 检查授予的能力是否覆盖请求的能力
+    fn covers(&self, requested: &Capability) -> bool {
+        match (self, requested) {
+            (Capability::Storage, Capability::Storage) => true,
+            (Capability::IdentitySign, Capability::IdentitySign) => true,
+            (Capability::BusIntercept, Capability::BusIntercept) => true,
+            (Capability::StorageOf(p), Capability::StorageOf(t))
+            | (Capability::ConfigOf(p), Capability::ConfigOf(t))
+            | (Capability::IdentitySignAs(p), Capability::IdentitySignAs(t))
+            | (Capability::BusPublish(p), Capability::BusPublish(t))
+            | (Capability::BusSubscribe(p), Capability::BusSubscribe(t))
+            | (Capability::BusSend(p), Capability::BusSend(t))
+            | (Capability::BusImpersonate(p), Capability::BusImpersonate(t)) => {
+                Pattern::new(p).is_ok_and(|pattern| pattern.matches(t))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::Storage => write!(f, "storage"),
+            Capability::StorageOf(p) => write!(f, "storage:{p}"),
+            Capability::ConfigOf(p) => write!(f, "config:{p}"),
+            Capability::IdentitySign => write!(f, "identity.sign"),
+            Capability::IdentitySignAs(p) => write!(f, "identity.sign:{p}"),
+            Capability::BusPublish(p) => write!(f, "bus.publish:{p}"),
+            Capability::BusSubscribe(p) => write!(f, "bus.subscribe:{p}"),
+            Capability::BusSend(p) => write!(f, "bus.send:{p}"),
+            Capability::BusImpersonate(p) => write!(f, "bus.impersonate:{p}"),
+            Capability::BusIntercept => write!(f, "bus.intercept"),
+        }
+    }
+}
+
+/// 权限拒绝错误
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("插件 '{plugin}' 没有 '{capability}' 权限")]
+pub struct PermissionDenied {
+    /// 调用方插件
+    pub plugin: String,
+    /// 被拒绝的能力
+    pub capability: Capability,
+}
+
+/// 插件被授予的能力集合
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionSet {
+    capabilities: Vec<Capability>,
+}
+
+impl PermissionSet {
+    /// 从能力声明列表创建
+    pub fn parse<S: AsRef<str>>(values: &[S]) -> Result<Self> {
+        let capabilities = values
+            .iter()
+            .map(|value| Capability::parse(value.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { capabilities })
+    }
+
+    /// 授予全部能力（供内核自身或测试使用）
+    pub fn all() -> Self {
+        Self {
+            capabilities: vec![
+                Capability::Storage,
+                Capability::IdentitySign,
+                Capability::BusPublish("*".into()),
+                Capability::BusSubscribe("*".into()),
+                Capability::BusSend("*".into()),
+            ],
+        }
+    }
+
+    /// 检查是否允许请求的能力
+    pub fn allows(&self, requested: &Capability) -> bool {
+        self.capabilities
+            .iter()
+            .any(|granted| granted.covers(requested))
+    }
+
+    /// 检查权限，拒绝时返回结构化错误
+    pub fn check(&self, plugin: &str, requested: Capability) -> Result<(), PermissionDenied> {
+        if self.allows(&requested) {
+            Ok(())
+        } else {
+            Err(PermissionDenied {
+                plugin: plugin.to_string(),
+                capability: requested,
+            })
+        }
+    }
+
+    /// 是否没有任何能力
+    pub fn is_empty(&self) -> bool {
+        self.capabilities.is_empty()
+    }
+
+    /// 以声明字符串的形式列出所有能力
+    pub fn describe(&self) -> Vec<String> {
+        self.capabilities.iter().map(|c| c.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capabilities() {
+        let set = PermissionSet::parse(&[
+            "storage",
+            "identity.sign",
+            "bus.publish:health.*",
+            "bus.send:echo",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            set.describe(),
+            vec![
+                "storage",
+                "identity.sign",
+                "bus.publish:health.*",
+                "bus.send:echo"
+            ]
+        );
+
+        assert!(Capability::parse("bus.publish").is_err());
+        assert!(Capability::parse("network").is_err());
+        assert!(Capability::parse("bus.send:[").is_err());
+    }
+
+    #[test]
+    fn test_topic_patterns() {
+        let set =
+            PermissionSet::parse(&["bus.publish:health.*", "bus.subscribe:system.stats"]).unwrap();
+
+        assert!(set.allows(&Capability::BusPublish("health.heart_rate".into())));
+        assert!(!set.allows(&Capability::BusPublish("system.stats".into())));
+        assert!(set.allows(&Capability::BusSubscribe("system.stats".into())));
+        assert!(!set.allows(&Capability::BusSubscribe("system.logs".into())));
+        assert!(!set.allows(&Capability::Storage));
+    }
+
+    #[test]
+    fn test_cross_plugin_capabilities() {
+        let set = PermissionSet::parse(&["storage:shared-*", "identity.sign:wallet"]).unwrap();
+
+        // 访问其它插件的能力不包含访问自身
+        assert!(!set.allows(&Capability::Storage));
+        assert!(set.allows(&Capability::StorageOf("shared-cache".into())));
+        assert!(!set.allows(&Capability::StorageOf("secrets".into())));
+        assert!(set.allows(&Capability::IdentitySignAs("wallet".into())));
+        assert!(!set.allows(&Capability::IdentitySign));
+        assert!(!set.allows(&Capability::BusImpersonate("wallet".into())));
+    }
+
+    #[test]
+    fn test_check_returns_structured_error() {
+        let set = PermissionSet::parse(&["storage"]).unwrap();
+
+        assert!(set.check("demo", Capability::Storage).is_ok());
+
+        let err = set
+            .check("demo", Capability::BusSend("echo".into()))
+            .unwrap_err();
+        assert_eq!(err.plugin, "demo");
+        assert_eq!(err.capability, Capability::BusSend("echo".into()));
+        assert!(err.to_string().contains("bus.send:echo"));
+    }
+}
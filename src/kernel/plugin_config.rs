@@ -0,0 +1,138 @@
+//! 插件配置
+//!
+//! 插件配置保存在 `plugin_metadata.config` 列中，读取时与 manifest.toml 的
+//! [config] 默认值合并，写入时按插件元数据声明的 `config_schema` 校验
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value as JsonValue};
+
+/// 配置校验失败时返回给插件的错误码
+pub const INVALID_CONFIG_CODE: &str = "invalid_config";
+
+/// 插件配置的默认值与校验规则
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigSpec {
+    /// manifest.toml 中 [config] 段的默认值
+    pub defaults: Map<String, JsonValue>,
+    /// 插件声明的 JSON Schema
+    pub schema: Option<JsonValue>,
+}
+
+impl ConfigSpec {
+    /// 从 manifest.toml 的 [config] 段创建
+    pub fn from_manifest(config: &toml::Table) -> Result<Self> {
+        let defaults = match serde_json::to_value(config)? {
+            JsonValue::Object(map) => map,
+            _ => Map::new(),
+        };
+
+        Ok(Self {
+            defaults,
+            schema: None,
+        })
+    }
+
+    /// 设置校验用的 JSON Schema（会先检查 schema 本身是否有效）
+    pub fn with_schema(mut self, schema: JsonValue) -> Result<Self> {
+        jsonschema::validator_for(&schema).map_err(|e| anyhow!("无效的配置 schema: {}", e))?;
+        self.schema = Some(schema);
+        Ok(self)
+    }
+
+    /// 合并默认值与已保存的配置（已保存的顶层字段优先）
+    pub fn resolve(&self, stored: Option<JsonValue>) -> JsonValue {
+        match stored {
+            Some(JsonValue::Object(stored)) => {
+                let mut merged = self.defaults.clone();
+                merged.extend(stored);
+                JsonValue::Object(merged)
+            }
+            Some(other) => other,
+            None => JsonValue::Object(self.defaults.clone()),
+        }
+    }
+
+    /// 按 schema 校验配置，未声明 schema 时总是通过
+    pub fn validate(&self, config: &JsonValue) -> Result<()> {
+        let Some(schema) = &self.schema else {
+            return Ok(());
+        };
+
+        let validator =
+            jsonschema::validator_for(schema).map_err(|e| anyhow!("无效的配置 schema: {}", e))?;
+        let errors: Vec<String> = validator
+            .iter_errors(config)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{path}: {e}")
+                }
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("配置不符合 schema: {}", errors.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec() -> ConfigSpec {
+        let table: toml::Table = toml::from_str("interval_ms = 1000\nunit = \"celsius\"").unwrap();
+        ConfigSpec::from_manifest(&table)
+            .unwrap()
+            .with_schema(json!({
+                "type": "object",
+                "properties": {
+                    "interval_ms": { "type": "integer", "minimum": 100 },
+                    "unit": { "enum": ["celsius", "fahrenheit"] }
+                }
+            }))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_merges_defaults() {
+        let spec = spec();
+
+        assert_eq!(
+            spec.resolve(None),
+            json!({ "interval_ms": 1000, "unit": "celsius" })
+        );
+        assert_eq!(
+            spec.resolve(Some(json!({ "unit": "fahrenheit" }))),
+            json!({ "interval_ms": 1000, "unit": "fahrenheit" })
+        );
+    }
+
+    #[test]
+    fn test_validate_against_schema() {
+        let spec = spec();
+
+        assert!(spec.validate(&json!({ "interval_ms": 500 })).is_ok());
+
+        let err = spec
+            .validate(&json!({ "interval_ms": 10, "unit": "kelvin" }))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("/interval_ms"));
+        assert!(err.contains("/unit"));
+
+        // 没有 schema 时不校验
+        assert!(ConfigSpec::default().validate(&json!("anything")).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_schema_rejected() {
+        let result = ConfigSpec::default().with_schema(json!({ "type": "no-such-type" }));
+        assert!(result.is_err());
+    }
+}
@@ -2,21 +2,140 @@
 //!
 //! 负责管理 WebAssembly 插件的加载、调用和卸载
 
+use crate::config::{OverflowPolicy, PluginConfig};
 use crate::identity::IdentityManager;
 use crate::storage::Storage;
 use anyhow::{anyhow, Result};
 use extism::*;
+use parking_lot::RwLock;
+use semver::Version;
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::runtime::RuntimeFlavor;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
 use walkdir::WalkDir;
 
 use super::dependency_resolver::DependencyResolver;
+use super::durable_queue::NackOutcome;
 use super::host_functions::{build_plugin_with_host_functions, create_context_store, HostContext};
-use super::manifest::{find_and_read_manifest, PluginManifest};
-use super::message::Message;
+use super::interceptor::{PluginInterceptor, INTERCEPT_FN};
+use super::lifecycle::{
+    PluginState, EXPORT_STATE_FN, IMPORT_STATE_FN, INITIALIZE_FN, PAUSE_FN, RESUME_FN, SHUTDOWN_FN,
+};
+use super::load_report::LoadReport;
+use super::manifest::{
+    find_and_read_manifest, kernel_satisfies, DependencySpec, Limits, Permissions, PluginManifest,
+    TopicDeclaration,
+};
+use super::message::{Message, PluginMessage, DELIVERY_ERROR_TYPE};
 use super::message_bus::MessageBusHandle;
+use super::permissions::{Capability, PermissionSet};
+use super::plugin_config::ConfigSpec;
+use super::plugin_queue::{PluginQueueConfig, PluginReceiver};
+use super::scheduler::{run_ticks, SharedTickStats, TickSchedule, TickStats};
+use super::topic_index::validate_topic_pattern;
+
+/// 插件处理消息的导出函数名
+const HANDLE_MESSAGE_FN: &str = "handle_message";
+
+/// 插件返回元数据的导出函数名
+const METADATA_FN: &str = "metadata";
+
+/// 每 MB 对应的 WASM 内存页数（每页 64KiB）
+const PAGES_PER_MB: u32 = 16;
+
+/// Extism 调用被超时中断时返回的错误
+const EXTISM_TIMEOUT_ERROR: &str = "timeout";
+
+/// 共享的插件实例（内核调用与消息分发任务共用）
+pub type SharedPlugin = Arc<Mutex<Plugin>>;
+
+/// 插件故障记录：插件名 -> 故障原因
+pub(super) type FaultMap = Arc<RwLock<HashMap<String, String>>>;
+
+/// 插件运行时资源限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeLimits {
+    /// 单次调用超时（毫秒，0 表示不限制）
+    pub timeout_ms: u64,
+    /// 最大内存（MB，0 表示不限制）
+    pub max_memory_mb: u32,
+    /// 消息队列深度
+    pub queue_depth: usize,
+    /// 消息队列已满时的处理策略
+    pub queue_overflow: OverflowPolicy,
+    /// `block` 策略下等待队列空位的最长时间（毫秒）
+    pub queue_block_timeout_ms: u64,
+}
+
+impl Default for RuntimeLimits {
+    fn default() -> Self {
+        Self::from(&PluginConfig::default())
+    }
+}
+
+impl From<&PluginConfig> for RuntimeLimits {
+    fn from(config: &PluginConfig) -> Self {
+        Self {
+            timeout_ms: config.timeout_ms,
+            max_memory_mb: config.max_memory_mb,
+            queue_depth: config.queue_depth,
+            queue_overflow: config.queue_overflow,
+            queue_block_timeout_ms: config.queue_block_timeout_ms,
+        }
+    }
+}
+
+impl RuntimeLimits {
+    /// 应用 manifest.toml 中 [limits] 的覆盖值
+    pub fn with_overrides(self, limits: &Limits) -> Self {
+        Self {
+            timeout_ms: limits.timeout_ms.unwrap_or(self.timeout_ms),
+            max_memory_mb: limits.max_memory_mb.unwrap_or(self.max_memory_mb),
+            queue_depth: limits.queue_depth.unwrap_or(self.queue_depth),
+            queue_overflow: limits.queue_overflow.unwrap_or(self.queue_overflow),
+            ..self
+        }
+    }
+
+    /// 插件消息队列配置
+    pub fn queue_config(&self) -> PluginQueueConfig {
+        PluginQueueConfig {
+            depth: self.queue_depth,
+            overflow: self.queue_overflow,
+            block_timeout: std::time::Duration::from_millis(self.queue_block_timeout_ms),
+        }
+    }
+
+    /// 将限制写入 Extism 清单
+    fn apply(&self, mut manifest: Manifest) -> Manifest {
+        if self.timeout_ms > 0 {
+            manifest = manifest.with_timeout(std::time::Duration::from_millis(self.timeout_ms));
+        }
+        if self.max_memory_mb > 0 {
+            manifest = manifest.with_memory_max(self.max_memory_mb.saturating_mul(PAGES_PER_MB));
+        }
+        manifest
+    }
+}
+
+/// 插件调用错误
+#[derive(thiserror::Error, Debug)]
+pub enum PluginCallError {
+    #[error("Plugin '{plugin}' call to '{function}' timed out after {timeout_ms}ms")]
+    Timeout {
+        plugin: String,
+        function: String,
+        timeout_ms: u64,
+    },
+
+    #[error("Plugin '{plugin}' is faulted: {reason}")]
+    Faulted { plugin: String, reason: String },
+}
 
 /// 插件信息
 #[derive(Debug, Clone)]
@@ -40,13 +159,15 @@ pub struct PluginInfo {
     /// 插件作者
     pub author: Option<String>,
     /// 必需依赖
-    pub dependencies: Vec<String>,
+    pub dependencies: Vec<DependencySpec>,
     /// 可选依赖
-    pub optional_dependencies: Vec<String>,
+    pub optional_dependencies: Vec<DependencySpec>,
     /// 插件标签
     pub tags: Vec<String>,
     /// 最小内核版本要求
     pub min_kernel_version: Option<String>,
+    /// 声明的能力权限（None 表示未声明，使用内核默认权限）
+    pub permissions: Option<Vec<String>>,
 }
 
 impl PluginInfo {
@@ -69,6 +190,7 @@ impl PluginInfo {
             optional_dependencies: manifest.dependencies.optional,
             tags: manifest.metadata.tags,
             min_kernel_version: manifest.metadata.min_kernel_version,
+            permissions: manifest.permissions.map(|p| p.allow),
         })
     }
 
@@ -97,24 +219,27 @@ impl PluginInfo {
             optional_dependencies: Vec::new(),
             tags: Vec::new(),
             min_kernel_version: None,
+            permissions: None,
         })
     }
 
     /// 检查是否兼容指定的内核版本
     pub fn is_compatible_with_kernel(&self, kernel_version: &str) -> bool {
-        if let Some(min_version) = &self.min_kernel_version {
-            // 简单的字符串比较，生产环境应使用 semver
-            min_version.as_str() <= kernel_version
-        } else {
-            true
-        }
+        kernel_satisfies(self.min_kernel_version.as_deref(), kernel_version)
+    }
+
+    /// 解析后的插件版本（无法解析时为 0.0.0）
+    pub fn semver(&self) -> Version {
+        Version::parse(&self.version).unwrap_or_else(|_| Version::new(0, 0, 0))
     }
 
-    /// 获取所有依赖（必需 + 可选）
+    /// 获取所有依赖的名称（必需 + 可选）
     pub fn all_dependencies(&self) -> Vec<String> {
-        let mut deps = self.dependencies.clone();
-        deps.extend(self.optional_dependencies.clone());
-        deps
+        self.dependencies
+            .iter()
+            .chain(&self.optional_dependencies)
+            .map(|dep| dep.name.clone())
+            .collect()
     }
 
     /// 检查是否有依赖
@@ -123,22 +248,68 @@ impl PluginInfo {
     }
 }
 
+/// 从插件清单读取的加载设置
+struct PluginSettings {
+    limits: RuntimeLimits,
+    permissions: PermissionSet,
+    config: ConfigSpec,
+    schedule: Option<TickSchedule>,
+    topics: Vec<TopicDeclaration>,
+    info: Result<PluginInfo>,
+}
+
 /// 插件加载器
 pub struct PluginLoader {
     /// 已加载的插件集合
-    plugins: HashMap<String, Plugin>,
-    /// 上下文存储
-    context_store: UserData<super::host_functions::ContextStore>,
+    plugins: HashMap<String, SharedPlugin>,
+    /// 已加载插件的文件路径（热重载时使用）
+    paths: HashMap<String, PathBuf>,
+    /// 共享的主机上下文模板（存储、身份、总线）
+    base_context: HostContext,
+    /// 每个已加载插件的主机上下文
+    contexts: HashMap<String, Arc<Mutex<HostContext>>>,
     /// 依赖解析器
     dependency_resolver: DependencyResolver,
+    /// 主消息发送器（用于回报投递错误）
+    msg_sender: mpsc::Sender<Message>,
+    /// 消息总线句柄（用于为插件注册通道）
+    message_bus: Option<MessageBusHandle>,
+    /// 每个插件的消息分发任务
+    dispatchers: HashMap<String, JoinHandle<()>>,
+    /// 默认资源限制（来自内核配置）
+    default_limits: RuntimeLimits,
+    /// 每个已加载插件实际生效的资源限制，与分发任务共享（热重载后立即生效）
+    limits: HashMap<String, watch::Sender<RuntimeLimits>>,
+    /// 未声明权限的插件获得的默认权限
+    default_permissions: PermissionSet,
+    /// 已故障的插件（如调用超时），与分发任务共享
+    faults: FaultMap,
+    /// 每个已加载插件的生命周期状态，与分发任务共享
+    states: HashMap<String, watch::Sender<PluginState>>,
+    /// 每个插件的定时任务
+    tickers: HashMap<String, JoinHandle<()>>,
+    /// 每个声明了定时任务的插件的 tick 统计
+    tick_stats: HashMap<String, SharedTickStats>,
 }
 
 impl std::fmt::Debug for PluginLoader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PluginLoader")
             .field("plugins", &self.plugins.keys().collect::<Vec<_>>())
-            .field("context_store", &"<UserData>")
+            .field("contexts", &self.contexts.keys().collect::<Vec<_>>())
             .field("dependency_resolver", &"<DependencyResolver>")
+            .field("dispatchers", &self.dispatchers.keys().collect::<Vec<_>>())
+            .field("tickers", &self.tickers.keys().collect::<Vec<_>>())
+            .field("default_limits", &self.default_limits)
+            .field("faults", &*self.faults.read())
+            .field(
+                "states",
+                &self
+                    .states
+                    .iter()
+                    .map(|(name, state)| (name, *state.borrow()))
+                    .collect::<HashMap<_, _>>(),
+            )
             .finish()
     }
 }
@@ -150,29 +321,105 @@ impl PluginLoader {
         storage: Arc<Storage>,
         identity: Option<Arc<IdentityManager>>,
     ) -> Result<Self> {
-        // 创建主机上下文（暂时不传递 MessageBus 引用）
-        let host_context = HostContext::new(Some(storage), msg_sender, identity, None);
-        let host_context = Arc::new(Mutex::new(host_context));
-
-        // 创建上下文存储
-        let context_store = create_context_store(host_context);
+        // 创建主机上下文模板（暂时不传递 MessageBus 引用）
+        let base_context = HostContext::new(Some(storage), msg_sender.clone(), identity, None);
 
         Ok(Self {
             plugins: HashMap::new(),
-            context_store,
+            paths: HashMap::new(),
+            base_context,
+            contexts: HashMap::new(),
             dependency_resolver: DependencyResolver::new(),
+            msg_sender,
+            message_bus: None,
+            dispatchers: HashMap::new(),
+            default_limits: RuntimeLimits::default(),
+            limits: HashMap::new(),
+            default_permissions: PermissionSet::parse(
+                &PluginConfig::default().default_permissions,
+            )?,
+            faults: Arc::new(RwLock::new(HashMap::new())),
+            states: HashMap::new(),
+            tickers: HashMap::new(),
+            tick_stats: HashMap::new(),
         })
     }
 
+    /// 设置默认资源限制（对之后加载的插件生效）
+    pub fn set_default_limits(&mut self, limits: RuntimeLimits) {
+        self.default_limits = limits;
+    }
+
+    /// 获取插件实际生效的资源限制
+    pub fn plugin_limits(&self, name: &str) -> Option<RuntimeLimits> {
+        self.limits.get(name).map(|limits| *limits.borrow())
+    }
+
+    /// 设置默认权限（对之后加载的、未声明权限的插件生效）
+    pub fn set_default_permissions(&mut self, permissions: PermissionSet) {
+        self.default_permissions = permissions;
+    }
+
+    /// 获取已加载插件被授予的权限
+    pub fn plugin_permissions(&self, name: &str) -> Option<PermissionSet> {
+        self.contexts
+            .get(name)
+            .map(|ctx| ctx.lock().unwrap().permissions.clone())
+    }
+
+    /// 检查插件是否已故障
+    pub fn is_faulted(&self, name: &str) -> bool {
+        self.faults.read().contains_key(name)
+    }
+
+    /// 获取所有故障插件及原因
+    pub fn faulted_plugins(&self) -> HashMap<String, String> {
+        self.faults.read().clone()
+    }
+
+    /// 获取插件当前的生命周期状态
+    pub fn plugin_state(&self, name: &str) -> Option<PluginState> {
+        self.states.get(name).map(|state| *state.borrow())
+    }
+
+    /// 获取插件的 tick 统计（未声明定时任务时返回 None）
+    pub fn tick_stats(&self, name: &str) -> Option<TickStats> {
+        self.tick_stats.get(name).map(|stats| stats.read().clone())
+    }
+
+    /// 获取所有插件的 tick 统计
+    pub fn all_tick_stats(&self) -> HashMap<String, TickStats> {
+        self.tick_stats
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.read().clone()))
+            .collect()
+    }
+
+    /// 清除插件的故障标记
+    pub fn clear_fault(&mut self, name: &str) -> bool {
+        self.faults.write().remove(name).is_some()
+    }
+
     /// 设置消息总线引用（在 Kernel 初始化后调用）
     pub fn set_message_bus(&mut self, message_bus: MessageBusHandle) {
-        let store = self.context_store.get().unwrap();
-        let store = store.lock().unwrap();
-        let inner_store = store.lock().unwrap();
+        self.base_context.message_bus = Some(message_bus.clone());
+        for ctx in self.contexts.values() {
+            ctx.lock().unwrap().message_bus = Some(message_bus.clone());
+        }
 
-        if let Some(ctx_arc) = inner_store.get("context") {
-            let mut ctx = ctx_arc.lock().unwrap();
-            ctx.message_bus = Some(message_bus);
+        self.message_bus = Some(message_bus);
+
+        // 为设置总线之前已加载的插件补上消息通道
+        let pending: Vec<(String, SharedPlugin)> = self
+            .plugins
+            .iter()
+            .filter(|(name, _)| !self.dispatchers.contains_key(*name))
+            .map(|(name, plugin)| (name.clone(), plugin.clone()))
+            .collect();
+        for (name, plugin) in pending {
+            self.register_interceptor(&name);
+            self.start_dispatcher(&name, plugin);
+            self.restore_subscriptions(&name);
         }
     }
 
@@ -193,39 +440,647 @@ impl PluginLoader {
             return Err(anyhow!("Plugin '{}' already loaded", name));
         }
 
+        let mut settings = self.read_settings(name, path)?;
+        let (plugin, context) = self.instantiate(name, path, &mut settings)?;
+        let plugin = Arc::new(Mutex::new(plugin));
+
+        // 注册插件发布的主题 schema
+        if let Some(bus) = &self.message_bus {
+            bus.schemas()
+                .register(name, &settings.topics)
+                .map_err(|e| anyhow!("Plugin '{}' has invalid topic schema: {}", name, e))?;
+        }
+
+        // 存储插件
+        self.plugins.insert(name.to_string(), plugin.clone());
+        self.paths.insert(name.to_string(), PathBuf::from(path));
+        self.contexts.insert(name.to_string(), context);
+        self.limits
+            .insert(name.to_string(), watch::channel(settings.limits).0);
+        self.states
+            .insert(name.to_string(), watch::channel(PluginState::Loaded).0);
+        self.persist_state(name, PluginState::Loaded);
+
+        // 记录依赖关系，关闭时按依赖的逆序进行
+        self.record_dependencies(name, settings.info);
+
+        // 注册拦截器与消息通道（初始化完成前消息暂存在通道中）、恢复订阅并启动定时任务
+        self.register_interceptor(name);
+        self.start_dispatcher(name, plugin.clone());
+        self.restore_subscriptions(name);
+        if let Some(schedule) = settings.schedule {
+            self.start_ticker(name, plugin, schedule);
+        }
+
+        if let Err(e) = self.initialize_plugin(name) {
+            tracing::error!("插件 {} 初始化失败: {}", name, e);
+            let _ = self.set_state(name, PluginState::Error);
+            self.remove_plugin(name)?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// 热重载插件：从原路径构建新实例，交接状态后原子替换旧实例
+    ///
+    /// 旧实例的 `export_state` 输出交给新实例的 `import_state`（两者均为可选导出）。
+    /// 替换期间持有插件锁，消息留在队列中、订阅保持不变，新的资源限制与队列设置随替换生效；
+    /// 新实例初始化或导入状态失败时保留旧实例
+    pub fn reload_plugin(&mut self, name: &str) -> Result<()> {
+        let state = self
+            .plugin_state(name)
+            .ok_or_else(|| anyhow!("Plugin '{}' not found", name))?;
+        if !state.is_active() {
+            return Err(anyhow!(
+                "Plugin '{}' is {} and cannot be reloaded",
+                name,
+                state
+            ));
+        }
+        let path = self
+            .paths
+            .get(name)
+            .and_then(|path| path.to_str())
+            .ok_or_else(|| anyhow!("Plugin '{}' has no reloadable path", name))?
+            .to_string();
+
+        let mut settings = self.read_settings(name, &path)?;
+        let (mut next, context) = self.instantiate(name, &path, &mut settings)?;
+        let input = self.initialize_input(name, &context);
+        let shared = self.get_plugin(name)?;
+        // 故障的旧实例已无法执行，不导出状态
+        let faulted = self.is_faulted(name);
+        let bus = self.message_bus.clone();
+        let limits = self.limits.get(name);
+
+        run_blocking(|| {
+            let mut current = shared
+                .lock()
+                .map_err(|_| anyhow!("Plugin '{}' lock poisoned", name))?;
+            let exported = if !faulted && current.function_exists(EXPORT_STATE_FN) {
+                let exported = current
+                    .call::<&str, String>(EXPORT_STATE_FN, "")
+                    .map_err(|e| anyhow!("Plugin '{}' failed to export state: {}", name, e))?;
+                Some(exported).filter(|exported| !exported.is_empty() && exported != "null")
+            } else {
+                None
+            };
+
+            let mut call_next = |function: &str, input: &str| {
+                if !next.function_exists(function) {
+                    return Ok(false);
+                }
+                next.call::<&str, String>(function, input)
+                    .map(|_| true)
+                    .map_err(|e| {
+                        anyhow!("Plugin '{}' new instance {} failed: {}", name, function, e)
+                    })
+            };
+            call_next(INITIALIZE_FN, &input)?;
+            if let Some(exported) = exported {
+                if !call_next(IMPORT_STATE_FN, &exported)? {
+                    tracing::warn!(
+                        "插件 {} 的新实例未导出 {}，旧状态被丢弃",
+                        name,
+                        IMPORT_STATE_FN
+                    );
+                }
+            }
+            if state == PluginState::Paused {
+                call_next(PAUSE_FN, "")?;
+            }
+            if let Some(bus) = &bus {
+                bus.schemas()
+                    .register(name, &settings.topics)
+                    .map_err(|e| anyhow!("Plugin '{}' has invalid topic schema: {}", name, e))?;
+            }
+
+            // 新的资源限制与队列设置随实例一起生效，分发任务拿到插件锁时即使用新超时
+            std::mem::swap(&mut *current, &mut next);
+            if let Some(limits) = limits {
+                limits.send_replace(settings.limits);
+            }
+            if let Some(bus) = &bus {
+                bus.reconfigure_plugin_queue(name, settings.limits.queue_config());
+            }
+            drop(current);
+
+            // 旧实例释放资源，失败不影响重载
+            if !faulted && next.function_exists(SHUTDOWN_FN) {
+                if let Err(e) = next.call::<&str, String>(SHUTDOWN_FN, "") {
+                    tracing::warn!("插件 {} 的旧实例关闭失败: {}", name, e);
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        })
+        .inspect_err(|e| tracing::error!("插件 {} 重载失败，保留旧实例: {}", name, e))?;
+        drop(next);
+
+        // 新实例的上下文生效，故障随旧实例清除
+        self.contexts.insert(name.to_string(), context);
+        self.faults.write().remove(name);
+        self.record_dependencies(name, settings.info);
+
+        // 权限与定时任务可能随新版本变化，重新注册
+        if let Some(bus) = &self.message_bus {
+            bus.interceptors()
+                .remove(&PluginInterceptor::name_for(name));
+        }
+        self.register_interceptor(name);
+        if let Some(ticker) = self.tickers.remove(name) {
+            ticker.abort();
+        }
+        self.tick_stats.remove(name);
+        if let Some(schedule) = settings.schedule {
+            self.start_ticker(name, shared, schedule);
+        }
+
+        tracing::info!("插件 {} 已热重载 ({})", name, path);
+        Ok(())
+    }
+
+    /// 读取 manifest.toml 中的资源限制覆盖、权限声明、配置默认值、定时任务与主题 schema
+    fn read_settings(&self, name: &str, path: &str) -> Result<PluginSettings> {
+        let settings = match find_and_read_manifest(Path::new(path)) {
+            Ok(mut plugin_manifest) => PluginSettings {
+                limits: self.default_limits.with_overrides(&plugin_manifest.limits),
+                permissions: self
+                    .resolve_permissions(name, plugin_manifest.permissions.as_ref())?,
+                config: ConfigSpec::from_manifest(&plugin_manifest.config)?,
+                schedule: TickSchedule::from_manifest(&plugin_manifest.schedule)
+                    .map_err(|e| anyhow!("Plugin '{}' has invalid schedule: {}", name, e))?,
+                topics: std::mem::take(&mut plugin_manifest.topics),
+                info: PluginInfo::from_manifest(Path::new(path), plugin_manifest),
+            },
+            Err(e) => {
+                tracing::warn!("读取插件清单失败，使用默认资源限制与权限: {} ({})", name, e);
+                PluginSettings {
+                    limits: self.default_limits,
+                    permissions: self.default_permissions.clone(),
+                    config: ConfigSpec::default(),
+                    schedule: None,
+                    topics: Vec::new(),
+                    info: PluginInfo::from_path(Path::new(path)),
+                }
+            }
+        };
+        tracing::info!(
+            "插件 {} 获得权限: {:?}",
+            name,
+            settings.permissions.describe()
+        );
+        Ok(settings)
+    }
+
+    /// 创建插件实例及其独立的主机上下文
+    fn instantiate(
+        &self,
+        name: &str,
+        path: &str,
+        settings: &mut PluginSettings,
+    ) -> Result<(Plugin, Arc<Mutex<HostContext>>)> {
         // 加载 WASM 文件
         let wasm = Wasm::file(path);
-        let manifest = Manifest::new([wasm]);
+        let manifest = settings.limits.apply(Manifest::new([wasm]));
+
+        // 为插件创建独立的主机上下文
+        let context = Arc::new(Mutex::new(
+            self.base_context
+                .for_plugin(name, settings.permissions.clone()),
+        ));
 
         // 使用带有主机函数的插件构建器
-        let plugin = build_plugin_with_host_functions(manifest, self.context_store.clone())?;
+        let mut plugin =
+            build_plugin_with_host_functions(manifest, create_context_store(context.clone()))?;
 
-        // 存储插件
-        self.plugins.insert(name.to_string(), plugin);
+        // 配置默认值与插件声明的 schema
+        let config = std::mem::take(&mut settings.config);
+        context.lock().unwrap().config = load_config_spec(name, &mut plugin, config);
+        Ok((plugin, context))
+    }
 
+    /// 记录已加载插件的依赖信息
+    fn record_dependencies(&mut self, name: &str, info: Result<PluginInfo>) {
+        if let Ok(mut info) = info {
+            info.name = name.to_string();
+            info.loaded = true;
+            self.dependency_resolver.add_plugin(info);
+        }
+    }
+
+    /// 已加载插件的文件路径
+    pub fn plugin_path(&self, name: &str) -> Option<&Path> {
+        self.paths.get(name).map(PathBuf::as_path)
+    }
+
+    /// 从指定文件加载的插件（按规范化路径比较）
+    pub fn plugin_for_path(&self, path: &Path) -> Option<&str> {
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        let target = canonical(path);
+        self.paths
+            .iter()
+            .find(|(_, loaded)| canonical(loaded) == target)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// 以已保存的配置（合并 manifest 默认值）调用插件的 initialize 函数
+    fn initialize_plugin(&mut self, name: &str) -> Result<()> {
+        let input = match self.contexts.get(name) {
+            Some(context) => self.initialize_input(name, context),
+            None => {
+                serde_json::json!({ "data": {}, "enabled": true, "log_level": "info" }).to_string()
+            }
+        };
+        self.call_lifecycle(name, INITIALIZE_FN, &input)?;
+
+        self.set_state(name, PluginState::Running)
+    }
+
+    /// initialize 函数的输入：已保存的配置合并 manifest 默认值
+    fn initialize_input(&self, name: &str, context: &Mutex<HostContext>) -> String {
+        let stored = match &self.base_context.storage {
+            Some(storage) => block_on(storage.get_plugin_config(name))
+                .transpose()
+                .unwrap_or_else(|e| {
+                    tracing::warn!("读取插件配置失败，使用默认配置: {} ({})", name, e);
+                    None
+                })
+                .flatten(),
+            None => None,
+        };
+        let data = context.lock().unwrap().config.resolve(stored);
+
+        serde_json::json!({
+            "data": data,
+            "enabled": true,
+            "log_level": "info",
+        })
+        .to_string()
+    }
+
+    /// 暂停插件：停止投递消息（消息保留在通道中）与定时任务
+    pub fn pause_plugin(&mut self, name: &str) -> Result<()> {
+        self.expect_state(name, PluginState::Running)?;
+        self.call_lifecycle(name, PAUSE_FN, "")?;
+        self.set_state(name, PluginState::Paused)
+    }
+
+    /// 恢复已暂停的插件，继续投递暂存的消息
+    pub fn resume_plugin(&mut self, name: &str) -> Result<()> {
+        self.expect_state(name, PluginState::Paused)?;
+        self.call_lifecycle(name, RESUME_FN, "")?;
+        self.set_state(name, PluginState::Running)
+    }
+
+    /// 检查插件是否处于指定状态
+    fn expect_state(&self, name: &str, expected: PluginState) -> Result<()> {
+        match self.plugin_state(name) {
+            Some(state) if state == expected => Ok(()),
+            Some(state) => Err(anyhow!(
+                "Plugin '{}' is {} (expected {})",
+                name,
+                state,
+                expected
+            )),
+            None => Err(anyhow!("Plugin '{}' not found", name)),
+        }
+    }
+
+    /// 切换插件的生命周期状态并持久化
+    fn set_state(&self, name: &str, next: PluginState) -> Result<()> {
+        let state = self
+            .states
+            .get(name)
+            .ok_or_else(|| anyhow!("Plugin '{}' not found", name))?;
+        let current = *state.borrow();
+        if !current.can_transition_to(next) {
+            return Err(anyhow!(
+                "Plugin '{}' cannot transition from {} to {}",
+                name,
+                current,
+                next
+            ));
+        }
+
+        state.send_replace(next);
+        self.persist_state(name, next);
+        tracing::info!("插件 {} 状态: {} -> {}", name, current, next);
         Ok(())
     }
 
+    /// 将插件状态写入 plugin_metadata
+    fn persist_state(&self, name: &str, state: PluginState) {
+        let Some(storage) = &self.base_context.storage else {
+            return;
+        };
+        if let Some(Err(e)) = block_on(storage.set_plugin_state(name, state.as_str())) {
+            tracing::warn!("保存插件状态失败: {} ({})", name, e);
+        }
+    }
+
+    /// 将已保存的主题订阅恢复到消息总线
+    fn restore_subscriptions(&self, name: &str) {
+        let (Some(storage), Some(bus)) = (&self.base_context.storage, &self.message_bus) else {
+            return;
+        };
+        let topics = match block_on(storage.get_plugin_subscriptions(name)) {
+            Some(Ok(topics)) => topics,
+            Some(Err(e)) => {
+                tracing::warn!("读取插件 {} 的订阅失败: {}", name, e);
+                return;
+            }
+            None => return,
+        };
+
+        let mut restored = 0;
+        for topic in &topics {
+            if let Err(e) = validate_topic_pattern(topic) {
+                tracing::warn!("丢弃插件 {} 的无效订阅: {}", name, e);
+                let _ = block_on(storage.remove_subscription(name, topic));
+                continue;
+            }
+            bus.subscribe_topic(name, topic);
+            restored += 1;
+        }
+        if restored > 0 {
+            tracing::info!("已恢复插件 {} 的 {} 个主题订阅", name, restored);
+        }
+    }
+
+    /// 清理已不在插件目录中（且未加载）的插件的持久化订阅，返回被清理的插件
+    pub fn reconcile_subscriptions(&self, plugin_dir: &Path) -> Result<Vec<String>> {
+        let Some(storage) = &self.base_context.storage else {
+            return Ok(Vec::new());
+        };
+        // 目录不存在时无法判断哪些插件已卸载，不做清理
+        if !plugin_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut installed: Vec<String> = self
+            .discover_plugins(plugin_dir)?
+            .into_iter()
+            .map(|info| info.name)
+            .collect();
+        installed.extend(self.plugins.keys().cloned());
+
+        let Some(subscribed) = block_on(storage.list_subscribed_plugins()) else {
+            return Ok(Vec::new());
+        };
+        let mut removed = Vec::new();
+        for plugin_id in subscribed? {
+            if installed.contains(&plugin_id) {
+                continue;
+            }
+            if let Some(Err(e)) = block_on(storage.remove_plugin_subscriptions(&plugin_id)) {
+                tracing::warn!("清理插件 {} 的订阅失败: {}", plugin_id, e);
+                continue;
+            }
+            removed.push(plugin_id);
+        }
+
+        if !removed.is_empty() {
+            tracing::info!("已清理未安装插件的订阅: {:?}", removed);
+        }
+        Ok(removed)
+    }
+
+    /// 调用插件的生命周期函数（插件未导出该函数时跳过）
+    fn call_lifecycle(
+        &mut self,
+        name: &str,
+        function: &str,
+        input: &str,
+    ) -> Result<Option<String>> {
+        let plugin = self.get_plugin(name)?;
+        let exported = plugin
+            .lock()
+            .map_err(|_| anyhow!("Plugin '{}' lock poisoned", name))?
+            .function_exists(function);
+        if !exported {
+            return Ok(None);
+        }
+
+        self.call_plugin_string(name, function, input).map(Some)
+    }
+
+    /// 解析插件的权限声明
+    fn resolve_permissions(
+        &self,
+        name: &str,
+        permissions: Option<&Permissions>,
+    ) -> Result<PermissionSet> {
+        match permissions {
+            Some(permissions) => PermissionSet::parse(&permissions.allow)
+                .map_err(|e| anyhow!("Plugin '{}' has invalid permissions: {}", name, e)),
+            None => {
+                tracing::warn!("插件 {} 未声明 [permissions]，使用默认权限", name);
+                Ok(self.default_permissions.clone())
+            }
+        }
+    }
+
     /// 获取指定名称的插件
-    pub fn get_plugin(&self, name: &str) -> Result<&Plugin> {
+    pub fn get_plugin(&self, name: &str) -> Result<SharedPlugin> {
         self.plugins
             .get(name)
+            .cloned()
             .ok_or_else(|| anyhow!("Plugin '{}' not found", name))
     }
 
-    /// 获取指定名称的插件（可变引用）
-    pub fn get_plugin_mut(&mut self, name: &str) -> Result<&mut Plugin> {
-        self.plugins
-            .get_mut(name)
-            .ok_or_else(|| anyhow!("Plugin '{}' not found", name))
+    /// 关闭并卸载指定插件
+    pub fn unload_plugin(&mut self, name: &str) -> Result<()> {
+        let state = self
+            .plugin_state(name)
+            .ok_or_else(|| anyhow!("Plugin '{}' not found", name))?;
+
+        // 先切换状态停止投递，再通知插件释放资源（故障插件已无法执行）
+        if state != PluginState::Shutdown {
+            self.set_state(name, PluginState::Shutdown)?;
+        }
+        if state.is_active() && !self.is_faulted(name) {
+            if let Err(e) = self.call_lifecycle(name, SHUTDOWN_FN, "") {
+                tracing::warn!("插件 {} 关闭失败: {}", name, e);
+            }
+        }
+
+        self.remove_plugin(name)
     }
 
-    /// 卸载指定插件
-    pub fn unload_plugin(&mut self, name: &str) -> Result<()> {
+    /// 按依赖的逆序关闭所有插件（依赖方先于被依赖的插件关闭）
+    pub fn shutdown_all(&mut self) -> Vec<String> {
+        let mut names: Vec<String> = self.plugins.keys().cloned().collect();
+        names.sort();
+
+        let mut order = match self.dependency_resolver.resolve_order(&names) {
+            Ok(order) => order
+                .into_iter()
+                .filter(|name| self.plugins.contains_key(name))
+                .collect(),
+            Err(e) => {
+                tracing::warn!("解析插件依赖失败，按名称顺序关闭: {}", e);
+                names
+            }
+        };
+        order.reverse();
+
+        for name in &order {
+            if let Err(e) = self.unload_plugin(name) {
+                tracing::warn!("卸载插件 {} 失败: {}", name, e);
+            }
+        }
+
+        order
+    }
+
+    /// 移除插件实例及其上下文、状态与消息通道
+    fn remove_plugin(&mut self, name: &str) -> Result<()> {
         self.plugins
             .remove(name)
-            .ok_or_else(|| anyhow!("Plugin '{}' not found", name))
-            .map(|_| ())
+            .ok_or_else(|| anyhow!("Plugin '{}' not found", name))?;
+        self.paths.remove(name);
+        self.contexts.remove(name);
+        self.limits.remove(name);
+        self.dependency_resolver.mark_unloaded(name);
+        self.states.remove(name);
+        self.tick_stats.remove(name);
+        self.faults.write().remove(name);
+        if let Some(ticker) = self.tickers.remove(name) {
+            ticker.abort();
+        }
+
+        // 拆除拦截器、主题 schema、消息通道与分发任务
+        if let Some(bus) = &self.message_bus {
+            bus.interceptors()
+                .remove(&PluginInterceptor::name_for(name));
+            bus.schemas().unregister(name);
+            bus.unregister_plugin(name);
+        }
+        if let Some(dispatcher) = self.dispatchers.remove(name) {
+            dispatcher.abort();
+        }
+
+        Ok(())
+    }
+
+    /// 插件持有 `bus.intercept` 权限并导出拦截函数时，将其加入总线的拦截器链
+    ///
+    /// 拦截器是插件的另一个实例（共享主机上下文），不会被消息分发或定时任务占用；
+    /// 该实例不调用生命周期函数，也不共享内存中的状态
+    fn register_interceptor(&self, name: &str) {
+        let Some(bus) = &self.message_bus else {
+            return;
+        };
+        let allowed = self
+            .plugin_permissions(name)
+            .is_some_and(|permissions| permissions.allows(&Capability::BusIntercept));
+        if !allowed {
+            return;
+        }
+        let (Some(path), Some(context), Some(state)) = (
+            self.paths.get(name),
+            self.contexts.get(name),
+            self.states.get(name),
+        ) else {
+            return;
+        };
+
+        let limits = self.plugin_limits(name).unwrap_or(self.default_limits);
+        let manifest = limits.apply(Manifest::new([Wasm::file(path)]));
+        let plugin =
+            match build_plugin_with_host_functions(manifest, create_context_store(context.clone()))
+            {
+                Ok(plugin) => plugin,
+                Err(e) => {
+                    tracing::error!("创建插件 {} 的拦截器实例失败: {}", name, e);
+                    return;
+                }
+            };
+        if !plugin.function_exists(INTERCEPT_FN) {
+            tracing::warn!(
+                "插件 {} 有 bus.intercept 权限但未导出 {} 函数，不作为拦截器",
+                name,
+                INTERCEPT_FN
+            );
+            return;
+        }
+
+        bus.interceptors().add(Arc::new(PluginInterceptor::new(
+            name,
+            Arc::new(Mutex::new(plugin)),
+            state.subscribe(),
+            bus.clone(),
+            self.faults.clone(),
+            limits.timeout_ms,
+        )));
+        tracing::info!("插件 {} 已注册为消息拦截器", name);
+    }
+
+    /// 为插件注册总线通道并启动消息分发任务
+    fn start_dispatcher(&mut self, name: &str, plugin: SharedPlugin) {
+        let Some(bus) = &self.message_bus else {
+            return;
+        };
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("没有可用的 tokio 运行时，插件 {} 将无法接收总线消息", name);
+            return;
+        };
+
+        let (Some(state), Some(limits)) = (
+            self.states.get(name).map(|state| state.subscribe()),
+            self.limits.get(name).map(|limits| limits.subscribe()),
+        ) else {
+            return;
+        };
+
+        let queue_config = limits.borrow().queue_config();
+        let receiver = bus.register_plugin_with_queue(name.to_string(), queue_config);
+        let task = runtime.spawn(dispatch_messages(
+            name.to_string(),
+            plugin,
+            receiver,
+            state,
+            self.msg_sender.clone(),
+            bus.clone(),
+            self.faults.clone(),
+            limits,
+        ));
+
+        if let Some(old) = self.dispatchers.insert(name.to_string(), task) {
+            old.abort();
+        }
+    }
+
+    /// 启动插件的定时任务（仅在插件运行时调用 tick）
+    fn start_ticker(&mut self, name: &str, plugin: SharedPlugin, schedule: TickSchedule) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("没有可用的 tokio 运行时，插件 {} 的定时任务不会执行", name);
+            return;
+        };
+        let Some(state) = self.states.get(name).map(|state| state.subscribe()) else {
+            return;
+        };
+
+        let stats = SharedTickStats::default();
+        let timeout_ms = self
+            .plugin_limits(name)
+            .map_or(self.default_limits.timeout_ms, |limits| limits.timeout_ms);
+        let task = runtime.spawn(run_ticks(
+            name.to_string(),
+            plugin,
+            schedule,
+            state,
+            stats.clone(),
+            self.faults.clone(),
+            timeout_ms,
+        ));
+
+        self.tick_stats.insert(name.to_string(), stats);
+        if let Some(old) = self.tickers.insert(name.to_string(), task) {
+            old.abort();
+        }
     }
 
     /// 调用插件函数
@@ -239,19 +1094,14 @@ impl PluginLoader {
         I: serde::Serialize,
         O: serde::de::DeserializeOwned,
     {
-        // 获取插件
-        let plugin = self.get_plugin_mut(plugin_name)?;
-
         // 序列化输入
         let input_json = serde_json::to_string(&input)?;
 
         // 调用插件函数
-        let output = plugin
-            .call::<&str, &str>(function_name, &input_json)
-            .map_err(|e| anyhow!("Failed to call plugin function '{}': {}", function_name, e))?;
+        let output = self.call_plugin_string(plugin_name, function_name, &input_json)?;
 
         // 反序列化输出
-        serde_json::from_str(output)
+        serde_json::from_str(&output)
             .map_err(|e| anyhow!("Failed to deserialize plugin output: {}", e))
     }
 
@@ -262,10 +1112,40 @@ impl PluginLoader {
         function_name: &str,
         input: &str,
     ) -> Result<String> {
-        let plugin = self.get_plugin_mut(plugin_name)?;
-        plugin
-            .call::<&str, String>(function_name, input)
-            .map_err(|e| anyhow!("Failed to call plugin function '{}': {}", function_name, e))
+        let plugin = self.get_plugin(plugin_name)?;
+        check_fault(&self.faults, plugin_name)?;
+
+        // 主机函数内部会 block_on，需在允许阻塞的上下文中调用
+        let (output, elapsed) = run_blocking(|| {
+            let mut plugin = plugin
+                .lock()
+                .map_err(|_| anyhow!("Plugin '{}' lock poisoned", plugin_name))?;
+            let started = Instant::now();
+            let output = plugin.call::<&str, String>(function_name, input);
+            Ok::<_, anyhow::Error>((output, started.elapsed()))
+        })?;
+        output.map_err(|e| self.call_error(plugin_name, function_name, elapsed, e))
+    }
+
+    /// 转换插件调用错误（超时会将插件标记为故障）
+    fn call_error(
+        &self,
+        plugin_name: &str,
+        function_name: &str,
+        elapsed: Duration,
+        error: Error,
+    ) -> anyhow::Error {
+        let timeout_ms = self
+            .plugin_limits(plugin_name)
+            .map_or(self.default_limits.timeout_ms, |limits| limits.timeout_ms);
+        convert_call_error(
+            &self.faults,
+            plugin_name,
+            function_name,
+            timeout_ms,
+            elapsed,
+            error,
+        )
     }
 
     /// 扫描目录并自动加载插件
@@ -324,38 +1204,11 @@ impl PluginLoader {
 
     /// 发现插件文件但不加载
     pub fn discover_plugins(&self, plugin_dir: &Path) -> Result<Vec<PluginInfo>> {
-        let mut plugins = Vec::new();
-
-        if !plugin_dir.exists() {
-            return Ok(plugins);
-        }
-
-        // 使用 walkdir 遍历目录查找 .wasm 文件
-        for entry in WalkDir::new(plugin_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
-        {
-            let wasm_path = entry.path();
-
-            // 尝试读取 manifest.toml，如果有的话
-            let plugin_info = match find_and_read_manifest(wasm_path) {
-                Ok(manifest) => {
-                    tracing::debug!("使用 manifest 创建插件信息: {}", wasm_path.display());
-                    PluginInfo::from_manifest(wasm_path, manifest)?
-                }
-                Err(_) => {
-                    tracing::debug!("使用路径创建插件信息: {}", wasm_path.display());
-                    PluginInfo::from_path(wasm_path)?
-                }
-            };
+        let mut plugins = scan_plugins(plugin_dir)?;
 
-            // 检查插件是否已加载
-            let mut info = plugin_info;
+        // 检查插件是否已加载
+        for info in &mut plugins {
             info.loaded = self.plugins.contains_key(&info.name);
-
-            plugins.push(info);
         }
 
         Ok(plugins)
@@ -419,86 +1272,99 @@ impl PluginLoader {
     }
 
     /// 使用依赖解析加载插件
+    ///
+    /// `target_plugins` 为空时加载目录中发现的所有插件。依赖解析失败的目标记为失败，
+    /// 必需依赖加载失败或被跳过时跳过依赖方，可选依赖存在时一并加载
     pub fn load_plugins_with_dependencies(
         &mut self,
         plugin_dir: &Path,
         target_plugins: &[String],
-    ) -> Result<Vec<String>> {
+    ) -> Result<LoadReport> {
+        // 确保插件目录存在
+        if !plugin_dir.exists() {
+            std::fs::create_dir_all(plugin_dir)?;
+        }
+
         // 发现所有插件
         let discovered_plugins = self.discover_plugins(plugin_dir)?;
+        let targets: Vec<String> = if target_plugins.is_empty() {
+            let mut names: Vec<String> = discovered_plugins
+                .iter()
+                .map(|plugin| plugin.name.clone())
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        } else {
+            target_plugins.to_vec()
+        };
 
         // 添加到依赖解析器
         self.dependency_resolver.add_plugins(discovered_plugins);
 
-        // 解析加载顺序
-        let load_order = self.dependency_resolver.resolve_order(target_plugins)?;
+        // 逐个加入目标，无法与已接受的目标一起解析的记为失败
+        let mut report = LoadReport::default();
+        let mut accepted = Vec::new();
+        for target in targets {
+            accepted.push(target);
+            if let Err(e) = self.dependency_resolver.resolve(&accepted) {
+                let target = accepted.pop().unwrap_or_default();
+                report.fail(&target, e.to_string());
+            }
+        }
 
-        let mut loaded_plugins = Vec::new();
+        // 选择满足版本要求的插件版本并解析加载顺序
+        let load_order = self.dependency_resolver.resolve(&accepted)?;
 
         // 按顺序加载插件
-        for plugin_name in load_order {
-            if self.plugins.contains_key(&plugin_name) {
+        for plugin in load_order {
+            if self.plugins.contains_key(&plugin.name) {
                 continue; // 已加载
             }
 
-            // 查找插件文件
-            if let Some(plugin_path) = self.find_plugin_path(plugin_dir, &plugin_name)? {
-                match self.load_plugin(&plugin_name, plugin_path.to_str().unwrap()) {
-                    Ok(_) => {
-                        loaded_plugins.push(plugin_name.clone());
-                        tracing::info!("依赖加载插件: {} ({})", plugin_name, plugin_path.display());
-                    }
-                    Err(e) => {
-                        tracing::warn!(
-                            "依赖加载失败: {} ({}): {}",
-                            plugin_name,
-                            plugin_path.display(),
-                            e
-                        );
-                    }
+            // 必需依赖未加载时跳过，可选依赖缺失只记录
+            if let Some(dep) = plugin
+                .dependencies
+                .iter()
+                .find(|dep| report.is_missing(&dep.name))
+            {
+                let cause = if report.skipped.iter().any(|issue| issue.plugin == dep.name) {
+                    "被跳过"
+                } else {
+                    "加载失败"
+                };
+                report.skip(&plugin.name, format!("必需依赖 {} {}", dep.name, cause));
+                continue;
+            }
+            for dep in &plugin.optional_dependencies {
+                if report.is_missing(&dep.name) {
+                    tracing::info!("插件 {} 的可选依赖 {} 未加载", plugin.name, dep.name);
                 }
-            } else {
-                tracing::warn!("未找到插件文件: {}", plugin_name);
             }
-        }
 
-        Ok(loaded_plugins)
-    }
-
-    /// 查找插件文件路径
-    fn find_plugin_path(&self, plugin_dir: &Path, plugin_name: &str) -> Result<Option<PathBuf>> {
-        // 尝试多种可能的路径
-        let possible_paths = vec![
-            plugin_dir.join(format!("{plugin_name}.wasm")),
-            plugin_dir
-                .join(plugin_name)
-                .join(format!("{plugin_name}.wasm")),
-            plugin_dir
-                .join(plugin_name)
-                .join("target/wasm32-unknown-unknown/release")
-                .join(format!("{plugin_name}.wasm")),
-        ];
-
-        for path in possible_paths {
-            if path.exists() {
-                return Ok(Some(path));
-            }
-        }
-
-        // 使用 walkdir 搜索
-        for entry in WalkDir::new(plugin_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
-        {
-            let wasm_path = entry.path();
-            if self.extract_plugin_name(wasm_path)? == plugin_name {
-                return Ok(Some(wasm_path.to_path_buf()));
+            match self.load_plugin(&plugin.name, plugin.path.to_str().unwrap()) {
+                Ok(_) => {
+                    report.loaded.push(plugin.name.clone());
+                    tracing::info!(
+                        "依赖加载插件: {} {} ({})",
+                        plugin.name,
+                        plugin.version,
+                        plugin.path.display()
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "依赖加载失败: {} ({}): {}",
+                        plugin.name,
+                        plugin.path.display(),
+                        e
+                    );
+                    report.fail(&plugin.name, e.to_string());
+                }
             }
         }
 
-        Ok(None)
+        Ok(report)
     }
 
     /// 检查依赖是否满足
@@ -524,9 +1390,403 @@ impl PluginLoader {
     }
 }
 
+/// 扫描插件目录中的 wasm 文件及其清单（不检查加载状态）
+pub fn scan_plugins(plugin_dir: &Path) -> Result<Vec<PluginInfo>> {
+    let mut plugins = Vec::new();
+
+    if !plugin_dir.exists() {
+        return Ok(plugins);
+    }
+
+    // 使用 walkdir 遍历目录查找 .wasm 文件
+    for entry in WalkDir::new(plugin_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
+    {
+        let wasm_path = entry.path();
+
+        // 尝试读取 manifest.toml，如果有的话
+        let plugin_info = match find_and_read_manifest(wasm_path) {
+            Ok(manifest) => {
+                tracing::debug!("使用 manifest 创建插件信息: {}", wasm_path.display());
+                PluginInfo::from_manifest(wasm_path, manifest)?
+            }
+            Err(_) => {
+                tracing::debug!("使用路径创建插件信息: {}", wasm_path.display());
+                PluginInfo::from_path(wasm_path)?
+            }
+        };
+
+        plugins.push(plugin_info);
+    }
+
+    Ok(plugins)
+}
+
+/// 读取插件元数据中声明的 `config_schema`，与 manifest 默认值组成配置规则
+///
+/// schema 无效或默认值不符合 schema 时只记录警告，不阻止插件加载
+fn load_config_spec(name: &str, plugin: &mut Plugin, config: ConfigSpec) -> ConfigSpec {
+    if !plugin.function_exists(METADATA_FN) {
+        return config;
+    }
+
+    let schema = match plugin.call::<&str, String>(METADATA_FN, "") {
+        Ok(metadata) => serde_json::from_str::<serde_json::Value>(&metadata)
+            .ok()
+            .and_then(|metadata| metadata.get("config_schema").cloned())
+            .filter(|schema| !schema.is_null()),
+        Err(e) => {
+            tracing::warn!("读取插件元数据失败: {} ({})", name, e);
+            None
+        }
+    };
+    let Some(schema) = schema else {
+        return config;
+    };
+
+    match config.clone().with_schema(schema) {
+        Ok(config) => {
+            if let Err(e) = config.validate(&config.resolve(None)) {
+                tracing::warn!("插件 {} 的默认配置不符合 schema: {}", name, e);
+            }
+            config
+        }
+        Err(e) => {
+            tracing::warn!("插件 {} 声明的配置 schema 无效: {}", name, e);
+            config
+        }
+    }
+}
+
+/// 在允许阻塞的上下文中执行同步操作
+///
+/// 多线程运行时使用 block_in_place；单线程运行时在独立线程中执行，
+/// 以便其中的 `Handle::block_on` 不会阻塞运行时所在线程
+fn run_blocking<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return f();
+    };
+
+    match handle.runtime_flavor() {
+        RuntimeFlavor::MultiThread => tokio::task::block_in_place(f),
+        _ => std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _guard = handle.enter();
+                    f()
+                })
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e))
+        }),
+    }
+}
+
+/// 在同步代码中等待异步操作完成（没有 tokio 运行时时返回 None）
+fn block_on<F>(future: F) -> Option<F::Output>
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    let handle = tokio::runtime::Handle::try_current().ok()?;
+    Some(run_blocking(move || handle.block_on(future)))
+}
+
+/// 检查插件是否已故障
+pub(super) fn check_fault(faults: &FaultMap, plugin_name: &str) -> Result<()> {
+    match faults.read().get(plugin_name) {
+        Some(reason) => Err(PluginCallError::Faulted {
+            plugin: plugin_name.to_string(),
+            reason: reason.clone(),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// 将 Extism 调用错误转换为内核错误
+///
+/// Extism 在超时中断后插件实例无法继续执行，因此超时会记录为插件故障。
+/// Extism 以 `timeout` 错误报告超时中断；同时要求调用耗时达到超时时间，
+/// 避免插件自身返回的同名错误被当作超时
+pub(super) fn convert_call_error(
+    faults: &FaultMap,
+    plugin_name: &str,
+    function_name: &str,
+    timeout_ms: u64,
+    elapsed: Duration,
+    error: Error,
+) -> anyhow::Error {
+    let timed_out = error.root_cause().to_string() == EXTISM_TIMEOUT_ERROR
+        && timeout_ms > 0
+        && elapsed >= Duration::from_millis(timeout_ms);
+    if !timed_out {
+        return anyhow!(
+            "Failed to call plugin function '{}': {}",
+            function_name,
+            error
+        );
+    }
+
+    let error = PluginCallError::Timeout {
+        plugin: plugin_name.to_string(),
+        function: function_name.to_string(),
+        timeout_ms,
+    };
+    tracing::error!("{}，已将插件标记为故障", error);
+    faults
+        .write()
+        .insert(plugin_name.to_string(), error.to_string());
+    error.into()
+}
+
+/// 将总线消息逐条投递给插件的 handle_message 导出函数
+async fn dispatch_messages(
+    plugin_name: String,
+    plugin: SharedPlugin,
+    mut receiver: PluginReceiver,
+    mut state: watch::Receiver<PluginState>,
+    msg_sender: mpsc::Sender<Message>,
+    bus: MessageBusHandle,
+    faults: FaultMap,
+    limits: watch::Receiver<RuntimeLimits>,
+) {
+    tracing::debug!("插件 {} 的消息分发任务已启动", plugin_name);
+
+    while let Some(message) = receiver.recv().await {
+        // 初始化完成前或暂停期间暂缓投递，插件被移除时结束
+        let current = match state.wait_for(|state| !state.holds_messages()).await {
+            Ok(state) => *state,
+            Err(_) => break,
+        };
+        if current != PluginState::Running {
+            let error = format!("插件状态为 {current}，无法处理消息");
+            settle_failure(&bus, &msg_sender, &plugin_name, &message, &error).await;
+            continue;
+        }
+
+        // 在通道中等待期间过期的消息不再交给插件
+        if message.is_expired() {
+            tracing::debug!("消息 {} 已过期，不再投递给插件 {}", message.id, plugin_name);
+            bus.record_expired(&plugin_name);
+            if message.durable {
+                bus.ack_durable(&message.id, &plugin_name).await;
+            }
+            continue;
+        }
+
+        let input = match serde_json::to_string(&PluginMessage::from(&message)) {
+            Ok(input) => input,
+            Err(e) => {
+                settle_failure(&bus, &msg_sender, &plugin_name, &message, &e.to_string()).await;
+                continue;
+            }
+        };
+
+        // 插件调用是阻塞的，且主机函数内部会 block_on，需放到阻塞线程执行
+        let plugin = plugin.clone();
+        let faults = faults.clone();
+        let name = plugin_name.clone();
+        let limits = limits.clone();
+        let result = tokio::task::spawn_blocking(move || -> Result<String> {
+            check_fault(&faults, &name)?;
+            let mut plugin = plugin.lock().map_err(|_| anyhow!("插件锁已中毒"))?;
+            // 持有插件锁后读取限制，热重载替换实例后使用新实例的超时
+            let timeout_ms = limits.borrow().timeout_ms;
+            if !plugin.function_exists(HANDLE_MESSAGE_FN) {
+                return Err(anyhow!("插件未导出 {} 函数", HANDLE_MESSAGE_FN));
+            }
+            let started = Instant::now();
+            plugin
+                .call::<&str, String>(HANDLE_MESSAGE_FN, &input)
+                .map_err(|e| {
+                    let elapsed = started.elapsed();
+                    convert_call_error(&faults, &name, HANDLE_MESSAGE_FN, timeout_ms, elapsed, e)
+                })
+        })
+        .await;
+
+        let error = match result {
+            Ok(Ok(_)) => {
+                tracing::trace!("消息 {} 已投递给插件 {}", message.id, plugin_name);
+                if message.durable {
+                    bus.ack_durable(&message.id, &plugin_name).await;
+                }
+                continue;
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => format!("分发任务失败: {e}"),
+        };
+
+        tracing::warn!(
+            "插件 {} 处理消息 {} 失败: {}",
+            plugin_name,
+            message.id,
+            error
+        );
+        settle_failure(&bus, &msg_sender, &plugin_name, &message, &error).await;
+    }
+
+    tracing::debug!("插件 {} 的消息分发任务已结束", plugin_name);
+}
+
+/// 处理投递失败：持久消息安排重投，仅在移入死信表后回报发送者
+async fn settle_failure(
+    bus: &MessageBusHandle,
+    msg_sender: &mpsc::Sender<Message>,
+    plugin_name: &str,
+    message: &Message,
+    error: &str,
+) {
+    bus.record_failed(plugin_name);
+    if message.durable {
+        match bus.nack_durable(&message.id, plugin_name, error).await {
+            Some(NackOutcome::Retry { .. }) | Some(NackOutcome::Unknown) => return,
+            Some(NackOutcome::DeadLettered) | None => {}
+        }
+    }
+    report_delivery_error(msg_sender, plugin_name, message, error);
+}
+
+/// 向消息发送者回报投递错误
+fn report_delivery_error(
+    msg_sender: &mpsc::Sender<Message>,
+    plugin_name: &str,
+    message: &Message,
+    error: &str,
+) {
+    // 不回报错误消息本身，避免两个插件之间来回弹跳
+    if message.from.is_empty() || message.msg_type.as_deref() == Some(DELIVERY_ERROR_TYPE) {
+        return;
+    }
+
+    let payload = serde_json::json!({
+        "message_id": message.id,
+        "plugin": plugin_name,
+        "error": error,
+    });
+    let mut report = Message::new(
+        plugin_name.to_string(),
+        message.from.clone(),
+        payload.to_string().into_bytes(),
+    )
+    .with_type(DELIVERY_ERROR_TYPE.to_string());
+
+    // 请求处理失败时让等待方立即收到错误
+    if let Some(correlation_id) = &message.correlation_id {
+        report = report.with_correlation_id(correlation_id.clone());
+    }
+
+    if let Err(e) = msg_sender.try_send(report) {
+        tracing::warn!("回报投递错误失败: {}", e);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kernel::message::MessagePriority;
+    use crate::kernel::message_bus::create_message_bus;
+    use tempfile::TempDir;
+    use tokio::time::{timeout, Duration};
+
+    /// 只导出 handle_message 且总是返回错误码的最小插件
+    const FAILING_HANDLER_WAT: &str =
+        r#"(module (func (export "handle_message") (result i32) i32.const 1))"#;
+
+    /// 导出一个死循环函数的最小插件
+    const SPIN_WAT: &str =
+        r#"(module (func (export "spin") (result i32) (loop br 0) i32.const 0))"#;
+
+    /// 测试插件的导出函数
+    enum Export<'a> {
+        /// 以给定的字符串参数调用主机函数，并输出其响应
+        Call(&'a str, &'a [&'a str]),
+        /// 输出固定字符串
+        Output(&'a str),
+    }
+
+    /// 生成使用 Extism 内核内存调用主机函数的 WAT 测试插件
+    fn wat_plugin(exports: &[(&str, Export)]) -> String {
+        let mut imports = Vec::new();
+        let mut data = String::new();
+        let mut funcs = String::new();
+        let mut offset = 0;
+
+        // 每个字符串参数放在独立的数据段中，由 $str 复制到 Extism 内存
+        let mut string_arg = |value: &str| {
+            let bytes: String = value.bytes().map(|b| format!("\\{b:02x}")).collect();
+            data.push_str(&format!("  (data (i32.const {offset}) \"{bytes}\")\n"));
+            let arg = format!(
+                "(call $str (i32.const {offset}) (i32.const {}))",
+                value.len()
+            );
+            offset += value.len() + 1;
+            arg
+        };
+
+        for (name, export) in exports {
+            let value = match export {
+                Export::Call(function, args) => {
+                    if !imports.iter().any(|(f, _)| f == function) {
+                        imports.push((*function, args.len()));
+                    }
+                    let args: Vec<String> = args.iter().map(|arg| string_arg(arg)).collect();
+                    format!("(call ${function} {})", args.join(" "))
+                }
+                Export::Output(value) => string_arg(value),
+            };
+            funcs.push_str(&format!(
+                "  (func (export \"{name}\") (result i32) (local $r i64)\n    \
+                 (local.set $r {value})\n    \
+                 (call $output_set (local.get $r) (call $length (local.get $r)))\n    \
+                 (i32.const 0))\n"
+            ));
+        }
+
+        let imports: String = imports
+            .iter()
+            .map(|(function, arity)| {
+                let params = vec!["i64"; *arity].join(" ");
+                format!(
+                    "  (import \"extism:host/user\" \"{function}\" \
+                     (func ${function} (param {params}) (result i64)))\n"
+                )
+            })
+            .collect();
+
+        format!(
+            r#"(module
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "length" (func $length (param i64) (result i64)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+{imports}  (memory 1)
+{data}  (func $str (param $off i32) (param $len i32) (result i64)
+    (local $h i64) (local $i i32)
+    (local.set $h (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $h) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $off) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (local.get $h))
+{funcs})"#
+        )
+    }
+
+    /// 以 ("demo", "key", "\"v\"") 调用 store_data_host 的插件
+    fn store_wat() -> String {
+        wat_plugin(&[(
+            "store",
+            Export::Call("store_data_host", &["demo", "key", "\"v\""]),
+        )])
+    }
 
     async fn create_test_loader() -> PluginLoader {
         // 使用内存数据库进行测试
@@ -550,4 +1810,1273 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("not found"));
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dispatch_reports_errors_and_unload_tears_down() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+
+        let temp_dir = TempDir::new().unwrap();
+        let wasm_path = temp_dir.path().join("failing.wasm");
+        std::fs::write(&wasm_path, FAILING_HANDLER_WAT).unwrap();
+        loader
+            .load_plugin("failing", wasm_path.to_str().unwrap())
+            .unwrap();
+
+        let mut tester_rx = handle.register_plugin("tester".to_string());
+        tokio::spawn(router.run());
+
+        // 插件处理失败时，发送者应收到投递错误回报
+        handle
+            .send_message(Message::new(
+                "tester".to_string(),
+                "failing".to_string(),
+                b"hello".to_vec(),
+            ))
+            .await
+            .unwrap();
+
+        let report = timeout(Duration::from_secs(5), tester_rx.recv())
+            .await
+            .expect("应收到投递错误回报")
+            .unwrap();
+        assert_eq!(report.from, "failing");
+        assert_eq!(report.msg_type.as_deref(), Some(DELIVERY_ERROR_TYPE));
+
+        // 卸载后通道被拆除，不再投递
+        loader.unload_plugin("failing").unwrap();
+        handle
+            .send_message(Message::new(
+                "tester".to_string(),
+                "failing".to_string(),
+                b"hello again".to_vec(),
+            ))
+            .await
+            .unwrap();
+
+        let result = timeout(Duration::from_millis(300), tester_rx.recv()).await;
+        assert!(result.is_err(), "卸载后不应再有回报");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_call_timeout_marks_plugin_faulted() {
+        let mut loader = create_test_loader().await;
+        loader.set_default_limits(RuntimeLimits {
+            timeout_ms: 100,
+            max_memory_mb: 16,
+            ..Default::default()
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let wasm_path = temp_dir.path().join("spin.wasm");
+        std::fs::write(&wasm_path, SPIN_WAT).unwrap();
+        loader
+            .load_plugin("spin", wasm_path.to_str().unwrap())
+            .unwrap();
+        assert_eq!(loader.plugin_limits("spin").unwrap().timeout_ms, 100);
+
+        let err = loader.call_plugin_string("spin", "spin", "").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PluginCallError>(),
+            Some(PluginCallError::Timeout {
+                timeout_ms: 100,
+                ..
+            })
+        ));
+        assert!(loader.is_faulted("spin"));
+
+        // 故障插件拒绝后续调用
+        let err = loader.call_plugin_string("spin", "spin", "").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PluginCallError>(),
+            Some(PluginCallError::Faulted { .. })
+        ));
+
+        // 卸载后故障记录被清除
+        loader.unload_plugin("spin").unwrap();
+        assert!(!loader.is_faulted("spin"));
+    }
+
+    #[test]
+    fn test_call_error_detects_extism_timeout() {
+        let faults = FaultMap::default();
+
+        // 未达到超时时间的 timeout 错误来自插件自身
+        let err = convert_call_error(
+            &faults,
+            "demo",
+            "run",
+            100,
+            Duration::from_millis(5),
+            Error::msg("timeout"),
+        );
+        assert!(err.downcast_ref::<PluginCallError>().is_none());
+        assert!(faults.read().is_empty());
+
+        // 耗时较长的其它错误（如等待主机函数）不是超时
+        convert_call_error(
+            &faults,
+            "demo",
+            "run",
+            100,
+            Duration::from_secs(1),
+            Error::msg("request failed"),
+        );
+        assert!(faults.read().is_empty());
+
+        let err = convert_call_error(
+            &faults,
+            "demo",
+            "run",
+            100,
+            Duration::from_millis(100),
+            Error::msg("timeout"),
+        );
+        assert!(matches!(
+            err.downcast_ref::<PluginCallError>(),
+            Some(PluginCallError::Timeout {
+                timeout_ms: 100,
+                ..
+            })
+        ));
+        assert!(faults.read().contains_key("demo"));
+    }
+
+    /// 在插件目录写入 manifest.toml 与插件并以指定名称加载
+    fn load_test_plugin(
+        loader: &mut PluginLoader,
+        dir: &Path,
+        name: &str,
+        manifest: &str,
+        wat: &str,
+    ) -> Result<()> {
+        let manifest = format!("[plugin]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n{manifest}");
+        std::fs::write(dir.join("manifest.toml"), manifest).unwrap();
+        let wasm_path = dir.join(format!("{name}.wasm"));
+        std::fs::write(&wasm_path, wat).unwrap();
+        loader.load_plugin(name, wasm_path.to_str().unwrap())
+    }
+
+    /// 加载 store_wat 插件并授予指定权限
+    fn load_store_plugin(
+        loader: &mut PluginLoader,
+        dir: &Path,
+        name: &str,
+        permissions: &str,
+    ) -> Result<()> {
+        let manifest = format!("[permissions]\nallow = {permissions}\n");
+        load_test_plugin(loader, dir, name, &manifest, &store_wat())
+    }
+
+    /// 在阻塞线程中调用插件（主机函数内部会 block_on）
+    async fn call_blocking(loader: &PluginLoader, name: &str, function: &str) -> String {
+        let plugin = loader.get_plugin(name).unwrap();
+        let function = function.to_string();
+        tokio::task::spawn_blocking(move || {
+            plugin
+                .lock()
+                .unwrap()
+                .call::<&str, String>(&function, "")
+                .unwrap()
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_host_function_requires_permission() {
+        let mut loader = create_test_loader().await;
+
+        // 未授予 storage 权限：返回结构化的权限错误
+        let denied_dir = TempDir::new().unwrap();
+        load_store_plugin(
+            &mut loader,
+            denied_dir.path(),
+            "demo",
+            r#"["bus.publish:demo.*"]"#,
+        )
+        .unwrap();
+        assert!(!loader
+            .plugin_permissions("demo")
+            .unwrap()
+            .allows(&crate::kernel::permissions::Capability::Storage));
+
+        let output = call_blocking(&loader, "demo", "store").await;
+        let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "permission_denied");
+        assert_eq!(response["capability"], "storage");
+        loader.unload_plugin("demo").unwrap();
+
+        // 授予 storage 权限后调用成功
+        let allowed_dir = TempDir::new().unwrap();
+        load_store_plugin(&mut loader, allowed_dir.path(), "demo", r#"["storage"]"#).unwrap();
+        let output = call_blocking(&loader, "demo", "store").await;
+        assert_eq!(output, "success");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_host_function_bound_to_caller() {
+        let db_url = "sqlite::memory:";
+        let storage = Arc::new(Storage::new(db_url).await.unwrap());
+        let (tx, _rx) = mpsc::channel(100);
+        let mut loader = PluginLoader::new(tx, storage.clone(), None).unwrap();
+
+        // "other" 以 "demo" 的名义写存储：没有跨插件权限时拒绝
+        let denied_dir = TempDir::new().unwrap();
+        load_store_plugin(&mut loader, denied_dir.path(), "other", r#"["storage"]"#).unwrap();
+        let output = call_blocking(&loader, "other", "store").await;
+        let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["code"], "permission_denied");
+        assert_eq!(response["capability"], "storage:demo");
+        assert!(storage.get_data("demo", "key").await.unwrap().is_none());
+        loader.unload_plugin("other").unwrap();
+
+        // 显式授予 storage:demo 后允许访问
+        let allowed_dir = TempDir::new().unwrap();
+        load_store_plugin(
+            &mut loader,
+            allowed_dir.path(),
+            "other",
+            r#"["storage", "storage:demo"]"#,
+        )
+        .unwrap();
+        let output = call_blocking(&loader, "other", "store").await;
+        assert_eq!(output, "success");
+        assert_eq!(
+            storage.get_data("demo", "key").await.unwrap(),
+            Some(serde_json::json!("v"))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_config_host_functions() {
+        let mut loader = create_test_loader().await;
+        let temp_dir = TempDir::new().unwrap();
+        let schema = r#"{"config_schema": {"type": "object", "properties": {
+            "interval_ms": {"type": "integer", "minimum": 100}}}}"#;
+        let wat = wat_plugin(&[
+            ("metadata", Export::Output(schema)),
+            ("get", Export::Call("get_config_host", &["sensor"])),
+            (
+                "set_ok",
+                Export::Call("set_config_host", &["sensor", r#"{"interval_ms": 500}"#]),
+            ),
+            (
+                "set_bad",
+                Export::Call("set_config_host", &["sensor", r#"{"interval_ms": 10}"#]),
+            ),
+            ("get_other", Export::Call("get_config_host", &["other"])),
+        ]);
+        let manifest =
+            "[permissions]\nallow = []\n\n[config]\ninterval_ms = 1000\nunit = \"celsius\"\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "sensor", manifest, &wat).unwrap();
+
+        // 未保存时返回 manifest 默认值
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "sensor", "get").await).unwrap();
+        assert_eq!(
+            response["data"],
+            serde_json::json!({"interval_ms": 1000, "unit": "celsius"})
+        );
+
+        // 不符合 schema 的配置被拒绝
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "sensor", "set_bad").await).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "invalid_config");
+
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "sensor", "set_ok").await).unwrap();
+        assert_eq!(response["success"], true);
+
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "sensor", "get").await).unwrap();
+        assert_eq!(
+            response["data"],
+            serde_json::json!({"interval_ms": 500, "unit": "celsius"})
+        );
+
+        // 读取其它插件的配置需要 config:<插件> 权限
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "sensor", "get_other").await).unwrap();
+        assert_eq!(response["code"], "permission_denied");
+    }
+
+    /// 在生命周期各阶段写入存储的插件
+    fn lifecycle_wat() -> String {
+        wat_plugin(&[
+            (
+                "initialize",
+                Export::Call("store_data_host", &["demo", "initialized", "\"yes\""]),
+            ),
+            (
+                "handle_message",
+                Export::Call("store_data_host", &["demo", "message", "\"yes\""]),
+            ),
+            ("pause", Export::Output("paused")),
+            ("resume", Export::Output("resumed")),
+            (
+                "shutdown",
+                Export::Call("store_data_host", &["demo", "shutdown", "\"yes\""]),
+            ),
+        ])
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_publish_keeps_envelope_priority_and_expiry() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+        let mut rx = handle.register_plugin("monitor".to_string());
+        handle.subscribe_topic("monitor", "health.#");
+
+        let envelope = |expires_in_ms: i64| {
+            let mut message =
+                Message::new_topic("sensor".into(), "health.heart_rate".into(), b"72".to_vec())
+                    .with_priority(MessagePriority::High);
+            message.expires_at =
+                Some(chrono::Utc::now() + chrono::Duration::milliseconds(expires_in_ms));
+            message
+                .metadata
+                .insert("unit".to_string(), "bpm".to_string());
+            serde_json::to_string(&PluginMessage::from(&message)).unwrap()
+        };
+        let (fresh, stale) = (envelope(60_000), envelope(-1_000));
+        let wat = wat_plugin(&[
+            (
+                "publish_fresh",
+                Export::Call(
+                    "publish_message_host",
+                    &["", "health.heart_rate", fresh.as_str()],
+                ),
+            ),
+            (
+                "publish_stale",
+                Export::Call(
+                    "publish_message_host",
+                    &["", "health.heart_rate", stale.as_str()],
+                ),
+            ),
+        ]);
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[permissions]\nallow = [\"bus.publish:health.*\"]\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "sensor", manifest, &wat).unwrap();
+
+        // 主题消息沿用信封中的优先级、过期时间与元数据，SDK 从 data 读取消息 ID
+        let output = call_blocking(&loader, "sensor", "publish_fresh").await;
+        let received = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(response["data"], received.id.as_str());
+        assert_eq!(received.payload, b"72");
+        assert_eq!(received.priority, MessagePriority::High);
+        assert!(received.expires_at.is_some());
+        assert_eq!(
+            received.metadata.get("unit").map(String::as_str),
+            Some("bpm")
+        );
+
+        // 过期的主题消息不再投递
+        call_blocking(&loader, "sensor", "publish_stale").await;
+        assert!(timeout(Duration::from_millis(300), rx.recv())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subscriptions_persist_and_restore() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage.clone(), None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[permissions]\nallow = [\"bus.subscribe:*\"]\n";
+        let wat = wat_plugin(&[
+            (
+                "subscribe",
+                Export::Call("subscribe_topic_host", &["", "health.#"]),
+            ),
+            (
+                "unsubscribe",
+                Export::Call("unsubscribe_topic_host", &["", "health.#"]),
+            ),
+        ]);
+        load_test_plugin(&mut loader, temp_dir.path(), "sub", manifest, &wat).unwrap();
+
+        // 订阅写入存储
+        let output = call_blocking(&loader, "sub", "subscribe").await;
+        let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(
+            storage.get_plugin_subscriptions("sub").await.unwrap(),
+            vec!["health.#"]
+        );
+
+        // 卸载只移除内存中的订阅，重新加载后恢复
+        loader.unload_plugin("sub").unwrap();
+        assert!(handle.match_topic_subscribers("health.cpu").is_empty());
+        load_test_plugin(&mut loader, temp_dir.path(), "sub", manifest, &wat).unwrap();
+        assert_eq!(handle.match_topic_subscribers("health.cpu"), vec!["sub"]);
+
+        // 恢复后再次订阅视为成功
+        let output = call_blocking(&loader, "sub", "subscribe").await;
+        let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(response["already_subscribed"], true);
+
+        // 清理不在插件目录中的插件的订阅
+        storage.set_plugin_state("ghost", "loaded").await.unwrap();
+        storage.add_subscription("ghost", "health.#").await.unwrap();
+        let removed = loader.reconcile_subscriptions(temp_dir.path()).unwrap();
+        assert_eq!(removed, vec!["ghost"]);
+        assert_eq!(
+            storage.list_subscribed_plugins().await.unwrap(),
+            vec!["sub"]
+        );
+
+        // 取消订阅同时删除持久化记录
+        let output = call_blocking(&loader, "sub", "unsubscribe").await;
+        let response: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["success"], true);
+        assert!(handle.match_topic_subscribers("health.cpu").is_empty());
+        assert!(storage
+            .get_plugin_subscriptions("sub")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_host_subscriptions_are_not_persisted() {
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[permissions]\nallow = [\"bus.subscribe:*\"]\n";
+        let wat = wat_plugin(&[(
+            "subscribe",
+            Export::Call("subscribe_topic_host", &["", "health.#"]),
+        )]);
+
+        let (handle, router) = create_message_bus(100);
+        let mut loader = PluginLoader::new(handle.get_sender(), storage.clone(), None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+        load_test_plugin(&mut loader, temp_dir.path(), "sub", manifest, &wat).unwrap();
+        call_blocking(&loader, "sub", "subscribe").await;
+
+        // 主机端订阅只存在于内存中
+        assert!(handle.subscribe_topic("ui-widget", "health.#"));
+        assert!(storage
+            .get_plugin_subscriptions("ui-widget")
+            .await
+            .unwrap()
+            .is_empty());
+        loader.unload_plugin("sub").unwrap();
+
+        // 模拟重启：新的消息总线只恢复插件的订阅
+        let (handle, router) = create_message_bus(100);
+        let mut loader = PluginLoader::new(handle.get_sender(), storage.clone(), None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+        load_test_plugin(&mut loader, temp_dir.path(), "sub", manifest, &wat).unwrap();
+        assert_eq!(handle.match_topic_subscribers("health.cpu"), vec!["sub"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_lifecycle_pause_resume_and_unload() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage.clone(), None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[permissions]\nallow = [\"storage\"]\n";
+        load_test_plugin(
+            &mut loader,
+            temp_dir.path(),
+            "demo",
+            manifest,
+            &lifecycle_wat(),
+        )
+        .unwrap();
+
+        // 加载时调用 initialize 并进入运行状态
+        assert_eq!(loader.plugin_state("demo"), Some(PluginState::Running));
+        assert!(storage
+            .get_data("demo", "initialized")
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            storage.get_plugin_state("demo").await.unwrap().as_deref(),
+            Some("running")
+        );
+
+        // 暂停期间消息保留在通道中
+        loader.pause_plugin("demo").unwrap();
+        assert_eq!(
+            storage.get_plugin_state("demo").await.unwrap().as_deref(),
+            Some("paused")
+        );
+        assert!(loader.pause_plugin("demo").is_err());
+
+        handle
+            .send_message(Message::new(
+                "tester".to_string(),
+                "demo".to_string(),
+                b"hello".to_vec(),
+            ))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(storage.get_data("demo", "message").await.unwrap().is_none());
+
+        // 恢复后投递暂存的消息
+        loader.resume_plugin("demo").unwrap();
+        timeout(Duration::from_secs(5), async {
+            while storage.get_data("demo", "message").await.unwrap().is_none() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("恢复后应投递暂存的消息");
+
+        // 卸载时调用 shutdown 并记录状态
+        loader.unload_plugin("demo").unwrap();
+        assert!(storage
+            .get_data("demo", "shutdown")
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(loader.plugin_state("demo"), None);
+        assert_eq!(
+            storage.get_plugin_state("demo").await.unwrap().as_deref(),
+            Some("shutdown")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_initialize_failure_rejects_plugin() {
+        let db_url = "sqlite::memory:";
+        let storage = Arc::new(Storage::new(db_url).await.unwrap());
+        let (tx, _rx) = mpsc::channel(100);
+        let mut loader = PluginLoader::new(tx, storage.clone(), None).unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let wat = r#"(module (func (export "initialize") (result i32) i32.const 1))"#;
+        assert!(load_test_plugin(&mut loader, temp_dir.path(), "broken", "", wat).is_err());
+
+        assert_eq!(loader.plugin_count(), 0);
+        assert_eq!(
+            storage.get_plugin_state("broken").await.unwrap().as_deref(),
+            Some("error")
+        );
+    }
+
+    /// 导入状态并通过 state 输出、version 输出 "v2" 的插件
+    const IMPORT_STATE_WAT: &str = r#"(module
+  (import "extism:host/env" "input_length" (func $input_length (result i64)))
+  (import "extism:host/env" "input_load_u8" (func $input_load_u8 (param i64) (result i32)))
+  (import "extism:host/env" "alloc" (func $alloc (param i64) (result i64)))
+  (import "extism:host/env" "store_u8" (func $store_u8 (param i64 i32)))
+  (import "extism:host/env" "output_set" (func $output_set (param i64 i64)))
+  (memory 1)
+  (data (i32.const 0) "v2")
+  (global $len (mut i32) (i32.const 0))
+  (func $output (param $off i32) (param $len i32)
+    (local $h i64) (local $i i32)
+    (local.set $h (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $h) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $off) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $h) (i64.extend_i32_u (local.get $len))))
+  (func (export "import_state") (result i32) (local $i i32)
+    (global.set $len (i32.wrap_i64 (call $input_length)))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (global.get $len)))
+        (i32.store8 (i32.add (i32.const 16) (local.get $i))
+          (call $input_load_u8 (i64.extend_i32_u (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (i32.const 0))
+  (func (export "state") (result i32)
+    (call $output (i32.const 16) (global.get $len))
+    (i32.const 0))
+  (func (export "version") (result i32)
+    (call $output (i32.const 0) (i32.const 2))
+    (i32.const 0)))"#;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_hands_off_state_and_keeps_old_on_failure() {
+        let mut loader = create_test_loader().await;
+        let temp_dir = TempDir::new().unwrap();
+        let v1 = wat_plugin(&[
+            ("export_state", Export::Output(r#"{"count":3}"#)),
+            ("version", Export::Output("v1")),
+        ]);
+        load_test_plugin(&mut loader, temp_dir.path(), "demo", "", &v1).unwrap();
+        let shared = loader.get_plugin("demo").unwrap();
+        let wasm_path = temp_dir.path().join("demo.wasm");
+        assert_eq!(loader.plugin_for_path(&wasm_path), Some("demo"));
+
+        // 新版本导入旧版本导出的状态，共享实例句柄不变（分发任务与订阅继续使用）
+        std::fs::write(&wasm_path, IMPORT_STATE_WAT).unwrap();
+        loader.reload_plugin("demo").unwrap();
+        assert_eq!(call_blocking(&loader, "demo", "version").await, "v2");
+        assert_eq!(
+            call_blocking(&loader, "demo", "state").await,
+            r#"{"count":3}"#
+        );
+        assert!(Arc::ptr_eq(&shared, &loader.get_plugin("demo").unwrap()));
+        assert_eq!(loader.plugin_state("demo"), Some(PluginState::Running));
+
+        // 新版本初始化失败时保留旧实例
+        std::fs::write(
+            &wasm_path,
+            r#"(module (func (export "initialize") (result i32) i32.const 1))"#,
+        )
+        .unwrap();
+        assert!(loader.reload_plugin("demo").is_err());
+        assert_eq!(call_blocking(&loader, "demo", "version").await, "v2");
+        assert_eq!(loader.plugin_state("demo"), Some(PluginState::Running));
+
+        assert!(loader.reload_plugin("missing").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_keeps_subscriptions_and_queued_messages() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage.clone(), None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[permissions]\nallow = [\"storage\"]\n";
+        load_test_plugin(
+            &mut loader,
+            temp_dir.path(),
+            "demo",
+            manifest,
+            &lifecycle_wat(),
+        )
+        .unwrap();
+        handle.subscribe_topic("demo", "sensor.#");
+
+        let publish = |i: u8| {
+            handle.send_message(Message::new_topic(
+                "tester".to_string(),
+                "sensor.temp".to_string(),
+                vec![i],
+            ))
+        };
+        let wait_for_depth = |depth: usize| {
+            let handle = handle.clone();
+            async move {
+                timeout(Duration::from_secs(5), async {
+                    while handle.queue_stats("demo").unwrap().depth != depth {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                    }
+                })
+                .await
+                .unwrap_or_else(|_| panic!("队列深度应为 {depth}"));
+            }
+        };
+
+        // 暂停期间消息留在队列中（分发任务取出的第一条等待恢复）
+        loader.pause_plugin("demo").unwrap();
+        for i in 0..3 {
+            publish(i).await.unwrap();
+        }
+        wait_for_depth(2).await;
+
+        // 重载后旧实例被关闭，新实例保持暂停
+        let v2 = wat_plugin(&[
+            (
+                "handle_message",
+                Export::Call("store_data_host", &["demo", "handled_v2", "\"yes\""]),
+            ),
+            ("pause", Export::Output("paused")),
+            ("resume", Export::Output("resumed")),
+        ]);
+        std::fs::write(temp_dir.path().join("demo.wasm"), v2).unwrap();
+        let limits = "[limits]\ntimeout_ms = 4321\nqueue_depth = 7\n";
+        std::fs::write(
+            temp_dir.path().join("manifest.toml"),
+            format!("[plugin]\nname = \"demo\"\nversion = \"0.2.0\"\n\n{manifest}{limits}"),
+        )
+        .unwrap();
+        loader.reload_plugin("demo").unwrap();
+        assert_eq!(loader.plugin_state("demo"), Some(PluginState::Paused));
+
+        // 新清单的资源限制与队列设置随替换生效，已排队的消息保留
+        assert_eq!(loader.plugin_limits("demo").unwrap().timeout_ms, 4321);
+        let stats = handle.queue_stats("demo").unwrap();
+        assert_eq!((stats.capacity, stats.depth), (7, 2));
+        assert!(storage
+            .get_data("demo", "shutdown")
+            .await
+            .unwrap()
+            .is_some());
+
+        // 重载前的订阅仍然有效
+        publish(3).await.unwrap();
+        wait_for_depth(3).await;
+
+        // 恢复后排队的消息全部交给新实例处理
+        loader.resume_plugin("demo").unwrap();
+        wait_for_depth(0).await;
+        timeout(Duration::from_secs(5), async {
+            while storage
+                .get_data("demo", "handled_v2")
+                .await
+                .unwrap()
+                .is_none()
+            {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("新实例应处理排队的消息");
+        assert!(storage.get_data("demo", "message").await.unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown_all_in_reverse_dependency_order() {
+        let mut loader = create_test_loader().await;
+
+        let base_dir = TempDir::new().unwrap();
+        load_test_plugin(
+            &mut loader,
+            base_dir.path(),
+            "base",
+            "",
+            FAILING_HANDLER_WAT,
+        )
+        .unwrap();
+        let app_dir = TempDir::new().unwrap();
+        let manifest = "[dependencies]\nrequires = [\"base\"]\n";
+        load_test_plugin(
+            &mut loader,
+            app_dir.path(),
+            "app",
+            manifest,
+            FAILING_HANDLER_WAT,
+        )
+        .unwrap();
+
+        assert_eq!(loader.shutdown_all(), vec!["app", "base"]);
+        assert_eq!(loader.plugin_count(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_load_with_dependencies_reports_failures() {
+        let mut loader = create_test_loader().await;
+        let temp_dir = TempDir::new().unwrap();
+        let write_plugin = |name: &str, manifest: &str, wat: &str| {
+            let dir = temp_dir.path().join(name);
+            std::fs::create_dir(&dir).unwrap();
+            let manifest =
+                format!("[plugin]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n{manifest}");
+            std::fs::write(dir.join("manifest.toml"), manifest).unwrap();
+            std::fs::write(dir.join(format!("{name}.wasm")), wat).unwrap();
+        };
+        let failing_init = r#"(module (func (export "initialize") (result i32) i32.const 1))"#;
+
+        write_plugin("base", "", failing_init);
+        write_plugin(
+            "app",
+            "[dependencies]\nrequires = [\"base\"]\n",
+            FAILING_HANDLER_WAT,
+        );
+        write_plugin(
+            "ui",
+            "[dependencies]\nrequires = [\"app\"]\n",
+            FAILING_HANDLER_WAT,
+        );
+        write_plugin("core", "", FAILING_HANDLER_WAT);
+        let manifest = "[dependencies]\noptional = [\"core\", \"base\"]\n";
+        write_plugin("widget", manifest, FAILING_HANDLER_WAT);
+        write_plugin(
+            "orphan",
+            "[dependencies]\nrequires = [\"ghost\"]\n",
+            FAILING_HANDLER_WAT,
+        );
+
+        let report = loader
+            .load_plugins_with_dependencies(temp_dir.path(), &[])
+            .unwrap();
+
+        assert_eq!(report.loaded, vec!["core", "widget"]);
+        let failed: Vec<_> = report.failed.iter().map(|i| i.plugin.as_str()).collect();
+        assert_eq!(failed, vec!["orphan", "base"]);
+        assert!(report.failed[0].reason.contains("ghost"));
+        let skipped: Vec<_> = report.skipped.iter().map(|i| i.plugin.as_str()).collect();
+        assert_eq!(skipped, vec!["app", "ui"]);
+        assert!(report.skipped[0].reason.contains("base"));
+        assert!(report.skipped[1].reason.contains("app"));
+        assert!(!report.is_complete());
+        assert_eq!(loader.plugin_count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_scheduler_ticks_running_plugins() {
+        let mut loader = create_test_loader().await;
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[schedule]\ntick_interval_ms = 20\njitter_ms = 5\n";
+        let wat = wat_plugin(&[("tick", Export::Output("ok"))]);
+        load_test_plugin(&mut loader, temp_dir.path(), "ticker", manifest, &wat).unwrap();
+
+        let runs = |loader: &PluginLoader| loader.tick_stats("ticker").unwrap().runs;
+        timeout(Duration::from_secs(5), async {
+            while runs(&loader) < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("插件应被定时调用");
+
+        let stats = loader.tick_stats("ticker").unwrap();
+        assert_eq!(stats.failures, 0);
+        assert!(stats.last_run.is_some());
+        assert!(stats.last_duration_ms.is_some());
+
+        // 暂停期间不调用 tick
+        loader.pause_plugin("ticker").unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let paused_runs = runs(&loader);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(runs(&loader), paused_runs);
+        assert!(loader.tick_stats("ticker").unwrap().skipped > 0);
+
+        loader.resume_plugin("ticker").unwrap();
+        timeout(Duration::from_secs(5), async {
+            while runs(&loader) == paused_runs {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("恢复后应继续调用 tick");
+
+        loader.unload_plugin("ticker").unwrap();
+        assert!(loader.tick_stats("ticker").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_schedule_rejected() {
+        let mut loader = create_test_loader().await;
+        let temp_dir = TempDir::new().unwrap();
+
+        let manifest = "[schedule]\ncron = \"every minute\"\n";
+        let err = load_test_plugin(
+            &mut loader,
+            temp_dir.path(),
+            "ticker",
+            manifest,
+            FAILING_HANDLER_WAT,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid schedule"));
+        assert_eq!(loader.plugin_count(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_topic_schemas_follow_plugin_lifecycle() {
+        let (handle, _router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+
+        let manifest =
+            "[[topics]]\ntopic = \"system.stats\"\n\n[topics.schema]\ntype = \"object\"\n";
+        let temp_dir = TempDir::new().unwrap();
+        let collector = temp_dir.path().join("collector");
+        std::fs::create_dir(&collector).unwrap();
+        load_test_plugin(
+            &mut loader,
+            &collector,
+            "collector",
+            manifest,
+            &wat_plugin(&[]),
+        )
+        .unwrap();
+        let schemas = handle.schemas().list();
+        assert_eq!(schemas.len(), 1);
+        assert_eq!(schemas[0].owner, "collector");
+
+        // 其它插件不能为同一主题声明 schema
+        let err = load_test_plugin(
+            &mut loader,
+            temp_dir.path(),
+            "rival",
+            manifest,
+            &wat_plugin(&[]),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid topic schema"));
+        assert_eq!(loader.plugin_count(), 1);
+
+        loader.unload_plugin("collector").unwrap();
+        assert!(handle.schemas().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_host_returns_reply() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        let mut responder_rx = handle.register_plugin("responder".to_string());
+        tokio::spawn(router.run());
+
+        let responder = handle.clone();
+        tokio::spawn(async move {
+            while let Some(request) = responder_rx.recv().await {
+                let reply = Message::response(
+                    "responder".to_string(),
+                    request.from,
+                    request.correlation_id.unwrap(),
+                    b"pong".to_vec(),
+                );
+                responder.send_message(reply).await.unwrap();
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let wat = wat_plugin(&[
+            (
+                "ask",
+                Export::Call("request_host", &["responder", "ping", "2000"]),
+            ),
+            (
+                "ask_missing",
+                Export::Call("request_host", &["missing", "ping", "2000"]),
+            ),
+        ]);
+        let manifest = "[permissions]\nallow = [\"bus.send:*\"]\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "demo", manifest, &wat).unwrap();
+
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "demo", "ask").await).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(response["data"], "pong");
+
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "demo", "ask_missing").await).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["code"], "not_found");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_binary_payload_host_functions() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        let mut sink_rx = handle.register_plugin("sink".to_string());
+        handle.subscribe_topic("sink", "sensor.#");
+        tokio::spawn(router.run());
+
+        // 负载含有 NUL 等非文本字节，原样到达接收方
+        let samples = "\u{0}\u{1}\u{7f}";
+        let temp_dir = TempDir::new().unwrap();
+        let wat = wat_plugin(&[
+            (
+                "send",
+                Export::Call(
+                    "send_bytes_host",
+                    &["", "sink", "application/cbor", samples],
+                ),
+            ),
+            (
+                "publish",
+                Export::Call(
+                    "publish_bytes_host",
+                    &["", "sensor.samples", "application/msgpack", samples],
+                ),
+            ),
+        ]);
+        let manifest = "[permissions]\nallow = [\"bus.send:sink\", \"bus.publish:sensor.*\"]\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "demo", manifest, &wat).unwrap();
+
+        let response: serde_json::Value =
+            serde_json::from_str(&call_blocking(&loader, "demo", "send").await).unwrap();
+        assert_eq!(response["success"], true);
+        let received = timeout(Duration::from_secs(1), sink_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.id, response["data"]);
+        assert_eq!(received.payload, vec![0, 1, 0x7f]);
+        assert_eq!(received.msg_type.as_deref(), Some("application/cbor"));
+
+        call_blocking(&loader, "demo", "publish").await;
+        let received = timeout(Duration::from_secs(1), sink_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.topic.as_deref(), Some("sensor.samples"));
+        assert_eq!(received.payload, vec![0, 1, 0x7f]);
+        assert_eq!(received.msg_type.as_deref(), Some("application/msgpack"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_and_publish_return_message_id() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        let mut sink_rx = handle.register_plugin("sink".to_string());
+        handle.subscribe_topic("sink", "sensor.#");
+        tokio::spawn(router.run());
+
+        let temp_dir = TempDir::new().unwrap();
+        let wat = wat_plugin(&[
+            (
+                "send_message",
+                Export::Call("send_message_host", &["", "sink", "hello"]),
+            ),
+            (
+                "send_bytes",
+                Export::Call("send_bytes_host", &["", "sink", "", "hello"]),
+            ),
+            (
+                "publish_message",
+                Export::Call("publish_message_host", &["", "sensor.text", "hello"]),
+            ),
+            (
+                "publish_bytes",
+                Export::Call("publish_bytes_host", &["", "sensor.bytes", "", "hello"]),
+            ),
+        ]);
+        let manifest = "[permissions]\nallow = [\"bus.send:sink\", \"bus.publish:sensor.*\"]\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "demo", manifest, &wat).unwrap();
+
+        // 发送与发布均返回 {"success", "data": 消息 ID}
+        for function in [
+            "send_message",
+            "send_bytes",
+            "publish_message",
+            "publish_bytes",
+        ] {
+            let output = call_blocking(&loader, "demo", function).await;
+            let response: serde_json::Value = serde_json::from_str(&output)
+                .unwrap_or_else(|e| panic!("{function} 的返回值无效: {e} ({output})"));
+            assert_eq!(response["success"], true, "{function}");
+            let received = timeout(Duration::from_secs(1), sink_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(received.id, response["data"], "{function}");
+            assert_eq!(received.payload, b"hello", "{function}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_fails_when_handler_errors() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+
+        let temp_dir = TempDir::new().unwrap();
+        load_test_plugin(
+            &mut loader,
+            temp_dir.path(),
+            "failing",
+            "",
+            FAILING_HANDLER_WAT,
+        )
+        .unwrap();
+
+        // 目标处理失败时不必等到超时
+        let err = timeout(
+            Duration::from_secs(2),
+            handle.request("host", "failing", b"ping".to_vec(), Duration::from_secs(10)),
+        )
+        .await
+        .expect("应立即返回错误")
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::kernel::message_bus::RequestError::Failed { .. }
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_plugin_interceptor_requires_permission() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+
+        let guard = wat_plugin(&[(
+            INTERCEPT_FN,
+            Export::Output(r#"{"action":"reject","reason":"blocked"}"#),
+        )]);
+        let temp_dir = TempDir::new().unwrap();
+        let unprivileged = temp_dir.path().join("unprivileged");
+        std::fs::create_dir(&unprivileged).unwrap();
+        load_test_plugin(&mut loader, &unprivileged, "unprivileged", "", &guard).unwrap();
+        assert!(handle.interceptors().is_empty());
+
+        let manifest = "[permissions]\nallow = [\"bus.intercept\"]\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "guard", manifest, &guard).unwrap();
+        assert_eq!(handle.interceptors().names(), vec!["plugin:guard"]);
+
+        let mut rx = handle.register_plugin("target".to_string());
+        let err = timeout(
+            Duration::from_secs(2),
+            handle.request("host", "target", b"ping".to_vec(), Duration::from_secs(10)),
+        )
+        .await
+        .expect("被拒绝的请求应立即返回错误")
+        .unwrap_err();
+        assert!(err.to_string().contains("blocked"));
+        assert!(rx.try_recv().is_err());
+
+        loader.unload_plugin("guard").unwrap();
+        assert!(handle.interceptors().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_plugin_interceptor_fails_closed() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        tokio::spawn(router.run());
+
+        // 返回值无法解析的拦截器拒绝消息，而不是放行
+        let broken = wat_plugin(&[(INTERCEPT_FN, Export::Output("not a verdict"))]);
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = "[permissions]\nallow = [\"bus.intercept\"]\n";
+        load_test_plugin(&mut loader, temp_dir.path(), "broken", manifest, &broken).unwrap();
+
+        let mut rx = handle.register_plugin("target".to_string());
+        let err = timeout(
+            Duration::from_secs(2),
+            handle.request("host", "target", b"ping".to_vec(), Duration::from_secs(10)),
+        )
+        .await
+        .expect("被拒绝的请求应立即返回错误")
+        .unwrap_err();
+        assert!(err.to_string().contains("拦截器返回值无效"), "{err}");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_interceptor_plugin_can_make_requests() {
+        let (handle, router) = create_message_bus(100);
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let mut loader = PluginLoader::new(handle.get_sender(), storage, None).unwrap();
+        loader.set_message_bus(handle.clone());
+        let mut responder_rx = handle.register_plugin("responder".to_string());
+        tokio::spawn(router.run());
+
+        let responder = handle.clone();
+        tokio::spawn(async move {
+            while let Some(request) = responder_rx.recv().await {
+                let reply = Message::response(
+                    "responder".to_string(),
+                    request.from,
+                    request.correlation_id.unwrap(),
+                    b"pong".to_vec(),
+                );
+                responder.send_message(reply).await.unwrap();
+            }
+        });
+
+        // 拒绝所有消息的拦截器：自己的请求与收到的回复都不经过它
+        let wat = wat_plugin(&[
+            (
+                INTERCEPT_FN,
+                Export::Output(r#"{"action":"reject","reason":"blocked"}"#),
+            ),
+            (
+                "ask",
+                Export::Call("request_host", &["responder", "ping", "2000"]),
+            ),
+        ]);
+        let manifest = "[permissions]\nallow = [\"bus.intercept\", \"bus.send:*\"]\n";
+        let temp_dir = TempDir::new().unwrap();
+        load_test_plugin(&mut loader, temp_dir.path(), "guard", manifest, &wat).unwrap();
+
+        // 插件实例在等待回复期间被占用，拦截器实例仍可处理其它消息
+        let ask = {
+            let plugin = loader.get_plugin("guard").unwrap();
+            tokio::task::spawn_blocking(move || {
+                plugin
+                    .lock()
+                    .unwrap()
+                    .call::<&str, String>("ask", "")
+                    .unwrap()
+            })
+        };
+        let mut rx = handle.register_plugin("target".to_string());
+        let err = timeout(
+            Duration::from_secs(2),
+            handle.request("host", "target", b"ping".to_vec(), Duration::from_secs(10)),
+        )
+        .await
+        .expect("拦截器不应等待插件实例")
+        .unwrap_err();
+        assert!(err.to_string().contains("blocked"), "{err}");
+        assert!(rx.try_recv().is_err());
+
+        let response: serde_json::Value = serde_json::from_str(&ask.await.unwrap()).unwrap();
+        assert_eq!(response["success"], true);
+        assert_eq!(response["data"], "pong");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_permissions_rejected() {
+        let mut loader = create_test_loader().await;
+        let temp_dir = TempDir::new().unwrap();
+
+        let err =
+            load_store_plugin(&mut loader, temp_dir.path(), "demo", r#"["network"]"#).unwrap_err();
+        assert!(err.to_string().contains("invalid permissions"));
+        assert_eq!(loader.plugin_count(), 0);
+    }
+
+    #[test]
+    fn test_runtime_limits_overrides() {
+        let defaults = RuntimeLimits {
+            timeout_ms: 5000,
+            max_memory_mb: 128,
+            ..Default::default()
+        };
+        let limits = defaults.with_overrides(&Limits {
+            timeout_ms: Some(250),
+            max_memory_mb: None,
+            queue_depth: Some(10),
+            queue_overflow: Some(OverflowPolicy::DropOldest),
+        });
+
+        assert_eq!(limits.timeout_ms, 250);
+        assert_eq!(limits.max_memory_mb, 128);
+        let queue = limits.queue_config();
+        assert_eq!(queue.depth, 10);
+        assert_eq!(queue.overflow, OverflowPolicy::DropOldest);
+        assert_eq!(queue.block_timeout, Duration::from_millis(1000));
+    }
 }
@@ -0,0 +1,506 @@
+//! 插件消息队列
+//!
+//! 每个插件一个有界队列，取代固定 100 条的 mpsc 通道。
+//! 队列深度与溢出策略可按插件配置，路由器按策略处理已满的队列：
+//! 丢弃最旧/最新的消息、在后台等待空位（不阻塞其它插件），或写入持久投递队列。
+//! 等待空位的消息按到达顺序放入与队列同样深度的溢出缓冲，由每个插件一个等待任务依次入队
+
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::{TryRecvError, TrySendError};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use super::message::Message;
+use crate::config::OverflowPolicy;
+
+/// 插件消息队列配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginQueueConfig {
+    /// 队列深度
+    pub depth: usize,
+    /// 队列已满时的处理策略
+    pub overflow: OverflowPolicy,
+    /// `Block` 策略下等待空位的最长时间
+    pub block_timeout: Duration,
+}
+
+impl Default for PluginQueueConfig {
+    fn default() -> Self {
+        Self {
+            depth: 100,
+            overflow: OverflowPolicy::Block,
+            block_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+/// 插件消息队列状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PluginQueueStats {
+    /// 当前排队的消息数
+    pub depth: usize,
+    /// 队列深度上限
+    pub capacity: usize,
+    /// 溢出策略
+    pub overflow: OverflowPolicy,
+    /// 因队列已满而丢弃的消息数
+    pub dropped: u64,
+    /// 因队列已满而写入持久投递队列的消息数
+    pub spilled: u64,
+    /// 在溢出缓冲中等待空位的消息数
+    #[serde(default)]
+    pub waiting: usize,
+}
+
+/// `Block` 策略下等待空位的消息
+struct Waiting {
+    message: Message,
+    deadline: Instant,
+}
+
+/// `Block` 策略下入队的结果
+pub enum BlockingSend {
+    /// 已入队
+    Queued,
+    /// 队列已满，已放入溢出缓冲；`start_waiter` 为 true 时调用方需运行 `drain_waiting`
+    Waiting { start_waiter: bool },
+    /// 队列与溢出缓冲都已满，原样返回消息
+    Full(Message),
+    /// 接收端已关闭
+    Closed(Message),
+}
+
+/// 等待空位的消息的最终结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitOutcome {
+    /// 已入队
+    Queued,
+    /// 等待超时，已丢弃
+    TimedOut,
+    /// 接收端已关闭
+    Closed,
+}
+
+struct Shared {
+    queue: Mutex<VecDeque<Message>>,
+    /// 等待空位的消息（按到达顺序，上限与队列深度相同）
+    waiting: Mutex<VecDeque<Waiting>>,
+    /// 是否有等待任务在处理溢出缓冲
+    waiter_running: AtomicBool,
+    /// 队列配置（热重载时可替换）
+    config: Mutex<PluginQueueConfig>,
+    /// 队列中有新消息
+    readable: Notify,
+    /// 队列中有空位
+    writable: Notify,
+    /// 存活的发送端数量，归零时接收端结束
+    senders: AtomicUsize,
+    /// 接收端是否存活
+    receiver_alive: AtomicBool,
+    dropped: AtomicU64,
+    spilled: AtomicU64,
+}
+
+/// 队列深度至少为 1
+fn normalize(config: PluginQueueConfig) -> PluginQueueConfig {
+    PluginQueueConfig {
+        depth: config.depth.max(1),
+        ..config
+    }
+}
+
+/// 创建插件消息队列
+pub fn plugin_queue(config: PluginQueueConfig) -> (PluginSender, PluginReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(config.depth.min(1024))),
+        waiting: Mutex::new(VecDeque::new()),
+        waiter_running: AtomicBool::new(false),
+        config: Mutex::new(normalize(config)),
+        readable: Notify::new(),
+        writable: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+        dropped: AtomicU64::new(0),
+        spilled: AtomicU64::new(0),
+    });
+
+    (
+        PluginSender {
+            shared: shared.clone(),
+        },
+        PluginReceiver { shared },
+    )
+}
+
+/// 队列发送端（可克隆）
+pub struct PluginSender {
+    shared: Arc<Shared>,
+}
+
+impl Clone for PluginSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for PluginSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.readable.notify_one();
+        }
+    }
+}
+
+impl PluginSender {
+    /// 尝试入队，队列已满时原样返回消息
+    // 与 tokio mpsc 一致，失败时把消息交还调用方
+    #[allow(clippy::result_large_err)]
+    pub fn try_send(&self, message: Message) -> Result<(), TrySendError<Message>> {
+        if !self.shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(TrySendError::Closed(message));
+        }
+
+        {
+            let mut queue = self.shared.queue.lock();
+            if queue.len() >= self.config().depth {
+                return Err(TrySendError::Full(message));
+            }
+            queue.push_back(message);
+        }
+        self.shared.readable.notify_one();
+        Ok(())
+    }
+
+    /// 入队，队列已满时丢弃最旧的消息并返回它
+    #[allow(clippy::result_large_err)]
+    pub fn send_displacing_oldest(&self, message: Message) -> Result<Option<Message>, Message> {
+        if !self.shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(message);
+        }
+
+        let displaced = {
+            let mut queue = self.shared.queue.lock();
+            let displaced = if queue.len() >= self.config().depth {
+                queue.pop_front()
+            } else {
+                None
+            };
+            queue.push_back(message);
+            displaced
+        };
+        if displaced.is_some() {
+            self.record_dropped();
+        }
+        self.shared.readable.notify_one();
+        Ok(displaced)
+    }
+
+    /// 按到达顺序入队：队列已满或已有消息在等待时放入溢出缓冲
+    ///
+    /// 溢出缓冲中的消息由 `drain_waiting` 依次入队，超过 `block_timeout` 仍未入队时丢弃
+    pub fn send_or_wait(&self, message: Message) -> BlockingSend {
+        let mut waiting = self.shared.waiting.lock();
+        let message = if waiting.is_empty() {
+            match self.try_send(message) {
+                Ok(()) => return BlockingSend::Queued,
+                Err(TrySendError::Closed(message)) => return BlockingSend::Closed(message),
+                Err(TrySendError::Full(message)) => message,
+            }
+        } else {
+            message
+        };
+
+        let config = self.config();
+        if waiting.len() >= config.depth {
+            return BlockingSend::Full(message);
+        }
+        waiting.push_back(Waiting {
+            message,
+            deadline: Instant::now() + config.block_timeout,
+        });
+        BlockingSend::Waiting {
+            start_waiter: !self.shared.waiter_running.swap(true, Ordering::AcqRel),
+        }
+    }
+
+    /// 等待任务：队列有空位时按顺序把溢出缓冲中的消息入队，缓冲清空时返回
+    ///
+    /// 每条消息的最终结果交给 `on_done`（在持有缓冲锁时调用，应尽快返回）
+    pub async fn drain_waiting(&self, mut on_done: impl FnMut(&Message, WaitOutcome)) {
+        loop {
+            // 先注册等待再尝试入队，避免错过两者之间的空位通知
+            let writable = self.shared.writable.notified();
+            let deadline = {
+                let mut waiting = self.shared.waiting.lock();
+                loop {
+                    let Some(next) = waiting.pop_front() else {
+                        self.shared.waiter_running.store(false, Ordering::Release);
+                        return;
+                    };
+                    if next.deadline <= Instant::now() {
+                        self.record_dropped();
+                        on_done(&next.message, WaitOutcome::TimedOut);
+                        continue;
+                    }
+                    if !self.shared.receiver_alive.load(Ordering::Acquire) {
+                        on_done(&next.message, WaitOutcome::Closed);
+                        continue;
+                    }
+                    let mut queue = self.shared.queue.lock();
+                    if queue.len() >= self.config().depth {
+                        let deadline = next.deadline;
+                        waiting.push_front(next);
+                        break deadline;
+                    }
+                    on_done(&next.message, WaitOutcome::Queued);
+                    queue.push_back(next.message);
+                    drop(queue);
+                    self.shared.readable.notify_one();
+                }
+            };
+            let _ = tokio::time::timeout_at(deadline, writable).await;
+        }
+    }
+
+    /// 队列配置
+    pub fn config(&self) -> PluginQueueConfig {
+        *self.shared.config.lock()
+    }
+
+    /// 替换队列配置，已排队的消息保留（深度变小时超出的消息仍会被投递）
+    pub fn reconfigure(&self, config: PluginQueueConfig) {
+        *self.shared.config.lock() = normalize(config);
+        self.shared.writable.notify_waiters();
+    }
+
+    /// 记录一条因队列已满而丢弃的消息
+    pub fn record_dropped(&self) {
+        self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一条因队列已满而写入持久投递队列的消息
+    pub fn record_spilled(&self) {
+        self.shared.spilled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 队列状态
+    pub fn stats(&self) -> PluginQueueStats {
+        let config = self.config();
+        PluginQueueStats {
+            depth: self.shared.queue.lock().len(),
+            capacity: config.depth,
+            overflow: config.overflow,
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            spilled: self.shared.spilled.load(Ordering::Relaxed),
+            waiting: self.shared.waiting.lock().len(),
+        }
+    }
+}
+
+/// 队列接收端（由插件的分发任务持有）
+pub struct PluginReceiver {
+    shared: Arc<Shared>,
+}
+
+impl Drop for PluginReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+        self.shared.writable.notify_waiters();
+    }
+}
+
+impl PluginReceiver {
+    /// 接收下一条消息，所有发送端释放且队列为空时返回 None
+    pub async fn recv(&mut self) -> Option<Message> {
+        let shared = self.shared.clone();
+        loop {
+            let readable = shared.readable.notified();
+            match self.try_recv() {
+                Ok(message) => return Some(message),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+            readable.await;
+        }
+    }
+
+    /// 尝试接收消息
+    pub fn try_recv(&mut self) -> Result<Message, TryRecvError> {
+        let message = self.shared.queue.lock().pop_front();
+        match message {
+            Some(message) => {
+                self.shared.writable.notify_one();
+                Ok(message)
+            }
+            None if self.shared.senders.load(Ordering::Acquire) == 0 => {
+                Err(TryRecvError::Disconnected)
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(payload: &str) -> Message {
+        Message::new("a".into(), "b".into(), payload.as_bytes().to_vec())
+    }
+
+    fn payloads(rx: &mut PluginReceiver) -> Vec<String> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| String::from_utf8(message.payload).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_bounded_queue_and_overflow() {
+        let (tx, mut rx) = plugin_queue(PluginQueueConfig {
+            depth: 2,
+            ..Default::default()
+        });
+
+        tx.try_send(message("1")).unwrap();
+        tx.try_send(message("2")).unwrap();
+        assert!(matches!(
+            tx.try_send(message("3")),
+            Err(TrySendError::Full(_))
+        ));
+
+        let displaced = tx.send_displacing_oldest(message("3")).unwrap().unwrap();
+        assert_eq!(displaced.payload, b"1");
+        let stats = tx.stats();
+        assert_eq!((stats.depth, stats.capacity, stats.dropped), (2, 2, 1));
+        assert_eq!(payloads(&mut rx), vec!["2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_keeps_queued_messages() {
+        let (tx, mut rx) = plugin_queue(PluginQueueConfig {
+            depth: 1,
+            ..Default::default()
+        });
+        tx.try_send(message("1")).unwrap();
+        assert!(tx.try_send(message("2")).is_err());
+
+        tx.reconfigure(PluginQueueConfig {
+            depth: 3,
+            overflow: OverflowPolicy::DropOldest,
+            ..Default::default()
+        });
+        tx.try_send(message("2")).unwrap();
+        let stats = tx.stats();
+        assert_eq!((stats.depth, stats.capacity), (2, 3));
+        assert_eq!(stats.overflow, OverflowPolicy::DropOldest);
+
+        // 深度变小时已排队的消息不丢弃，只是暂不接收新消息
+        tx.reconfigure(PluginQueueConfig {
+            depth: 1,
+            ..Default::default()
+        });
+        assert!(tx.try_send(message("3")).is_err());
+        assert_eq!(payloads(&mut rx), vec!["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_messages_keep_order_and_bound() {
+        let (tx, mut rx) = plugin_queue(PluginQueueConfig {
+            depth: 2,
+            overflow: OverflowPolicy::Block,
+            block_timeout: Duration::from_secs(2),
+        });
+
+        assert!(matches!(
+            tx.send_or_wait(message("1")),
+            BlockingSend::Queued
+        ));
+        assert!(matches!(
+            tx.send_or_wait(message("2")),
+            BlockingSend::Queued
+        ));
+        assert!(matches!(
+            tx.send_or_wait(message("3")),
+            BlockingSend::Waiting { start_waiter: true }
+        ));
+        assert!(matches!(
+            tx.send_or_wait(message("4")),
+            BlockingSend::Waiting {
+                start_waiter: false
+            }
+        ));
+        // 溢出缓冲同样有界
+        assert!(matches!(
+            tx.send_or_wait(message("5")),
+            BlockingSend::Full(_)
+        ));
+        assert_eq!(tx.stats().waiting, 2);
+
+        let waiter = {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut outcomes = Vec::new();
+                tx.drain_waiting(|message, outcome| {
+                    outcomes.push((String::from_utf8(message.payload.clone()).unwrap(), outcome))
+                })
+                .await;
+                outcomes
+            })
+        };
+
+        // 取走消息后，等待的消息按到达顺序入队
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(String::from_utf8(rx.recv().await.unwrap().payload).unwrap());
+        }
+        assert_eq!(received, vec!["1", "2", "3", "4"]);
+        assert_eq!(
+            waiter.await.unwrap(),
+            vec![
+                ("3".to_string(), WaitOutcome::Queued),
+                ("4".to_string(), WaitOutcome::Queued)
+            ]
+        );
+
+        // 超时未入队的消息被丢弃
+        let (tx, _rx) = plugin_queue(PluginQueueConfig {
+            depth: 1,
+            overflow: OverflowPolicy::Block,
+            block_timeout: Duration::from_millis(20),
+        });
+        tx.try_send(message("1")).unwrap();
+        assert!(matches!(
+            tx.send_or_wait(message("2")),
+            BlockingSend::Waiting { start_waiter: true }
+        ));
+        let mut outcomes = Vec::new();
+        tx.drain_waiting(|_, outcome| outcomes.push(outcome)).await;
+        assert_eq!(outcomes, vec![WaitOutcome::TimedOut]);
+        assert_eq!((tx.stats().dropped, tx.stats().waiting), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_closing_ends_receiver_and_rejects_senders() {
+        let (tx, mut rx) = plugin_queue(PluginQueueConfig::default());
+        tx.try_send(message("last")).unwrap();
+        drop(tx);
+
+        // 发送端全部释放后仍可取完剩余消息
+        assert_eq!(rx.recv().await.unwrap().payload, b"last");
+        assert!(rx.recv().await.is_none());
+
+        let (tx, rx) = plugin_queue(PluginQueueConfig::default());
+        drop(rx);
+        assert!(matches!(
+            tx.try_send(message("x")),
+            Err(TrySendError::Closed(_))
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+//! 消息优先级队列
+//!
+//! 路由器先把已到达的消息放入队列，再按优先级取出处理，
+//! 同一优先级内保持到达顺序
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::message::{Message, MessagePriority};
+
+/// 队列中的消息
+struct QueuedMessage {
+    priority: MessagePriority,
+    /// 到达序号（同一优先级内先到先出）
+    seq: u64,
+    message: Message,
+}
+
+impl PartialEq for QueuedMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedMessage {}
+
+impl PartialOrd for QueuedMessage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedMessage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// 按优先级出队的消息队列
+#[derive(Default)]
+pub struct PriorityQueue {
+    heap: BinaryHeap<QueuedMessage>,
+    next_seq: u64,
+}
+
+impl PriorityQueue {
+    /// 创建空队列
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 消息入队
+    pub fn push(&mut self, message: Message) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.heap.push(QueuedMessage {
+            priority: message.priority,
+            seq,
+            message,
+        });
+    }
+
+    /// 取出优先级最高、最早到达的消息
+    pub fn pop(&mut self) -> Option<Message> {
+        self.heap.pop().map(|queued| queued.message)
+    }
+
+    /// 队列中的消息数
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_order_and_fifo_within_priority() {
+        let mut queue = PriorityQueue::new();
+        let message = |payload: &str, priority| {
+            Message::new("a".into(), "b".into(), payload.as_bytes().to_vec())
+                .with_priority(priority)
+        };
+
+        queue.push(message("low", MessagePriority::Low));
+        queue.push(message("normal-1", MessagePriority::Normal));
+        queue.push(message("critical", MessagePriority::Critical));
+        queue.push(message("normal-2", MessagePriority::Normal));
+        queue.push(message("high", MessagePriority::High));
+        assert_eq!(queue.len(), 5);
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop())
+            .map(|message| String::from_utf8(message.payload).unwrap())
+            .collect();
+        assert_eq!(
+            order,
+            vec!["critical", "high", "normal-1", "normal-2", "low"]
+        );
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,528 @@
+//! 本地插件仓库
+//!
+//! 仓库是一个目录（或指向它的 `file://` URL），包含 `index.json` 索引和 .mkpkg 插件包。
+//! 索引中每个版本一条记录，版本、描述、标签和依赖取自包内的 `manifest.toml`
+
+use anyhow::{anyhow, bail, Context, Result};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::dependency_resolver::DependencyResolver;
+use super::manifest::{kernel_satisfies, Dependencies, DependencySpec};
+use super::package::{PluginPackage, PACKAGE_EXTENSION};
+use super::plugin_loader::PluginInfo;
+use super::KERNEL_VERSION;
+
+/// 仓库索引文件名
+pub const INDEX_FILE: &str = "index.json";
+
+/// 仓库索引
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    /// 所有插件包（每个版本一条）
+    pub packages: Vec<RegistryEntry>,
+}
+
+/// 仓库中的一个插件版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// 插件名称
+    pub name: String,
+    /// 版本
+    pub version: String,
+    /// 描述
+    #[serde(default)]
+    pub description: String,
+    /// 作者
+    #[serde(default)]
+    pub author: Option<String>,
+    /// 标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 最小内核版本要求
+    #[serde(default)]
+    pub min_kernel_version: Option<String>,
+    /// 依赖
+    #[serde(default)]
+    pub dependencies: Dependencies,
+    /// 发布者地址
+    pub publisher: String,
+    /// 插件包相对仓库根目录的路径
+    pub package: String,
+}
+
+impl RegistryEntry {
+    /// 解析后的版本（无法解析时为 0.0.0）
+    pub fn semver(&self) -> Version {
+        Version::parse(&self.version).unwrap_or_else(|_| Version::new(0, 0, 0))
+    }
+
+    /// 是否兼容指定的内核版本
+    pub fn is_compatible_with_kernel(&self, kernel_version: &str) -> bool {
+        kernel_satisfies(self.min_kernel_version.as_deref(), kernel_version)
+    }
+
+    /// 关键字是否匹配名称、描述或标签（不区分大小写）
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self.description.to_lowercase().contains(&query)
+            || self.tags.iter().any(|tag| tag.to_lowercase() == query)
+    }
+
+    /// 转换为依赖解析器使用的插件信息
+    fn plugin_info(&self, package_path: PathBuf) -> PluginInfo {
+        PluginInfo {
+            name: self.name.clone(),
+            path: package_path,
+            file_size: 0,
+            modified: std::time::UNIX_EPOCH,
+            loaded: false,
+            version: self.version.clone(),
+            description: self.description.clone(),
+            author: self.author.clone(),
+            dependencies: self.dependencies.requires.clone(),
+            optional_dependencies: self.dependencies.optional.clone(),
+            tags: self.tags.clone(),
+            min_kernel_version: self.min_kernel_version.clone(),
+            permissions: None,
+        }
+    }
+}
+
+/// 可更新的插件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutdatedPlugin {
+    /// 插件名称
+    pub name: String,
+    /// 已安装的版本
+    pub installed: String,
+    /// 仓库中兼容当前内核的最新版本
+    pub latest: String,
+}
+
+/// 本地插件仓库
+#[derive(Debug, Clone)]
+pub struct Registry {
+    /// 仓库根目录
+    root: PathBuf,
+    /// 索引
+    index: RegistryIndex,
+    /// 用于检查 `min_kernel_version` 的内核版本
+    kernel_version: String,
+}
+
+impl Registry {
+    /// 打开仓库（目录、`index.json` 路径或 `file://` URL）
+    pub fn open(location: &str) -> Result<Self> {
+        let path = match location.strip_prefix("file://") {
+            Some(path) => PathBuf::from(path),
+            None if location.contains("://") => bail!("不支持的仓库地址: {location}"),
+            None => PathBuf::from(location),
+        };
+        let (root, index_path) = if path.is_dir() {
+            (path.clone(), path.join(INDEX_FILE))
+        } else {
+            let root = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            (root, path)
+        };
+
+        let content = std::fs::read_to_string(&index_path)
+            .with_context(|| format!("无法读取仓库索引: {}", index_path.display()))?;
+        let index = serde_json::from_str(&content)
+            .with_context(|| format!("无效的仓库索引: {}", index_path.display()))?;
+
+        Ok(Self {
+            root,
+            index,
+            kernel_version: KERNEL_VERSION.to_string(),
+        })
+    }
+
+    /// 使用指定的内核版本检查插件兼容性
+    pub fn with_kernel_version(mut self, kernel_version: &str) -> Self {
+        self.kernel_version = kernel_version.to_string();
+        self
+    }
+
+    /// 仓库根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 插件包的本地路径
+    pub fn package_path(&self, entry: &RegistryEntry) -> PathBuf {
+        self.root.join(&entry.package)
+    }
+
+    /// 插件的所有版本（从新到旧）
+    pub fn versions(&self, name: &str) -> Vec<&RegistryEntry> {
+        let mut versions: Vec<_> = self
+            .index
+            .packages
+            .iter()
+            .filter(|entry| entry.name == name)
+            .collect();
+        versions.sort_by_key(|entry| Reverse(entry.semver()));
+        versions
+    }
+
+    /// 兼容当前内核的最新版本
+    pub fn latest(&self, name: &str) -> Option<&RegistryEntry> {
+        self.versions(name)
+            .into_iter()
+            .find(|entry| entry.is_compatible_with_kernel(&self.kernel_version))
+    }
+
+    /// 按名称、描述或标签搜索插件，每个插件返回兼容当前内核的最新版本（空关键字返回全部）
+    ///
+    /// 没有兼容版本的插件返回其最新版本，可用 `is_compatible` 区分
+    pub fn search(&self, query: &str) -> Vec<&RegistryEntry> {
+        let names: BTreeSet<&str> = self
+            .index
+            .packages
+            .iter()
+            .filter(|entry| query.is_empty() || entry.matches(query))
+            .map(|entry| entry.name.as_str())
+            .collect();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                self.latest(name)
+                    .or_else(|| self.versions(name).first().copied())
+            })
+            .collect()
+    }
+
+    /// 插件版本是否兼容当前内核
+    pub fn is_compatible(&self, entry: &RegistryEntry) -> bool {
+        entry.is_compatible_with_kernel(&self.kernel_version)
+    }
+
+    /// 解析安装请求（`name` 或 `name@version`），按安装顺序返回需要安装的插件包（依赖在前）
+    ///
+    /// 已安装的插件保持当前版本，不满足版本要求时返回错误；未安装的必需依赖从仓库中选择版本
+    pub fn resolve_install(
+        &self,
+        request: &str,
+        installed: &[PluginInfo],
+    ) -> Result<Vec<&RegistryEntry>> {
+        let target = parse_install_request(request)?;
+        let versions = self.versions(&target.name);
+        if versions.is_empty() {
+            bail!("仓库中没有插件 {}", target.name);
+        }
+        if !versions.iter().any(|entry| target.matches(&entry.semver())) {
+            let available: Vec<_> = versions.iter().map(|e| e.version.as_str()).collect();
+            bail!(
+                "仓库中没有满足 {} 的版本（可用版本: {}）",
+                target,
+                available.join(", ")
+            );
+        }
+
+        let mut resolver = DependencyResolver::new().with_kernel_version(&self.kernel_version);
+        for plugin in installed.iter().filter(|plugin| plugin.name != target.name) {
+            let mut plugin = plugin.clone();
+            plugin.loaded = true;
+            resolver.add_plugin(plugin);
+        }
+        for entry in &self.index.packages {
+            if entry.name == target.name && !target.matches(&entry.semver()) {
+                continue;
+            }
+            resolver.add_plugin(entry.plugin_info(self.package_path(entry)));
+        }
+
+        let order = resolver.resolve(std::slice::from_ref(&target.name))?;
+        order
+            .iter()
+            .filter(|plugin| !plugin.loaded)
+            .map(|plugin| {
+                self.index
+                    .packages
+                    .iter()
+                    .find(|entry| entry.name == plugin.name && entry.version == plugin.version)
+                    .ok_or_else(|| anyhow!("仓库中没有 {} {}", plugin.name, plugin.version))
+            })
+            .collect()
+    }
+
+    /// 已安装插件中有新版本可用的插件
+    pub fn outdated(&self, installed: &[PluginInfo]) -> Vec<OutdatedPlugin> {
+        let mut newest: BTreeMap<&str, &PluginInfo> = BTreeMap::new();
+        for plugin in installed {
+            let current = newest.entry(&plugin.name).or_insert(plugin);
+            if plugin.semver() > current.semver() {
+                *current = plugin;
+            }
+        }
+
+        newest
+            .into_values()
+            .filter_map(|plugin| {
+                let latest = self.latest(&plugin.name)?;
+                (latest.semver() > plugin.semver()).then(|| OutdatedPlugin {
+                    name: plugin.name.clone(),
+                    installed: plugin.version.clone(),
+                    latest: latest.version.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 由目录中的 .mkpkg 插件包生成仓库索引
+pub fn build_index(registry_dir: &Path) -> Result<RegistryIndex> {
+    let mut packages = Vec::new();
+
+    for entry in WalkDir::new(registry_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .is_some_and(|ext| ext == PACKAGE_EXTENSION)
+        })
+    {
+        let package = PluginPackage::read(entry.path())?;
+        let manifest = package
+            .manifest()
+            .with_context(|| format!("无效的插件包: {}", entry.path().display()))?;
+        let relative = entry.path().strip_prefix(registry_dir)?;
+
+        packages.push(RegistryEntry {
+            name: manifest.plugin.name,
+            version: manifest.plugin.version,
+            description: manifest.plugin.description,
+            author: manifest.plugin.author,
+            tags: manifest.metadata.tags,
+            min_kernel_version: manifest.metadata.min_kernel_version,
+            dependencies: manifest.dependencies,
+            publisher: package.index.package.publisher.clone(),
+            package: relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        });
+    }
+
+    packages.sort_by(|a, b| {
+        a.name
+            .cmp(&b.name)
+            .then_with(|| b.semver().cmp(&a.semver()))
+    });
+    Ok(RegistryIndex { packages })
+}
+
+/// 生成并写入仓库的 `index.json`
+pub fn write_index(registry_dir: &Path) -> Result<RegistryIndex> {
+    let index = build_index(registry_dir)?;
+    std::fs::write(
+        registry_dir.join(INDEX_FILE),
+        serde_json::to_string_pretty(&index)?,
+    )?;
+    Ok(index)
+}
+
+/// 解析 `name` 或 `name@version`（纯版本号表示精确版本，也可使用 `^1.2` 等版本要求）
+pub fn parse_install_request(request: &str) -> Result<DependencySpec> {
+    match request.split_once('@') {
+        None => Ok(DependencySpec::any(request)),
+        Some((name, version)) if Version::parse(version).is_ok() => {
+            DependencySpec::parse(name, &format!("={version}"))
+        }
+        Some((name, version)) => DependencySpec::parse(name, version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::IdentityManager;
+    use tempfile::TempDir;
+
+    const PUBLISHER_KEY: &str =
+        "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    /// 打包插件并放入仓库目录
+    fn publish(registry: &Path, name: &str, version: &str, extra: &str) {
+        let source = TempDir::new().unwrap();
+        let manifest = format!("[plugin]\nname = \"{name}\"\nversion = \"{version}\"\n{extra}");
+        std::fs::write(source.path().join("manifest.toml"), manifest).unwrap();
+        std::fs::write(source.path().join(format!("{name}.wasm")), "(module)").unwrap();
+
+        let identity = IdentityManager::from_private_key(PUBLISHER_KEY).unwrap();
+        let package = PluginPackage::build(source.path(), &identity).unwrap();
+        package
+            .write(&registry.join(format!("{name}-{version}.mkpkg")))
+            .unwrap();
+    }
+
+    fn installed(name: &str, version: &str) -> PluginInfo {
+        RegistryEntry {
+            name: name.to_string(),
+            version: version.to_string(),
+            description: String::new(),
+            author: None,
+            tags: vec![],
+            min_kernel_version: None,
+            dependencies: Dependencies::default(),
+            publisher: String::new(),
+            package: String::new(),
+        }
+        .plugin_info(PathBuf::from(format!("plugins/{name}/{name}.wasm")))
+    }
+
+    /// 仓库：storage 1.0.0 / 1.3.0 / 2.0.0（需要新内核）、chart 1.0.0（依赖 storage ^1.2）
+    fn sample_registry() -> (TempDir, Registry) {
+        let dir = TempDir::new().unwrap();
+        publish(
+            dir.path(),
+            "storage",
+            "1.0.0",
+            "description = \"键值存储\"\n\n[metadata]\ntags = [\"data\"]\n",
+        );
+        publish(
+            dir.path(),
+            "storage",
+            "1.3.0",
+            "description = \"键值存储\"\n",
+        );
+        publish(
+            dir.path(),
+            "storage",
+            "2.0.0",
+            "\n[metadata]\nmin_kernel_version = \"9.0.0\"\n",
+        );
+        publish(
+            dir.path(),
+            "chart",
+            "1.0.0",
+            "description = \"图表\"\n\n[dependencies.requires]\nstorage = \"^1.2\"\n",
+        );
+        write_index(dir.path()).unwrap();
+
+        let url = format!("file://{}", dir.path().display());
+        let registry = Registry::open(&url).unwrap().with_kernel_version("1.0.0");
+        (dir, registry)
+    }
+
+    #[test]
+    fn test_index_search_and_versions() {
+        let (_dir, registry) = sample_registry();
+
+        let versions: Vec<_> = registry
+            .versions("storage")
+            .iter()
+            .map(|entry| entry.version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["2.0.0", "1.3.0", "1.0.0"]);
+        assert_eq!(registry.latest("storage").unwrap().version, "1.3.0");
+        assert_eq!(
+            registry.package_path(registry.latest("chart").unwrap()),
+            registry.root().join("chart-1.0.0.mkpkg")
+        );
+
+        let names: Vec<_> = registry.search("").iter().map(|e| e.name.clone()).collect();
+        assert_eq!(names, vec!["chart", "storage"]);
+        let found = registry.search("图表");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "chart");
+        // 标签匹配任意版本，返回该插件兼容当前内核的最新版本
+        let found = registry.search("DATA");
+        assert_eq!(found[0].version, "1.3.0");
+        assert!(registry.is_compatible(found[0]));
+    }
+
+    #[test]
+    fn test_search_marks_plugins_without_compatible_version() {
+        let (dir, _) = sample_registry();
+        publish(
+            dir.path(),
+            "future",
+            "1.0.0",
+            "\n[metadata]\nmin_kernel_version = \"9.0.0\"\n",
+        );
+        write_index(dir.path()).unwrap();
+        let url = format!("file://{}", dir.path().display());
+        let registry = Registry::open(&url).unwrap().with_kernel_version("1.0.0");
+
+        let found = registry.search("future");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].version, "1.0.0");
+        assert!(!registry.is_compatible(found[0]));
+        assert!(registry.latest("future").is_none());
+    }
+
+    #[test]
+    fn test_resolve_install_pulls_dependencies() {
+        let (_dir, registry) = sample_registry();
+
+        let plan: Vec<_> = registry
+            .resolve_install("chart", &[])
+            .unwrap()
+            .iter()
+            .map(|entry| format!("{} {}", entry.name, entry.version))
+            .collect();
+        assert_eq!(plan, vec!["storage 1.3.0", "chart 1.0.0"]);
+
+        // 已安装且满足要求的依赖不会重新安装
+        let plan = registry
+            .resolve_install("chart@1.0.0", &[installed("storage", "1.2.5")])
+            .unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].name, "chart");
+
+        // 已安装的依赖版本不满足要求时报告冲突
+        let err = registry
+            .resolve_install("chart", &[installed("storage", "1.0.0")])
+            .unwrap_err();
+        assert!(err.to_string().contains("storage ^1.2"));
+
+        // 指定版本
+        let plan = registry.resolve_install("storage@1.0.0", &[]).unwrap();
+        assert_eq!(plan[0].version, "1.0.0");
+        assert!(registry.resolve_install("storage@3.0.0", &[]).is_err());
+        assert!(registry.resolve_install("missing", &[]).is_err());
+    }
+
+    #[test]
+    fn test_outdated() {
+        let (_dir, registry) = sample_registry();
+
+        let outdated = registry.outdated(&[
+            installed("storage", "1.0.0"),
+            installed("chart", "1.0.0"),
+            installed("local-only", "0.1.0"),
+        ]);
+        assert_eq!(
+            outdated,
+            vec![OutdatedPlugin {
+                name: "storage".to_string(),
+                installed: "1.0.0".to_string(),
+                latest: "1.3.0".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_install_request() {
+        assert_eq!(
+            parse_install_request("chart").unwrap(),
+            DependencySpec::any("chart")
+        );
+        let spec = parse_install_request("chart@1.2.0").unwrap();
+        assert!(spec.matches(&Version::new(1, 2, 0)));
+        assert!(!spec.matches(&Version::new(1, 2, 1)));
+        let spec = parse_install_request("chart@^1.2").unwrap();
+        assert!(spec.matches(&Version::new(1, 9, 0)));
+        assert!(parse_install_request("chart@latest").is_err());
+    }
+}
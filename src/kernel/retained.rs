@@ -0,0 +1,193 @@
+//! 保留消息（最近值缓存）
+//!
+//! 匹配保留规则的主题消息经过路由器时会被保留下来（每个主题最近 N 条），
+//! 新订阅者订阅时立即收到，插件也可以不订阅直接读取。
+//! 规则要求持久化的主题同时写入 retained_messages 表，重启后恢复
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use super::message::{Message, RETAINED_KEY};
+use super::topic_index::topic_matches;
+use crate::config::RetainedTopicConfig;
+use crate::storage::Storage;
+
+/// 保留消息缓存（可克隆）
+#[derive(Clone)]
+pub struct RetainedCache {
+    rules: Arc<Vec<RetainedTopicConfig>>,
+    /// 主题 -> 最近的消息（按发布顺序）
+    topics: Arc<RwLock<HashMap<String, VecDeque<Message>>>>,
+    storage: Option<Arc<Storage>>,
+}
+
+impl RetainedCache {
+    /// 创建只保存在内存中的缓存
+    pub fn new(rules: Vec<RetainedTopicConfig>) -> Self {
+        Self {
+            rules: Arc::new(rules),
+            topics: Arc::default(),
+            storage: None,
+        }
+    }
+
+    /// 创建缓存，并恢复已持久化的保留消息
+    pub async fn load(storage: Arc<Storage>, rules: Vec<RetainedTopicConfig>) -> Result<Self> {
+        let mut cache = Self::new(rules);
+
+        let mut restored = 0;
+        for (topic, json) in storage.load_retained_messages().await? {
+            let Some(depth) = cache.rule(&topic).map(|rule| rule.depth) else {
+                continue;
+            };
+            match serde_json::from_str::<Message>(&json) {
+                Ok(message) => {
+                    push_bounded(
+                        cache.topics.write().entry(topic).or_default(),
+                        message,
+                        depth,
+                    );
+                    restored += 1;
+                }
+                Err(e) => tracing::warn!("无法解析主题 {} 的保留消息: {}", topic, e),
+            }
+        }
+        if restored > 0 {
+            tracing::info!("已恢复 {} 条保留消息", restored);
+        }
+
+        cache.storage = Some(storage);
+        Ok(cache)
+    }
+
+    /// 主题匹配的保留规则
+    pub fn rule(&self, topic: &str) -> Option<&RetainedTopicConfig> {
+        self.rules
+            .iter()
+            .find(|rule| topic_matches(&rule.pattern, topic))
+    }
+
+    /// 保留主题消息（主题没有匹配的规则时忽略），返回是否保留
+    pub async fn retain(&self, message: &Message) -> bool {
+        let Some(topic) = message.topic.as_deref() else {
+            return false;
+        };
+        let Some(rule) = self.rule(topic) else {
+            return false;
+        };
+
+        push_bounded(
+            self.topics.write().entry(topic.to_string()).or_default(),
+            message.clone(),
+            rule.depth,
+        );
+
+        if let (true, Some(storage)) = (rule.persist, &self.storage) {
+            let saved = match serde_json::to_string(message) {
+                Ok(json) => {
+                    storage
+                        .save_retained_message(topic, &message.id, &json, rule.depth)
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = saved {
+                tracing::warn!("保存主题 {} 的保留消息失败: {}", topic, e);
+            }
+        }
+        true
+    }
+
+    /// 读取匹配主题模式的保留消息（跳过已过期的消息），每条都带有保留标记
+    pub fn get(&self, pattern: &str) -> Vec<Message> {
+        let topics = self.topics.read();
+        let mut matched: Vec<(&String, &VecDeque<Message>)> = topics
+            .iter()
+            .filter(|(topic, _)| topic_matches(pattern, topic))
+            .collect();
+        matched.sort_by_key(|(topic, _)| *topic);
+
+        matched
+            .into_iter()
+            .flat_map(|(_, messages)| messages.iter())
+            .filter(|message| !message.is_expired())
+            .map(|message| {
+                message
+                    .clone()
+                    .with_metadata(RETAINED_KEY.to_string(), "true".to_string())
+            })
+            .collect()
+    }
+
+    /// 清除主题的保留消息
+    pub async fn clear(&self, topic: &str) -> Result<()> {
+        self.topics.write().remove(topic);
+        if let Some(storage) = &self.storage {
+            storage.clear_retained_messages(topic).await?;
+        }
+        Ok(())
+    }
+}
+
+fn push_bounded(messages: &mut VecDeque<Message>, message: Message, depth: usize) {
+    messages.push_back(message);
+    while messages.len() > depth {
+        messages.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, depth: usize, persist: bool) -> RetainedTopicConfig {
+        RetainedTopicConfig {
+            pattern: pattern.to_string(),
+            depth,
+            persist,
+        }
+    }
+
+    fn publish(topic: &str, payload: &str) -> Message {
+        Message::new_topic("monitor".into(), topic.into(), payload.as_bytes().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_retain_last_values_and_restore() {
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let rules = vec![rule("system.stats", 1, true), rule("health.#", 2, false)];
+        let cache = RetainedCache::load(storage.clone(), rules.clone())
+            .await
+            .unwrap();
+
+        assert!(cache.retain(&publish("system.stats", "old")).await);
+        assert!(cache.retain(&publish("system.stats", "new")).await);
+        for payload in ["1", "2", "3"] {
+            assert!(cache.retain(&publish("health.cpu", payload)).await);
+        }
+        assert!(!cache.retain(&publish("other", "x")).await);
+
+        let stats = cache.get("system.stats");
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].payload, b"new");
+        assert_eq!(stats[0].metadata[RETAINED_KEY], "true");
+
+        let health: Vec<Vec<u8>> = cache
+            .get("health.+")
+            .into_iter()
+            .map(|m| m.payload)
+            .collect();
+        assert_eq!(health, vec![b"2".to_vec(), b"3".to_vec()]);
+        assert!(cache.get("other").is_empty());
+
+        // 只有持久化的主题在重启后恢复
+        let restored = RetainedCache::load(storage, rules).await.unwrap();
+        assert_eq!(restored.get("system.stats")[0].payload, b"new");
+        assert!(restored.get("health.#").is_empty());
+
+        restored.clear("system.stats").await.unwrap();
+        assert!(restored.get("#").is_empty());
+    }
+}
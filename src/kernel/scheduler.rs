@@ -0,0 +1,293 @@
+//! 插件定时任务调度
+//!
+//! 按 manifest.toml 中 [schedule] 声明的间隔或 cron 表达式调用插件的 tick 函数。
+//! 每个插件一个调度任务，上一次 tick 返回后才会安排下一次，因此不会重叠执行
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use rand::Rng;
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use super::lifecycle::PluginState;
+use super::manifest::Schedule;
+use super::plugin_loader::{check_fault, convert_call_error, FaultMap, SharedPlugin};
+
+/// 插件定时任务的导出函数名
+pub const TICK_FN: &str = "tick";
+
+/// 未声明 jitter_ms 时，固定间隔的默认随机延迟比例
+const DEFAULT_JITTER_DIVISOR: u32 = 10;
+
+/// 共享的定时任务统计（调度任务写入，加载器读取）
+pub type SharedTickStats = Arc<RwLock<TickStats>>;
+
+/// tick 触发方式
+#[derive(Debug, Clone)]
+pub enum TickTrigger {
+    /// 固定间隔
+    Interval(Duration),
+    /// cron 表达式（UTC）
+    Cron(Box<cron::Schedule>),
+}
+
+impl TickTrigger {
+    /// 计算下一次触发时间，返回 (触发时刻, 因上次执行超时而错过的次数)
+    fn next_after(&self, previous: Instant, now: Instant) -> (Instant, u64) {
+        match self {
+            TickTrigger::Interval(interval) => {
+                let mut next = previous + *interval;
+                let mut missed = 0;
+                while next <= now {
+                    next += *interval;
+                    missed += 1;
+                }
+                (next, missed)
+            }
+            TickTrigger::Cron(schedule) => {
+                let delay = schedule
+                    .upcoming(Utc)
+                    .next()
+                    .and_then(|at| (at - Utc::now()).to_std().ok())
+                    .unwrap_or(Duration::ZERO);
+                (now + delay, 0)
+            }
+        }
+    }
+}
+
+/// 插件的 tick 计划
+#[derive(Debug, Clone)]
+pub struct TickSchedule {
+    /// 触发方式
+    pub trigger: TickTrigger,
+    /// 随机延迟上限
+    pub jitter: Duration,
+}
+
+impl TickSchedule {
+    /// 从 manifest.toml 的 [schedule] 段创建，未声明计划时返回 None
+    pub fn from_manifest(schedule: &Schedule) -> Result<Option<Self>> {
+        let trigger = match (schedule.tick_interval_ms, &schedule.cron) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!("tick_interval_ms 与 cron 不能同时设置"));
+            }
+            (Some(0), None) => return Err(anyhow!("tick_interval_ms 必须大于 0")),
+            (Some(ms), None) => TickTrigger::Interval(Duration::from_millis(ms)),
+            (None, Some(expr)) => TickTrigger::Cron(Box::new(
+                cron::Schedule::from_str(expr)
+                    .map_err(|e| anyhow!("无效的 cron 表达式 '{}': {}", expr, e))?,
+            )),
+            (None, None) => return Ok(None),
+        };
+
+        let jitter = match (schedule.jitter_ms, &trigger) {
+            (Some(ms), _) => Duration::from_millis(ms),
+            (None, TickTrigger::Interval(interval)) => *interval / DEFAULT_JITTER_DIVISOR,
+            (None, TickTrigger::Cron(_)) => Duration::ZERO,
+        };
+
+        Ok(Some(Self { trigger, jitter }))
+    }
+
+    /// 在 [0, jitter] 范围内随机取一个延迟
+    fn random_jitter(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        let max_ms = self.jitter.as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=max_ms))
+    }
+}
+
+/// 插件的 tick 统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TickStats {
+    /// 已执行次数
+    pub runs: u64,
+    /// 失败次数
+    pub failures: u64,
+    /// 因上次执行超出间隔而跳过的次数
+    pub missed: u64,
+    /// 因插件未运行（如暂停）而跳过的次数
+    pub skipped: u64,
+    /// 最近一次执行开始时间
+    pub last_run: Option<DateTime<Utc>>,
+    /// 最近一次执行耗时（毫秒）
+    pub last_duration_ms: Option<u64>,
+    /// 最近一次失败原因
+    pub last_error: Option<String>,
+}
+
+/// 按计划调用插件的 tick 函数，直到插件被移除
+pub(super) async fn run_ticks(
+    plugin_name: String,
+    plugin: SharedPlugin,
+    schedule: TickSchedule,
+    state: watch::Receiver<PluginState>,
+    stats: SharedTickStats,
+    faults: FaultMap,
+    timeout_ms: u64,
+) {
+    tracing::debug!("插件 {} 的定时任务已启动", plugin_name);
+
+    let (mut next, _) = schedule.trigger.next_after(Instant::now(), Instant::now());
+    loop {
+        tokio::time::sleep_until(next + schedule.random_jitter()).await;
+
+        // 插件被移除后结束；未运行（初始化前、暂停）时跳过本次
+        if state.has_changed().is_err() {
+            break;
+        }
+        if *state.borrow() != PluginState::Running {
+            stats.write().skipped += 1;
+        } else if !tick_once(&plugin_name, &plugin, &stats, &faults, timeout_ms).await {
+            break;
+        }
+
+        let (following, missed) = schedule.trigger.next_after(next, Instant::now());
+        if missed > 0 {
+            tracing::debug!(
+                "插件 {} 的 tick 执行超出间隔，跳过 {} 次",
+                plugin_name,
+                missed
+            );
+            stats.write().missed += missed;
+        }
+        next = following;
+    }
+
+    tracing::debug!("插件 {} 的定时任务已结束", plugin_name);
+}
+
+/// 执行一次 tick 并记录统计，插件未导出 tick 时返回 false
+async fn tick_once(
+    plugin_name: &str,
+    plugin: &SharedPlugin,
+    stats: &SharedTickStats,
+    faults: &FaultMap,
+    timeout_ms: u64,
+) -> bool {
+    let started_at = Utc::now();
+    let started = Instant::now();
+
+    // 插件调用是阻塞的，且主机函数内部会 block_on，需放到阻塞线程执行
+    let plugin = plugin.clone();
+    let faults = faults.clone();
+    let name = plugin_name.to_string();
+    let result = tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+        check_fault(&faults, &name)?;
+        let mut plugin = plugin.lock().map_err(|_| anyhow!("插件锁已中毒"))?;
+        if !plugin.function_exists(TICK_FN) {
+            return Ok(None);
+        }
+        let call_started = std::time::Instant::now();
+        plugin
+            .call::<&str, String>(TICK_FN, "")
+            .map(Some)
+            .map_err(|e| {
+                let elapsed = call_started.elapsed();
+                convert_call_error(&faults, &name, TICK_FN, timeout_ms, elapsed, e)
+            })
+    })
+    .await;
+
+    let error = match result {
+        Ok(Ok(Some(_))) => None,
+        Ok(Ok(None)) => {
+            tracing::warn!(
+                "插件 {} 声明了定时任务但未导出 {} 函数",
+                plugin_name,
+                TICK_FN
+            );
+            return false;
+        }
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(e) => Some(format!("定时任务失败: {e}")),
+    };
+
+    let mut stats = stats.write();
+    stats.runs += 1;
+    stats.last_run = Some(started_at);
+    stats.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+    if let Some(error) = error {
+        tracing::warn!("插件 {} 的 tick 执行失败: {}", plugin_name, error);
+        stats.failures += 1;
+        stats.last_error = Some(error);
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_from_manifest() {
+        let schedule = TickSchedule::from_manifest(&Schedule {
+            tick_interval_ms: Some(1000),
+            ..Default::default()
+        })
+        .unwrap()
+        .unwrap();
+        assert!(matches!(schedule.trigger, TickTrigger::Interval(d) if d.as_millis() == 1000));
+        assert_eq!(schedule.jitter, Duration::from_millis(100));
+
+        let schedule = TickSchedule::from_manifest(&Schedule {
+            cron: Some("*/5 * * * * *".to_string()),
+            jitter_ms: Some(20),
+            ..Default::default()
+        })
+        .unwrap()
+        .unwrap();
+        assert!(matches!(schedule.trigger, TickTrigger::Cron(_)));
+        assert_eq!(schedule.jitter, Duration::from_millis(20));
+
+        assert!(TickSchedule::from_manifest(&Schedule::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_schedule_rejected() {
+        let both = Schedule {
+            tick_interval_ms: Some(1000),
+            cron: Some("* * * * * *".to_string()),
+            jitter_ms: None,
+        };
+        assert!(TickSchedule::from_manifest(&both).is_err());
+
+        let bad_cron = Schedule {
+            cron: Some("every minute".to_string()),
+            ..Default::default()
+        };
+        assert!(TickSchedule::from_manifest(&bad_cron).is_err());
+
+        let zero = Schedule {
+            tick_interval_ms: Some(0),
+            ..Default::default()
+        };
+        assert!(TickSchedule::from_manifest(&zero).is_err());
+    }
+
+    #[test]
+    fn test_interval_skips_missed_ticks() {
+        let trigger = TickTrigger::Interval(Duration::from_millis(100));
+        let start = Instant::now();
+
+        let (next, missed) = trigger.next_after(start, start + Duration::from_millis(10));
+        assert_eq!(next, start + Duration::from_millis(100));
+        assert_eq!(missed, 0);
+
+        // 上一次执行用了 350ms：错过 3 次，下一次对齐到 400ms
+        let (next, missed) = trigger.next_after(start, start + Duration::from_millis(350));
+        assert_eq!(next, start + Duration::from_millis(400));
+        assert_eq!(missed, 3);
+    }
+}
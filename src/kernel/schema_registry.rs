@@ -0,0 +1,598 @@
+//! 主题 schema 注册表
+//!
+//! 插件在 manifest.toml 的 [[topics]] 中为发布的主题声明 JSON Schema，加载时注册到总线，
+//! 卸载时移除。路由前按匹配的 schema 校验 JSON 负载，不符合时按
+//! `message_bus.schema_validation` 记录警告或拒绝消息。非 JSON 内容类型的负载不校验
+
+use anyhow::{anyhow, Result};
+use jsonschema::Validator;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use super::interceptor::{InterceptFuture, Interception, MessageInterceptor};
+use super::manifest::{find_and_read_manifest, TopicDeclaration};
+use super::message::Message;
+use super::topic_index::{topic_matches, validate_topic_pattern};
+use crate::config::SchemaValidation;
+
+/// schema 校验器在拦截器链中的名称
+pub const SCHEMA_INTERCEPTOR: &str = "schema";
+
+/// 插件声明的主题 schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopicSchema {
+    /// 主题或主题模式
+    pub topic: String,
+    /// 声明 schema 的插件
+    pub owner: String,
+    /// 描述
+    pub description: String,
+    /// 负载的 JSON Schema
+    pub schema: JsonValue,
+}
+
+/// 已编译的 schema
+struct Entry {
+    schema: TopicSchema,
+    validator: Arc<Validator>,
+}
+
+/// 主题 schema 注册表（可克隆，克隆共享同一份注册表）
+#[derive(Clone, Default)]
+pub struct SchemaRegistry {
+    entries: Arc<RwLock<Vec<Entry>>>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册插件声明的主题 schema，替换该插件之前的声明
+    ///
+    /// 主题模式或 schema 无效、主题已由其它插件声明时返回错误，注册表保持不变
+    pub fn register(&self, owner: &str, declarations: &[TopicDeclaration]) -> Result<()> {
+        let mut compiled = Vec::with_capacity(declarations.len());
+        let mut topics = HashSet::new();
+        for declaration in declarations {
+            validate_topic_pattern(&declaration.topic)?;
+            if !topics.insert(declaration.topic.as_str()) {
+                return Err(anyhow!("主题 '{}' 重复声明", declaration.topic));
+            }
+
+            let schema = serde_json::to_value(&declaration.schema)?;
+            let validator = jsonschema::validator_for(&schema)
+                .map_err(|e| anyhow!("主题 '{}' 的 schema 无效: {}", declaration.topic, e))?;
+            compiled.push(Entry {
+                schema: TopicSchema {
+                    topic: declaration.topic.clone(),
+                    owner: owner.to_string(),
+                    description: declaration.description.clone(),
+                    schema,
+                },
+                validator: Arc::new(validator),
+            });
+        }
+
+        let mut entries = self.entries.write();
+        if let Some(taken) = entries.iter().find(|entry| {
+            entry.schema.owner != owner && topics.contains(entry.schema.topic.as_str())
+        }) {
+            return Err(anyhow!(
+                "主题 '{}' 的 schema 已由插件 '{}' 声明",
+                taken.schema.topic,
+                taken.schema.owner
+            ));
+        }
+        entries.retain(|entry| entry.schema.owner != owner);
+        entries.extend(compiled);
+        Ok(())
+    }
+
+    /// 移除插件声明的所有 schema，返回移除的数量
+    pub fn unregister(&self, owner: &str) -> usize {
+        let mut entries = self.entries.write();
+        let before = entries.len();
+        entries.retain(|entry| entry.schema.owner != owner);
+        before - entries.len()
+    }
+
+    /// 列出所有已注册的 schema（按主题排序）
+    pub fn list(&self) -> Vec<TopicSchema> {
+        let mut schemas: Vec<TopicSchema> = self
+            .entries
+            .read()
+            .iter()
+            .map(|entry| entry.schema.clone())
+            .collect();
+        schemas.sort_by(|a, b| a.topic.cmp(&b.topic));
+        schemas
+    }
+
+    /// 是否没有注册任何 schema
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    /// 按匹配消息主题的所有 schema 校验负载，返回不符合的原因
+    pub fn validate(&self, message: &Message) -> Result<(), String> {
+        let Some(topic) = message.topic.as_deref() else {
+            return Ok(());
+        };
+        if !is_json(message.msg_type.as_deref()) {
+            return Ok(());
+        }
+
+        let validators: Vec<(String, Arc<Validator>)> = self
+            .entries
+            .read()
+            .iter()
+            .filter(|entry| topic_matches(&entry.schema.topic, topic))
+            .map(|entry| (entry.schema.owner.clone(), entry.validator.clone()))
+            .collect();
+        if validators.is_empty() {
+            return Ok(());
+        }
+
+        let payload: JsonValue = serde_json::from_slice(&message.payload)
+            .map_err(|e| format!("主题 {topic} 的负载不是有效的 JSON: {e}"))?;
+        for (owner, validator) in validators {
+            let errors: Vec<String> = validator
+                .iter_errors(&payload)
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{path}: {e}")
+                    }
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(format!(
+                    "主题 {topic} 的负载不符合插件 {owner} 声明的 schema: {}",
+                    errors.join("; ")
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 创建按指定方式处理校验失败的拦截器
+    pub fn validator(&self, mode: SchemaValidation) -> SchemaValidator {
+        SchemaValidator {
+            registry: self.clone(),
+            mode,
+        }
+    }
+}
+
+/// 读取插件目录中各插件清单声明的主题 schema（不加载插件）
+///
+/// 清单无法读取、声明无效或与其它插件冲突的插件会被跳过并记录警告
+pub fn declared_schemas(plugin_dir: &Path) -> Result<SchemaRegistry> {
+    let registry = SchemaRegistry::new();
+    if !plugin_dir.exists() {
+        return Ok(registry);
+    }
+
+    for entry in WalkDir::new(plugin_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
+    {
+        let manifest = match find_and_read_manifest(entry.path()) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                tracing::warn!("跳过 {} 的清单: {}", entry.path().display(), e);
+                continue;
+            }
+        };
+        if let Err(e) = registry.register(&manifest.plugin.name, &manifest.topics) {
+            tracing::warn!("跳过插件 {} 的主题 schema: {}", manifest.plugin.name, e);
+        }
+    }
+
+    Ok(registry)
+}
+
+/// 消息类型为空或 JSON 时才校验
+fn is_json(msg_type: Option<&str>) -> bool {
+    msg_type.is_none_or(|msg_type| {
+        let essence = msg_type.split(';').next().unwrap_or_default().trim();
+        essence == "application/json" || essence.ends_with("+json")
+    })
+}
+
+/// 路由前按注册表校验消息负载
+pub struct SchemaValidator {
+    registry: SchemaRegistry,
+    mode: SchemaValidation,
+}
+
+impl MessageInterceptor for SchemaValidator {
+    fn name(&self) -> &str {
+        SCHEMA_INTERCEPTOR
+    }
+
+    fn before_route<'a>(&'a self, message: &'a Message) -> InterceptFuture<'a, Interception> {
+        Box::pin(async move {
+            if self.mode == SchemaValidation::Off {
+                return Interception::Pass;
+            }
+            match self.registry.validate(message) {
+                Ok(()) => Interception::Pass,
+                Err(reason) if self.mode == SchemaValidation::Reject => {
+                    Interception::Reject(reason)
+                }
+                Err(reason) => {
+                    tracing::warn!("消息 {} 来自 {}: {}", message.id, message.from, reason);
+                    Interception::Pass
+                }
+            }
+        })
+    }
+}
+
+/// 由注册表生成 UI 使用的 TypeScript 类型声明
+///
+/// 每个主题生成一个负载类型，`TopicPayloads` 将主题映射到负载类型
+pub fn typescript_bindings(schemas: &[TopicSchema]) -> String {
+    let mut output = String::from("// 由内核主题 schema 注册表生成，请勿手动修改\n");
+    let mut names = HashSet::new();
+    let mut topics = Vec::new();
+
+    for schema in schemas {
+        let base = type_name(&schema.topic);
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while !names.insert(name.clone()) {
+            name = format!("{base}{suffix}");
+            suffix += 1;
+        }
+
+        output.push('\n');
+        let description = if schema.description.is_empty() {
+            schema.topic.clone()
+        } else {
+            schema.description.clone()
+        };
+        output.push_str(&format!(
+            "/** {}（{}） */\n",
+            description.replace("*/", "*\\/"),
+            schema.owner
+        ));
+        output.push_str(&format!(
+            "export type {name} = {};\n",
+            typescript_type(&schema.schema, 0)
+        ));
+        topics.push((schema.topic.as_str(), name));
+    }
+
+    output.push_str("\nexport interface TopicPayloads {\n");
+    for (topic, name) in topics {
+        output.push_str(&format!("  {}: {name};\n", JsonValue::from(topic)));
+    }
+    output.push_str("}\n");
+    output
+}
+
+/// 主题对应的类型名，如 `system.stats` -> `SystemStatsPayload`
+fn type_name(topic: &str) -> String {
+    let mut name: String = topic
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "Topic");
+    }
+    name + "Payload"
+}
+
+/// 将 JSON Schema 转换为 TypeScript 类型（不支持的关键字生成 `unknown`）
+fn typescript_type(schema: &JsonValue, indent: usize) -> String {
+    let JsonValue::Object(schema) = schema else {
+        // `true` 接受任意值，`false` 不接受任何值
+        return match schema {
+            JsonValue::Bool(false) => "never".to_string(),
+            _ => "unknown".to_string(),
+        };
+    };
+
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    if let Some(JsonValue::Array(values)) = schema.get("enum") {
+        return union(values.iter().map(JsonValue::to_string).collect());
+    }
+    for (keyword, separator) in [("anyOf", " | "), ("oneOf", " | "), ("allOf", " & ")] {
+        if let Some(JsonValue::Array(variants)) = schema.get(keyword) {
+            let types: Vec<String> = variants
+                .iter()
+                .map(|variant| wrap(typescript_type(variant, indent)))
+                .collect();
+            return types.join(separator);
+        }
+    }
+
+    match schema.get("type") {
+        Some(JsonValue::String(kind)) => typescript_kind(kind, schema, indent),
+        Some(JsonValue::Array(kinds)) => union(
+            kinds
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .map(|kind| typescript_kind(kind, schema, indent))
+                .collect(),
+        ),
+        _ if schema.contains_key("properties") => typescript_kind("object", schema, indent),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn typescript_kind(
+    kind: &str,
+    schema: &serde_json::Map<String, JsonValue>,
+    indent: usize,
+) -> String {
+    match kind {
+        "string" => "string".to_string(),
+        "number" | "integer" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => match schema.get("items") {
+            Some(items) => format!("{}[]", wrap(typescript_type(items, indent))),
+            None => "unknown[]".to_string(),
+        },
+        "object" => typescript_object(schema, indent),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn typescript_object(schema: &serde_json::Map<String, JsonValue>, indent: usize) -> String {
+    let properties = schema.get("properties").and_then(JsonValue::as_object);
+    let additional = schema
+        .get("additionalProperties")
+        .filter(|additional| !matches!(additional, JsonValue::Bool(false)));
+    if properties.is_none_or(|properties| properties.is_empty()) {
+        return match additional {
+            Some(additional) => format!("Record<string, {}>", typescript_type(additional, indent)),
+            None => "Record<string, unknown>".to_string(),
+        };
+    }
+
+    let required: HashSet<&str> = schema
+        .get("required")
+        .and_then(JsonValue::as_array)
+        .map(|required| required.iter().filter_map(JsonValue::as_str).collect())
+        .unwrap_or_default();
+    let padding = "  ".repeat(indent + 1);
+    let mut fields = String::from("{\n");
+    for (key, property) in properties.into_iter().flatten() {
+        let optional = if required.contains(key.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        fields.push_str(&format!(
+            "{padding}{}{optional}: {};\n",
+            property_key(key),
+            typescript_type(property, indent + 1)
+        ));
+    }
+    if additional.is_some() {
+        // 索引签名需兼容所有已声明的属性
+        fields.push_str(&format!("{padding}[key: string]: unknown;\n"));
+    }
+    fields.push_str(&"  ".repeat(indent));
+    fields.push('}');
+    fields
+}
+
+/// 属性名不是合法标识符时加引号
+fn property_key(key: &str) -> String {
+    let identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if identifier {
+        key.to_string()
+    } else {
+        JsonValue::from(key).to_string()
+    }
+}
+
+fn union(types: Vec<String>) -> String {
+    if types.is_empty() {
+        "never".to_string()
+    } else {
+        types.join(" | ")
+    }
+}
+
+/// 联合类型作为数组元素或交叉类型成员时加括号
+fn wrap(ty: String) -> String {
+    if ty.contains(" | ") || ty.contains(" & ") {
+        format!("({ty})")
+    } else {
+        ty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declaration(topic: &str, schema: &str) -> TopicDeclaration {
+        TopicDeclaration {
+            topic: topic.to_string(),
+            description: String::new(),
+            schema: toml::from_str(schema).unwrap(),
+        }
+    }
+
+    fn stats_schema() -> TopicDeclaration {
+        declaration(
+            "system.stats",
+            r#"
+type = "object"
+required = ["cpu_usage"]
+properties.cpu_usage = { type = "number", minimum = 0 }
+properties.hostname = { type = "string" }
+"#,
+        )
+    }
+
+    fn publish(topic: &str, payload: &str) -> Message {
+        Message::new_topic(
+            "collector".into(),
+            topic.into(),
+            payload.as_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_register_and_validate() {
+        let registry = SchemaRegistry::new();
+        registry.register("collector", &[stats_schema()]).unwrap();
+
+        assert!(registry
+            .validate(&publish("system.stats", r#"{"cpu_usage": 12.5}"#))
+            .is_ok());
+        let err = registry
+            .validate(&publish("system.stats", r#"{"cpu_usage": -1}"#))
+            .unwrap_err();
+        assert!(err.contains("/cpu_usage"));
+        assert!(err.contains("collector"));
+        assert!(registry
+            .validate(&publish("system.stats", "not json"))
+            .is_err());
+
+        // 未声明 schema 的主题与非 JSON 负载不校验
+        assert!(registry.validate(&publish("system.other", "raw")).is_ok());
+        let binary = publish("system.stats", "\u{0}").with_type("application/cbor".into());
+        assert!(registry.validate(&binary).is_ok());
+
+        assert_eq!(registry.unregister("collector"), 1);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_and_invalid_declarations() {
+        let registry = SchemaRegistry::new();
+        registry.register("collector", &[stats_schema()]).unwrap();
+
+        let err = registry
+            .register("other", &[stats_schema()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("collector"));
+
+        // 同一插件重新注册时替换之前的声明
+        registry
+            .register(
+                "collector",
+                &[declaration("system.load", "type = \"number\"")],
+            )
+            .unwrap();
+        assert_eq!(registry.list()[0].topic, "system.load");
+
+        assert!(registry
+            .register("bad", &[declaration("a.#.b", "type = \"number\"")])
+            .is_err());
+        assert!(registry
+            .register(
+                "bad",
+                &[declaration("bad.topic", "type = \"no-such-type\"")]
+            )
+            .is_err());
+        assert_eq!(registry.list().len(), 1);
+    }
+
+    #[test]
+    fn test_declared_schemas_skip_bad_manifests() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let write_plugin = |name: &str, manifest: &str| {
+            let dir = temp_dir.path().join(name);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("manifest.toml"), manifest).unwrap();
+            std::fs::write(dir.join(format!("{name}.wasm")), b"").unwrap();
+        };
+        write_plugin(
+            "collector",
+            "[plugin]\nname = \"collector\"\nversion = \"1.0.0\"\n\n\
+             [[topics]]\ntopic = \"system.stats\"\n\n[topics.schema]\ntype = \"object\"\n",
+        );
+        write_plugin("broken", "[plugin\nname = ");
+
+        let registry = declared_schemas(temp_dir.path()).unwrap();
+        let schemas = registry.list();
+        assert_eq!(schemas.len(), 1);
+        assert_eq!(schemas[0].owner, "collector");
+    }
+
+    #[tokio::test]
+    async fn test_validator_modes() {
+        let registry = SchemaRegistry::new();
+        registry.register("collector", &[stats_schema()]).unwrap();
+        let invalid = publish("system.stats", r#"{"hostname": "pc"}"#);
+
+        assert!(matches!(
+            registry.validator(SchemaValidation::Reject).before_route(&invalid).await,
+            Interception::Reject(reason) if reason.contains("cpu_usage")
+        ));
+        assert!(matches!(
+            registry
+                .validator(SchemaValidation::Warn)
+                .before_route(&invalid)
+                .await,
+            Interception::Pass
+        ));
+    }
+
+    #[test]
+    fn test_typescript_bindings() {
+        let registry = SchemaRegistry::new();
+        let mut stats = stats_schema();
+        stats.description = "系统资源统计".to_string();
+        registry
+            .register(
+                "collector",
+                &[
+                    stats,
+                    declaration(
+                        "health.+.samples",
+                        r#"
+type = "array"
+items = { type = ["number", "null"] }
+"#,
+                    ),
+                ],
+            )
+            .unwrap();
+
+        let bindings = typescript_bindings(&registry.list());
+        assert!(bindings.contains("/** 系统资源统计（collector） */"));
+        assert!(bindings.contains(
+            "export type SystemStatsPayload = {\n  cpu_usage: number;\n  hostname?: string;\n};"
+        ));
+        assert!(bindings.contains("export type HealthSamplesPayload = (number | null)[];"));
+        assert!(bindings.contains("  \"system.stats\": SystemStatsPayload;"));
+    }
+}
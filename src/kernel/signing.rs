@@ -0,0 +1,270 @@
+//! 消息签名
+//!
+//! 插件把消息提交到总线时，内核用发送方插件的派生密钥为消息签名；
+//! 路由前签名验证器（拦截器链的最后一环，在其它拦截器修改消息之后）检查签名，
+//! 通过后设置 `verified_from`。受保护主题上的消息签名缺失或无效时被拒绝，不会投递给订阅者
+
+use alloy::primitives::{keccak256, B256};
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::interceptor::{InterceptFuture, Interception, MessageInterceptor};
+use super::message::Message;
+use super::topic_index::{topic_matches, validate_topic_pattern};
+use crate::config::SigningConfig;
+use crate::identity::IdentityManager;
+
+/// 签名验证器在拦截器链中的名称
+pub const SIGNATURE_INTERCEPTOR: &str = "signature";
+
+/// 消息签名器（可克隆）
+#[derive(Clone)]
+pub struct MessageSigner {
+    identity: Arc<IdentityManager>,
+    /// 是否为所有消息签名
+    sign_all: bool,
+    /// 受保护的主题模式
+    protected_topics: Arc<Vec<String>>,
+}
+
+impl MessageSigner {
+    /// 创建签名器，主题模式无效时返回错误
+    pub fn new(identity: Arc<IdentityManager>, config: &SigningConfig) -> Result<Self> {
+        for pattern in &config.protected_topics {
+            validate_topic_pattern(pattern)?;
+        }
+
+        Ok(Self {
+            identity,
+            sign_all: config.enabled,
+            protected_topics: Arc::new(config.protected_topics.clone()),
+        })
+    }
+
+    /// 消息是否发往受保护的主题
+    pub fn is_protected(&self, message: &Message) -> bool {
+        message.topic.as_deref().is_some_and(|topic| {
+            self.protected_topics
+                .iter()
+                .any(|pattern| topic_matches(pattern, topic))
+        })
+    }
+
+    /// 消息是否需要签名
+    pub fn should_sign(&self, message: &Message) -> bool {
+        self.sign_all || self.is_protected(message)
+    }
+
+    /// 需要时以发送方的派生密钥为消息签名
+    ///
+    /// 签名失败时消息不带签名继续发送（受保护主题上的消息随后会被拒绝）
+    pub async fn sign(&self, message: &mut Message) {
+        if !self.should_sign(message) {
+            return;
+        }
+
+        let digest = signing_digest(message);
+        match self
+            .identity
+            .sign_for_plugin(&message.from, digest.as_slice())
+            .await
+        {
+            Ok(signature) => message.signature = Some(hex::encode(signature)),
+            Err(e) => {
+                tracing::warn!("无法为 {} 的消息 {} 签名: {}", message.from, message.id, e);
+                message.signature = None;
+            }
+        }
+        message.verified_from = false;
+    }
+
+    /// 验证消息签名是否出自 `from` 的派生密钥
+    pub async fn verify(&self, message: &Message) -> bool {
+        let Some(signature) = message
+            .signature
+            .as_deref()
+            .and_then(|signature| hex::decode(signature).ok())
+        else {
+            return false;
+        };
+
+        let digest = signing_digest(message);
+        self.identity
+            .verify_plugin_signature(&message.from, digest.as_slice(), &signature)
+            .await
+            .unwrap_or(false)
+    }
+}
+
+/// 路由前验证签名：设置 `verified_from`，拒绝受保护主题上未通过验证的消息
+impl MessageInterceptor for MessageSigner {
+    fn name(&self) -> &str {
+        SIGNATURE_INTERCEPTOR
+    }
+
+    fn before_route<'a>(&'a self, message: &'a Message) -> InterceptFuture<'a, Interception> {
+        Box::pin(async move {
+            let verified = self.verify(message).await;
+            if !verified && self.is_protected(message) {
+                let reason = match message.signature {
+                    Some(_) => "签名无效",
+                    None => "缺少签名",
+                };
+                return Interception::Reject(format!("受保护主题的消息{reason}"));
+            }
+            if message.signature.is_some() && !verified {
+                tracing::warn!("消息 {} 的签名与发送方 {} 不符", message.id, message.from);
+            }
+
+            if verified == message.verified_from {
+                return Interception::Pass;
+            }
+            let mut checked = message.clone();
+            checked.verified_from = verified;
+            Interception::Modify(Box::new(checked))
+        })
+    }
+}
+
+/// 计算签名覆盖的摘要：消息 ID、收发方、主题、类型、关联 ID、时间戳与负载
+///
+/// 元数据、优先级与过期时间不在签名范围内
+fn signing_digest(message: &Message) -> B256 {
+    let mut buf = Vec::with_capacity(message.payload.len() + 256);
+    let mut push = |field: Option<&[u8]>| match field {
+        Some(bytes) => {
+            buf.push(1);
+            buf.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+            buf.extend_from_slice(bytes);
+        }
+        None => buf.push(0),
+    };
+
+    push(Some(message.id.as_bytes()));
+    push(Some(message.from.as_bytes()));
+    push(Some(message.to.as_bytes()));
+    push(message.topic.as_deref().map(str::as_bytes));
+    push(message.msg_type.as_deref().map(str::as_bytes));
+    push(message.correlation_id.as_deref().map(str::as_bytes));
+    push(Some(&message.timestamp.timestamp_millis().to_be_bytes()));
+    push(Some(&message.payload));
+
+    keccak256(&buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::interceptor::InterceptorChain;
+
+    fn signer(enabled: bool, protected_topics: &[&str]) -> MessageSigner {
+        let config = SigningConfig {
+            enabled,
+            protected_topics: protected_topics.iter().map(|t| t.to_string()).collect(),
+        };
+        MessageSigner::new(Arc::new(IdentityManager::new().unwrap()), &config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify() {
+        let signer = signer(false, &["health.#"]);
+
+        let mut message = Message::new_topic(
+            "sensor".to_string(),
+            "health.heart_rate".to_string(),
+            b"72".to_vec(),
+        );
+        assert!(signer.should_sign(&message));
+        signer.sign(&mut message).await;
+        assert!(signer.verify(&message).await);
+
+        // 篡改负载或冒充发送方后签名失效
+        let mut tampered = message.clone();
+        tampered.payload = b"180".to_vec();
+        assert!(!signer.verify(&tampered).await);
+        let mut forged = message.clone();
+        forged.from = "other".to_string();
+        assert!(!signer.verify(&forged).await);
+
+        // 未受保护且未启用全局签名时不签名
+        let mut plain = Message::new_topic("sensor".into(), "system.stats".into(), vec![]);
+        signer.sign(&mut plain).await;
+        assert!(plain.signature.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verifier_rejects_unsigned_protected_messages() {
+        let signer = signer(true, &["health.#"]);
+
+        let unsigned = Message::new_topic("sensor".into(), "health.spo2".into(), b"98".to_vec());
+        assert!(matches!(
+            signer.before_route(&unsigned).await,
+            Interception::Reject(reason) if reason.contains("缺少签名")
+        ));
+
+        // 非受保护主题放行，但自称已验证的消息会被纠正
+        let mut claimed = Message::new_topic("sensor".into(), "system.stats".into(), vec![]);
+        claimed.verified_from = true;
+        match signer.before_route(&claimed).await {
+            Interception::Modify(checked) => assert!(!checked.verified_from),
+            other => panic!("unexpected interception: {other:?}"),
+        }
+
+        let mut signed = unsigned.clone();
+        signer.sign(&mut signed).await;
+        match signer.before_route(&signed).await {
+            Interception::Modify(checked) => assert!(checked.verified_from),
+            other => panic!("unexpected interception: {other:?}"),
+        }
+    }
+
+    /// 把负载替换为固定内容的拦截器
+    struct Rewriter;
+
+    impl MessageInterceptor for Rewriter {
+        fn name(&self) -> &str {
+            "rewriter"
+        }
+
+        fn before_route<'a>(&'a self, message: &'a Message) -> InterceptFuture<'a, Interception> {
+            Box::pin(async move {
+                let mut rewritten = message.clone();
+                rewritten.payload = b"0".to_vec();
+                Interception::Modify(Box::new(rewritten))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verifier_runs_after_modifying_interceptors() {
+        let signer = signer(true, &["health.#"]);
+        let chain = InterceptorChain::default();
+        chain.set_verifier(Arc::new(signer.clone()));
+        chain.add(Arc::new(Rewriter));
+        assert_eq!(chain.names(), vec!["rewriter", SIGNATURE_INTERCEPTOR]);
+
+        // 受保护主题上被改写的消息不再通过验证
+        let mut protected =
+            Message::new_topic("sensor".into(), "health.spo2".into(), b"98".to_vec());
+        signer.sign(&mut protected).await;
+        let rejected = chain.before_route(protected).await.unwrap_err();
+        assert_eq!(rejected.interceptor, SIGNATURE_INTERCEPTOR);
+
+        // 其它主题放行，但不再标记为已验证
+        let mut plain = Message::new_topic("sensor".into(), "system.stats".into(), b"1".to_vec());
+        signer.sign(&mut plain).await;
+        assert!(signer.verify(&plain).await);
+        let routed = chain.before_route(plain).await.unwrap();
+        assert_eq!(routed.payload, b"0");
+        assert!(!routed.verified_from);
+    }
+
+    #[test]
+    fn test_invalid_protected_topic_rejected() {
+        let config = SigningConfig {
+            enabled: false,
+            protected_topics: vec!["health.#.raw".to_string()],
+        };
+        assert!(MessageSigner::new(Arc::new(IdentityManager::new().unwrap()), &config).is_err());
+    }
+}
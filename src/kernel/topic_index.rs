@@ -0,0 +1,333 @@
+//! 主题订阅索引
+//!
+//! 主题按 `.` 分层，订阅模式支持通配符：
+//! - `+` 或 `*`：匹配恰好一层，如 `system.+.stats`、`health.*`
+//! - `#`：匹配剩余的零层或多层，只能出现在末尾，如 `health.#`、`#`
+//!
+//! 订阅按层存入前缀树，匹配时只沿主题的各层向下查找，
+//! 耗时与主题层数相关，而不随订阅总数线性增长
+
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+
+/// 主题层级分隔符
+pub const TOPIC_SEPARATOR: char = '.';
+
+/// 单层通配符
+pub const SINGLE_LEVEL_WILDCARD: &str = "+";
+
+/// 单层通配符的别名
+pub const SINGLE_LEVEL_WILDCARD_ALIAS: &str = "*";
+
+/// 多层通配符
+pub const MULTI_LEVEL_WILDCARD: &str = "#";
+
+/// 校验订阅模式
+pub fn validate_topic_pattern(pattern: &str) -> Result<()> {
+    let segments: Vec<&str> = pattern.split(TOPIC_SEPARATOR).collect();
+    for (i, segment) in segments.iter().enumerate() {
+        if segment.is_empty() {
+            return Err(anyhow!("主题模式 '{}' 含有空层级", pattern));
+        }
+        if *segment == MULTI_LEVEL_WILDCARD && i + 1 != segments.len() {
+            return Err(anyhow!("主题模式 '{}' 中的 # 只能位于末尾", pattern));
+        }
+        if segment.len() > 1 && segment.contains(['+', '*', '#']) {
+            return Err(anyhow!("主题模式 '{}' 中的通配符必须独占一层", pattern));
+        }
+    }
+    Ok(())
+}
+
+/// 检查主题是否匹配订阅模式
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    let mut pattern = pattern.split(TOPIC_SEPARATOR);
+    let mut topic = topic.split(TOPIC_SEPARATOR);
+
+    loop {
+        match (pattern.next(), topic.next()) {
+            (Some(MULTI_LEVEL_WILDCARD), _) => return true,
+            (Some(p), Some(_)) if is_single_level(p) => {}
+            (Some(p), Some(t)) if p == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+fn is_single_level(segment: &str) -> bool {
+    segment == SINGLE_LEVEL_WILDCARD || segment == SINGLE_LEVEL_WILDCARD_ALIAS
+}
+
+/// 前缀树节点
+#[derive(Debug, Default)]
+struct TopicNode {
+    /// 下一层：层级名（`*` 归一为 `+`）-> 子节点
+    children: HashMap<String, TopicNode>,
+    /// 订阅模式恰好在此结束的订阅者
+    subscribers: HashSet<String>,
+    /// 在此层订阅了 `#` 的订阅者
+    multi_level: HashSet<String>,
+}
+
+impl TopicNode {
+    fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.subscribers.is_empty() && self.multi_level.is_empty()
+    }
+
+    /// 收集匹配剩余层级的订阅者
+    fn collect(&self, segments: &[&str], matched: &mut HashSet<String>) {
+        matched.extend(self.multi_level.iter().cloned());
+
+        let Some((segment, rest)) = segments.split_first() else {
+            matched.extend(self.subscribers.iter().cloned());
+            return;
+        };
+
+        if let Some(child) = self.children.get(*segment) {
+            child.collect(rest, matched);
+        }
+        if let Some(child) = self.children.get(SINGLE_LEVEL_WILDCARD) {
+            child.collect(rest, matched);
+        }
+    }
+
+    /// 移除订阅者的所有订阅并清理空节点
+    fn remove_subscriber(&mut self, subscriber: &str) -> usize {
+        let mut removed = usize::from(self.subscribers.remove(subscriber))
+            + usize::from(self.multi_level.remove(subscriber));
+        self.children.retain(|_, child| {
+            removed += child.remove_subscriber(subscriber);
+            !child.is_empty()
+        });
+        removed
+    }
+
+    /// 列出所有订阅模式
+    fn patterns(&self, prefix: &str, out: &mut Vec<(String, Vec<String>)>) {
+        let join = |segment: &str| {
+            if prefix.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{prefix}{TOPIC_SEPARATOR}{segment}")
+            }
+        };
+
+        if !self.subscribers.is_empty() {
+            out.push((
+                prefix.to_string(),
+                self.subscribers.iter().cloned().collect(),
+            ));
+        }
+        if !self.multi_level.is_empty() {
+            let pattern = join(MULTI_LEVEL_WILDCARD);
+            out.push((pattern, self.multi_level.iter().cloned().collect()));
+        }
+        for (segment, child) in &self.children {
+            child.patterns(&join(segment), out);
+        }
+    }
+}
+
+/// 主题订阅索引
+#[derive(Debug, Default)]
+pub struct TopicIndex {
+    root: TopicNode,
+}
+
+impl TopicIndex {
+    /// 创建空索引
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加订阅，已订阅过同一模式时返回 false
+    pub fn subscribe(&mut self, pattern: &str, subscriber: &str) -> bool {
+        let (node, multi_level) = self.node_mut(pattern);
+        let set = if multi_level {
+            &mut node.multi_level
+        } else {
+            &mut node.subscribers
+        };
+        set.insert(subscriber.to_string())
+    }
+
+    /// 取消订阅，未订阅该模式时返回 false
+    pub fn unsubscribe(&mut self, pattern: &str, subscriber: &str) -> bool {
+        let segments = normalized_segments(pattern);
+        Self::remove_at(&mut self.root, &segments, subscriber)
+    }
+
+    fn remove_at(node: &mut TopicNode, segments: &[String], subscriber: &str) -> bool {
+        match segments.split_first() {
+            None => node.subscribers.remove(subscriber),
+            Some((segment, [])) if segment == MULTI_LEVEL_WILDCARD => {
+                node.multi_level.remove(subscriber)
+            }
+            Some((segment, rest)) => {
+                let Some(child) = node.children.get_mut(segment) else {
+                    return false;
+                };
+                let removed = Self::remove_at(child, rest, subscriber);
+                if child.is_empty() {
+                    node.children.remove(segment);
+                }
+                removed
+            }
+        }
+    }
+
+    /// 移除订阅者的全部订阅，返回移除的数量
+    pub fn remove_subscriber(&mut self, subscriber: &str) -> usize {
+        self.root.remove_subscriber(subscriber)
+    }
+
+    /// 获取订阅了指定模式（精确匹配模式本身）的订阅者
+    pub fn pattern_subscribers(&self, pattern: &str) -> Vec<String> {
+        let segments = normalized_segments(pattern);
+        let mut node = &self.root;
+        let mut multi_level = false;
+        for (i, segment) in segments.iter().enumerate() {
+            if segment == MULTI_LEVEL_WILDCARD && i + 1 == segments.len() {
+                multi_level = true;
+                break;
+            }
+            match node.children.get(segment) {
+                Some(child) => node = child,
+                None => return Vec::new(),
+            }
+        }
+
+        let set = if multi_level {
+            &node.multi_level
+        } else {
+            &node.subscribers
+        };
+        set.iter().cloned().collect()
+    }
+
+    /// 获取应收到指定主题消息的订阅者（每个订阅者只出现一次）
+    pub fn matching_subscribers(&self, topic: &str) -> HashSet<String> {
+        let segments: Vec<&str> = topic.split(TOPIC_SEPARATOR).collect();
+        let mut matched = HashSet::new();
+        self.root.collect(&segments, &mut matched);
+        matched
+    }
+
+    /// 列出所有订阅模式及其订阅者
+    pub fn patterns(&self) -> Vec<(String, Vec<String>)> {
+        let mut out = Vec::new();
+        self.root.patterns("", &mut out);
+        out
+    }
+
+    /// 定位模式对应的节点（不存在时创建），返回节点与是否为 `#` 订阅
+    fn node_mut(&mut self, pattern: &str) -> (&mut TopicNode, bool) {
+        let segments = normalized_segments(pattern);
+        let mut node = &mut self.root;
+        for (i, segment) in segments.iter().enumerate() {
+            if segment == MULTI_LEVEL_WILDCARD && i + 1 == segments.len() {
+                return (node, true);
+            }
+            node = node.children.entry(segment.clone()).or_default();
+        }
+        (node, false)
+    }
+}
+
+/// 拆分模式的层级，并将 `*` 归一为 `+`
+fn normalized_segments(pattern: &str) -> Vec<String> {
+    pattern
+        .split(TOPIC_SEPARATOR)
+        .map(|segment| {
+            if is_single_level(segment) {
+                SINGLE_LEVEL_WILDCARD.to_string()
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(set: HashSet<String>) -> Vec<String> {
+        let mut subscribers: Vec<String> = set.into_iter().collect();
+        subscribers.sort();
+        subscribers
+    }
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("health.cpu", "health.cpu"));
+        assert!(topic_matches("health.*", "health.cpu"));
+        assert!(topic_matches("health.+", "health.cpu"));
+        assert!(!topic_matches("health.*", "health.cpu.load"));
+        assert!(!topic_matches("health.*", "health"));
+        assert!(topic_matches("health.#", "health"));
+        assert!(topic_matches("health.#", "health.cpu.load"));
+        assert!(topic_matches("system.+.stats", "system.node1.stats"));
+        assert!(!topic_matches("system.+.stats", "system.node1.load"));
+        assert!(topic_matches("#", "anything.at.all"));
+        assert!(!topic_matches("health.cpu", "health.cpu.load"));
+    }
+
+    #[test]
+    fn test_validate_topic_pattern() {
+        assert!(validate_topic_pattern("health.#").is_ok());
+        assert!(validate_topic_pattern("system.+.stats").is_ok());
+        assert!(validate_topic_pattern("*").is_ok());
+        assert!(validate_topic_pattern("health.#.cpu").is_err());
+        assert!(validate_topic_pattern("health..cpu").is_err());
+        assert!(validate_topic_pattern("health.cpu*").is_err());
+        assert!(validate_topic_pattern("").is_err());
+    }
+
+    #[test]
+    fn test_overlapping_patterns_match_once() {
+        let mut index = TopicIndex::new();
+        assert!(index.subscribe("health.cpu", "monitor"));
+        assert!(index.subscribe("health.*", "monitor"));
+        assert!(index.subscribe("health.#", "monitor"));
+        assert!(index.subscribe("#", "bridge"));
+        assert!(index.subscribe("system.+.stats", "dashboard"));
+        assert!(!index.subscribe("health.+", "monitor"), "* 与 + 是同一模式");
+
+        assert_eq!(
+            sorted(index.matching_subscribers("health.cpu")),
+            vec!["bridge", "monitor"]
+        );
+        assert_eq!(
+            sorted(index.matching_subscribers("system.node1.stats")),
+            vec!["bridge", "dashboard"]
+        );
+        assert_eq!(
+            sorted(index.matching_subscribers("health")),
+            vec!["bridge", "monitor"]
+        );
+        assert_eq!(sorted(index.matching_subscribers("other")), vec!["bridge"]);
+    }
+
+    #[test]
+    fn test_unsubscribe_and_remove_subscriber() {
+        let mut index = TopicIndex::new();
+        index.subscribe("health.cpu", "monitor");
+        index.subscribe("health.#", "monitor");
+        index.subscribe("health.#", "logger");
+
+        assert!(index.unsubscribe("health.#", "monitor"));
+        assert!(!index.unsubscribe("health.#", "monitor"));
+        assert_eq!(index.pattern_subscribers("health.#"), vec!["logger"]);
+        assert_eq!(
+            sorted(index.matching_subscribers("health.cpu")),
+            vec!["logger", "monitor"]
+        );
+
+        assert_eq!(index.remove_subscriber("monitor"), 1);
+        assert_eq!(index.remove_subscriber("logger"), 1);
+        assert!(index.patterns().is_empty());
+        assert!(index.root.is_empty());
+    }
+}
@@ -1,7 +1,14 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
-use minimal_kernel::config::{Cli, Commands, Config};
+use minimal_kernel::config::{BusCommands, Cli, Commands, Config, RegistryCommands};
+use minimal_kernel::identity::IdentityManager;
+use minimal_kernel::kernel::bus_metrics::{BusStats, MessageCounters};
+use minimal_kernel::kernel::package::{self, PluginPackage, PACKAGE_EXTENSION};
+use minimal_kernel::kernel::plugin_loader::scan_plugins;
+use minimal_kernel::kernel::registry::{self, Registry};
+use minimal_kernel::kernel::schema_registry::declared_schemas;
 use minimal_kernel::kernel::Kernel;
+use minimal_kernel::storage::{MessageLogQuery, Storage};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -59,6 +66,10 @@ async fn handle_command(command: Commands, config: &Config) -> Result<()> {
                     plugin.file_size,
                     status
                 );
+                println!(
+                    "    权限: {}",
+                    describe_permissions(plugin.permissions.as_deref())
+                );
             }
         }
         Commands::PluginInfo { name } => {
@@ -66,9 +77,13 @@ async fn handle_command(command: Commands, config: &Config) -> Result<()> {
             let mut kernel = Kernel::new(config.clone()).await?;
 
             // 尝试加载插件（如果还没加载）
-            if !kernel.list_loaded_plugins().contains(&name.as_str()) {
-                let plugins = kernel.discover_plugins(&config.plugins.directory)?;
-                if let Some(plugin) = plugins.iter().find(|p| p.name == name) {
+            let plugins = kernel.discover_plugins(&config.plugins.directory)?;
+            if let Some(plugin) = plugins.iter().find(|p| p.name == name) {
+                println!(
+                    "请求的权限: {}",
+                    describe_permissions(plugin.permissions.as_deref())
+                );
+                if !kernel.list_loaded_plugins().contains(&name.as_str()) {
                     kernel.load_plugin(&name, plugin.path.to_str().unwrap())?;
                 }
             }
@@ -80,6 +95,197 @@ async fn handle_command(command: Commands, config: &Config) -> Result<()> {
                 println!("无法获取插件 '{name}' 的信息");
             }
         }
+        Commands::Journal {
+            plugin,
+            topic,
+            status,
+            since,
+            until,
+            limit,
+        } => {
+            // 只需要读取数据库，不启动内核
+            let storage = Storage::new(&config.database.url).await?;
+            let query = MessageLogQuery {
+                plugin_id: plugin,
+                topic,
+                status,
+                since,
+                until,
+                limit,
+            };
+            let entries = storage.search_message_log(&query).await?;
+
+            if entries.is_empty() {
+                println!("没有匹配的消息记录");
+            }
+            for entry in entries {
+                let target = entry
+                    .topic
+                    .map(|topic| format!("#{topic}"))
+                    .unwrap_or(entry.to_plugin);
+                println!(
+                    "{} {} {} -> {} [{}]",
+                    entry.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.message_id,
+                    entry.from_plugin,
+                    target,
+                    entry.status
+                );
+                if let Some(error) = entry.error {
+                    println!("    错误: {error}");
+                }
+                if let Some(signature) = entry.signature {
+                    let verified = if entry.verified_from {
+                        "已验证"
+                    } else {
+                        "未通过验证"
+                    };
+                    println!("    签名: {signature} ({verified})");
+                }
+            }
+        }
+        Commands::DeadLetters { plugin, limit } => {
+            let storage = Storage::new(&config.database.url).await?;
+            let dead_letters = storage.list_dead_letters(plugin.as_deref(), limit).await?;
+
+            if dead_letters.is_empty() {
+                println!("没有死信");
+            }
+            for dead in dead_letters {
+                println!(
+                    "[{}] {} {} -> {} (投递 {} 次)",
+                    dead.id,
+                    dead.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    dead.message_id,
+                    dead.recipient,
+                    dead.attempts
+                );
+                if let Some(error) = dead.last_error {
+                    println!("    错误: {error}");
+                }
+            }
+        }
+        Commands::ReplayDeadLetter { id } => {
+            let storage = Storage::new(&config.database.url).await?;
+            if storage
+                .replay_dead_letter(id, chrono::Utc::now().timestamp_millis())
+                .await?
+            {
+                println!("死信 {id} 已放回投递队列");
+            } else {
+                println!("死信 {id} 不存在");
+            }
+        }
+        Commands::Pack { dir, output } => {
+            let identity = IdentityManager::new_with_config(&config.identity).await?;
+            let package = PluginPackage::build(&dir, &identity)?;
+            let output = output.unwrap_or_else(|| {
+                format!(
+                    "{}-{}.{PACKAGE_EXTENSION}",
+                    package.name(),
+                    package.version()
+                )
+                .into()
+            });
+            package.write(&output)?;
+            println!(
+                "已打包 {} {} -> {}",
+                package.name(),
+                package.version(),
+                output.display()
+            );
+            println!("发布者: {}", package.index.package.publisher);
+        }
+        Commands::Install { package } => {
+            let package = PluginPackage::read(&package)?;
+            println!(
+                "{} {} 请求的权限: {}",
+                package.name(),
+                package.version(),
+                package_permissions(&package)?
+            );
+            let target = package.install(
+                &config.plugins.directory,
+                &config.plugins.trusted_publishers,
+            )?;
+            println!(
+                "已安装 {} {} 到 {}",
+                package.name(),
+                package.version(),
+                target.display()
+            );
+        }
+        Commands::Uninstall { name } => {
+            let target = package::uninstall(&config.plugins.directory, &name)?;
+            println!("已卸载 {name}（删除 {}）", target.display());
+        }
+        Commands::Verify { package } => {
+            let package = PluginPackage::read(&package)?;
+            let publisher = package.verify(&config.plugins.trusted_publishers)?;
+            println!("{} {} 校验通过", package.name(), package.version());
+            println!("发布者: {} ({})", publisher.name, publisher.address);
+            println!("请求的权限: {}", package_permissions(&package)?);
+            for path in package.file_paths() {
+                println!("    {path}");
+            }
+        }
+        Commands::Registry {
+            command: RegistryCommands::Index { dir },
+            ..
+        } => {
+            let index = registry::write_index(&dir)?;
+            println!(
+                "已生成 {}（{} 个插件包）",
+                dir.join(registry::INDEX_FILE).display(),
+                index.packages.len()
+            );
+        }
+        Commands::Registry {
+            registry: location,
+            command,
+        } => {
+            let location = location
+                .or_else(|| config.plugins.registry.clone())
+                .ok_or_else(|| anyhow!("未配置插件仓库（plugins.registry 或 --registry）"))?;
+            let registry = Registry::open(&location)?;
+            handle_registry_command(command, &registry, config)?;
+        }
+        Commands::Bus {
+            command: BusCommands::Stats { json },
+        } => {
+            // 读取运行中内核定期保存的快照
+            let storage = Storage::new(&config.database.url).await?;
+            let Some(snapshot) = storage.load_bus_stats().await? else {
+                println!("没有总线统计（内核未运行，或 message_bus.stats_snapshot_secs 为 0）");
+                return Ok(());
+            };
+            if json {
+                println!("{snapshot}");
+            } else {
+                print_bus_stats(&serde_json::from_str(&snapshot)?);
+            }
+        }
+        Commands::Bus {
+            command: BusCommands::Schemas { json },
+        } => {
+            // 读取插件清单中的声明，不加载插件
+            let schemas = declared_schemas(&config.plugins.directory)?.list();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&schemas)?);
+                return Ok(());
+            }
+
+            if schemas.is_empty() {
+                println!("没有插件声明主题 schema");
+            }
+            for schema in schemas {
+                println!("{} ({})", schema.topic, schema.owner);
+                if !schema.description.is_empty() {
+                    println!("    {}", schema.description);
+                }
+                println!("    {}", schema.schema);
+            }
+        }
         Commands::ResetConfig => {
             // 重置配置
             let default_config = Config::default();
@@ -94,3 +300,198 @@ async fn handle_command(command: Commands, config: &Config) -> Result<()> {
 
     Ok(())
 }
+
+/// 处理插件仓库子命令
+fn handle_registry_command(
+    command: RegistryCommands,
+    registry: &Registry,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        RegistryCommands::Search { query } => {
+            let entries = registry.search(query.as_deref().unwrap_or(""));
+            if entries.is_empty() {
+                println!("没有匹配的插件");
+            }
+            for entry in entries {
+                let incompatible = if registry.is_compatible(entry) {
+                    String::new()
+                } else {
+                    format!(
+                        "（需要内核 {}）",
+                        entry.min_kernel_version.as_deref().unwrap_or_default()
+                    )
+                };
+                println!(
+                    "{} {}{} - {}",
+                    entry.name, entry.version, incompatible, entry.description
+                );
+                if !entry.tags.is_empty() {
+                    println!("    标签: {}", entry.tags.join(", "));
+                }
+            }
+        }
+        RegistryCommands::Info { name } => {
+            let versions = registry.versions(&name);
+            let Some(latest) = versions.first() else {
+                println!("仓库中没有插件 {name}");
+                return Ok(());
+            };
+            println!("{} - {}", latest.name, latest.description);
+            if let Some(author) = &latest.author {
+                println!("作者: {author}");
+            }
+            println!("发布者: {}", latest.publisher);
+            for entry in versions {
+                let requires: Vec<_> = entry
+                    .dependencies
+                    .requires
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                let kernel = entry
+                    .min_kernel_version
+                    .as_deref()
+                    .map(|version| format!("，内核 {version}"))
+                    .unwrap_or_default();
+                println!("  {}{} [{}]", entry.version, kernel, entry.package);
+                if !requires.is_empty() {
+                    println!("    依赖: {}", requires.join(", "));
+                }
+            }
+        }
+        RegistryCommands::Install { plugin } => {
+            let installed = scan_plugins(&config.plugins.directory)?;
+            let plan = registry.resolve_install(&plugin, &installed)?;
+
+            // 先校验全部插件包，任何一个失败都不安装
+            let mut packages = Vec::new();
+            for entry in plan {
+                let package = PluginPackage::read(&registry.package_path(entry))?;
+                if package.name() != entry.name || package.version() != entry.version {
+                    return Err(anyhow!(
+                        "插件包 {} 与索引不一致（{} {}）",
+                        entry.package,
+                        entry.name,
+                        entry.version
+                    ));
+                }
+                package.verify(&config.plugins.trusted_publishers)?;
+                println!(
+                    "{} {} 请求的权限: {}",
+                    package.name(),
+                    package.version(),
+                    package_permissions(&package)?
+                );
+                packages.push(package);
+            }
+
+            for package in packages {
+                let target = package.install(
+                    &config.plugins.directory,
+                    &config.plugins.trusted_publishers,
+                )?;
+                println!(
+                    "已安装 {} {} 到 {}",
+                    package.name(),
+                    package.version(),
+                    target.display()
+                );
+            }
+        }
+        RegistryCommands::Outdated => {
+            let installed = scan_plugins(&config.plugins.directory)?;
+            let outdated = registry.outdated(&installed);
+            if outdated.is_empty() {
+                println!("所有已安装的插件都是最新版本");
+            }
+            for plugin in outdated {
+                println!("{} {} -> {}", plugin.name, plugin.installed, plugin.latest);
+            }
+        }
+        RegistryCommands::Index { .. } => unreachable!("由 handle_command 处理"),
+    }
+
+    Ok(())
+}
+
+/// 格式化插件声明的权限
+fn describe_permissions(permissions: Option<&[String]>) -> String {
+    match permissions {
+        Some([]) => "无".to_string(),
+        Some(permissions) => permissions.join(", "),
+        None => "未声明（使用默认权限）".to_string(),
+    }
+}
+
+/// 插件包清单中请求的权限
+fn package_permissions(package: &PluginPackage) -> Result<String> {
+    let manifest = package.manifest()?;
+    Ok(describe_permissions(
+        manifest
+            .permissions
+            .as_ref()
+            .map(|permissions| permissions.allow.as_slice()),
+    ))
+}
+
+/// 输出总线统计
+fn print_bus_stats(stats: &BusStats) {
+    let age = (chrono::Utc::now() - stats.collected_at)
+        .num_seconds()
+        .max(0);
+    println!(
+        "总线统计（采集于 {}，{} 秒前；已运行 {} 秒）",
+        stats.collected_at.format("%Y-%m-%d %H:%M:%S"),
+        age,
+        stats.uptime_secs
+    );
+    println!("  总计: {}", describe_counters(&stats.totals));
+    println!(
+        "  待路由: {}  等待回复的请求: {}",
+        stats.router_backlog, stats.pending_requests
+    );
+    println!(
+        "  路由延迟: 平均 {}µs, 最大 {}µs ({} 条)",
+        stats.latency.mean_us, stats.latency.max_us, stats.latency.count
+    );
+
+    if !stats.topics.is_empty() {
+        println!("主题:");
+        for (topic, counters) in &stats.topics {
+            println!("  {topic}: {}", describe_counters(counters));
+        }
+    }
+
+    if !stats.plugins.is_empty() {
+        println!("插件:");
+        for (plugin_id, plugin) in &stats.plugins {
+            println!("  {plugin_id}: {}", describe_counters(&plugin.counters));
+            if let Some(queue) = &plugin.queue {
+                println!(
+                    "    队列: {}/{} ({:?}), 丢弃 {}, 溢出到磁盘 {}; 订阅 {} 个主题模式",
+                    queue.depth,
+                    queue.capacity,
+                    queue.overflow,
+                    queue.dropped,
+                    queue.spilled,
+                    plugin.subscriptions
+                );
+            }
+        }
+    }
+}
+
+/// 格式化消息计数
+fn describe_counters(counters: &MessageCounters) -> String {
+    format!(
+        "发布 {}, 投递 {}, 排队 {}, 丢弃 {}, 失败 {}, 过期 {}, 拒绝 {}",
+        counters.published,
+        counters.delivered,
+        counters.queued,
+        counters.dropped,
+        counters.failed,
+        counters.expired,
+        counters.rejected
+    )
+}
@@ -34,6 +34,8 @@ pub struct PluginMetadata {
     pub loaded_at: DateTime<Utc>,
     pub last_active: Option<DateTime<Utc>>,
     pub config: Option<JsonValue>,
+    /// 生命周期状态
+    pub state: String,
 }
 
 /// 消息日志模型
@@ -48,8 +50,68 @@ pub struct MessageLogEntry {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub delivered_at: Option<DateTime<Utc>>,
+    /// 主题消息的主题
+    pub topic: Option<String>,
+    /// 投递失败原因
+    pub error: Option<String>,
+    /// 内核为消息生成的签名（十六进制）
+    pub signature: Option<String>,
+    /// 签名是否通过验证
+    pub verified_from: bool,
 }
 
+/// 消息日志查询条件（均为可选，按时间倒序返回）
+#[derive(Debug, Clone, Default)]
+pub struct MessageLogQuery {
+    /// 发送方或接收方
+    pub plugin_id: Option<String>,
+    /// 主题
+    pub topic: Option<String>,
+    /// 投递状态
+    pub status: Option<String>,
+    /// 起始时间（含）
+    pub since: Option<DateTime<Utc>>,
+    /// 截止时间（含）
+    pub until: Option<DateTime<Utc>>,
+    /// 最多返回的条数（0 表示使用默认值）
+    pub limit: i64,
+}
+
+/// 持久投递队列中的消息（每个接收方一条）
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DurableMessage {
+    pub id: i64,
+    pub message_id: String,
+    pub recipient: String,
+    /// 序列化的消息（JSON）
+    pub message: String,
+    /// 已投递次数
+    pub attempts: i64,
+    /// 下次投递时间（毫秒时间戳）
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 死信（超过最大投递次数的消息）
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub message_id: String,
+    pub recipient: String,
+    /// 序列化的消息（JSON）
+    pub message: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 消息日志查询默认返回的条数
+const DEFAULT_MESSAGE_LOG_LIMIT: i64 = 100;
+
+/// SQLite CURRENT_TIMESTAMP 的格式
+const SQLITE_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
 /// 存储管理器
 pub struct Storage {
     pool: SqlitePool,
@@ -258,6 +320,66 @@ impl Storage {
         Ok(result)
     }
 
+    /// 获取插件已保存的配置
+    pub async fn get_plugin_config(&self, plugin_id: &str) -> Result<Option<JsonValue>> {
+        let query = "SELECT config FROM plugin_metadata WHERE plugin_id = ?1";
+
+        let result = sqlx::query(query)
+            .bind(plugin_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(result.and_then(|row| row.get::<Option<JsonValue>, _>("config")))
+    }
+
+    /// 保存插件配置（插件尚未注册时创建元数据记录）
+    pub async fn set_plugin_config(&self, plugin_id: &str, config: &JsonValue) -> Result<()> {
+        let query = r#"
+            INSERT INTO plugin_metadata (plugin_id, name, version, config)
+            VALUES (?1, ?1, 'unknown', ?2)
+            ON CONFLICT(plugin_id) DO UPDATE SET config = excluded.config
+        "#;
+
+        sqlx::query(query)
+            .bind(plugin_id)
+            .bind(config)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 获取插件生命周期状态
+    pub async fn get_plugin_state(&self, plugin_id: &str) -> Result<Option<String>> {
+        let query = "SELECT state FROM plugin_metadata WHERE plugin_id = ?1";
+
+        let result = sqlx::query(query)
+            .bind(plugin_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(result.map(|row| row.get("state")))
+    }
+
+    /// 保存插件生命周期状态（插件尚未注册时创建元数据记录）
+    pub async fn set_plugin_state(&self, plugin_id: &str, state: &str) -> Result<()> {
+        let query = r#"
+            INSERT INTO plugin_metadata (plugin_id, name, version, state)
+            VALUES (?1, ?1, 'unknown', ?2)
+            ON CONFLICT(plugin_id) DO UPDATE SET
+                state = excluded.state,
+                last_active = CURRENT_TIMESTAMP
+        "#;
+
+        sqlx::query(query)
+            .bind(plugin_id)
+            .bind(state)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     /// 启用/禁用插件
     pub async fn set_plugin_enabled(&self, plugin_id: &str, enabled: bool) -> Result<()> {
         let query = "UPDATE plugin_metadata SET enabled = ?1 WHERE plugin_id = ?2";
@@ -281,10 +403,11 @@ impl Storage {
         to: &str,
         payload: Option<&[u8]>,
         message_type: Option<&str>,
+        topic: Option<&str>,
     ) -> Result<()> {
         let query = r#"
-            INSERT INTO message_log (message_id, from_plugin, to_plugin, payload, message_type)
-            VALUES (?1, ?2, ?3, ?4, ?5)
+            INSERT INTO message_log (message_id, from_plugin, to_plugin, payload, message_type, topic)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
         "#;
 
         sqlx::query(query)
@@ -293,22 +416,50 @@ impl Storage {
             .bind(to)
             .bind(payload)
             .bind(message_type)
+            .bind(topic)
             .execute(&self.pool)
             .await?;
 
         Ok(())
     }
 
+    /// 记录消息的签名与验证结果
+    pub async fn set_message_signature(
+        &self,
+        message_id: &str,
+        signature: &str,
+        verified_from: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE message_log SET signature = ?1, verified_from = ?2 WHERE message_id = ?3",
+        )
+        .bind(signature)
+        .bind(verified_from)
+        .bind(message_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// 更新消息状态
-    pub async fn update_message_status(&self, message_id: &str, status: &str) -> Result<()> {
+    pub async fn update_message_status(
+        &self,
+        message_id: &str,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
         let query = r#"
             UPDATE message_log 
-            SET status = ?1, delivered_at = CASE WHEN ?1 = 'delivered' THEN CURRENT_TIMESTAMP ELSE delivered_at END
-            WHERE message_id = ?2
+            SET status = ?1,
+                error = ?2,
+                delivered_at = CASE WHEN ?1 = 'delivered' THEN CURRENT_TIMESTAMP ELSE delivered_at END
+            WHERE message_id = ?3
         "#;
 
         sqlx::query(query)
             .bind(status)
+            .bind(error)
             .bind(message_id)
             .execute(&self.pool)
             .await?;
@@ -316,6 +467,85 @@ impl Storage {
         Ok(())
     }
 
+    /// 按插件、主题、状态与时间范围查询消息日志
+    pub async fn search_message_log(
+        &self,
+        query: &MessageLogQuery,
+    ) -> Result<Vec<MessageLogEntry>> {
+        let mut builder = sqlx::QueryBuilder::new("SELECT * FROM message_log WHERE 1 = 1");
+
+        if let Some(plugin_id) = &query.plugin_id {
+            builder
+                .push(" AND (from_plugin = ")
+                .push_bind(plugin_id)
+                .push(" OR to_plugin = ")
+                .push_bind(plugin_id)
+                .push(")");
+        }
+        if let Some(topic) = &query.topic {
+            builder.push(" AND topic = ").push_bind(topic);
+        }
+        if let Some(status) = &query.status {
+            builder.push(" AND status = ").push_bind(status);
+        }
+        if let Some(since) = query.since {
+            builder
+                .push(" AND created_at >= ")
+                .push_bind(since.format(SQLITE_TIMESTAMP_FORMAT).to_string());
+        }
+        if let Some(until) = query.until {
+            builder
+                .push(" AND created_at <= ")
+                .push_bind(until.format(SQLITE_TIMESTAMP_FORMAT).to_string());
+        }
+
+        let limit = if query.limit > 0 {
+            query.limit
+        } else {
+            DEFAULT_MESSAGE_LOG_LIMIT
+        };
+        builder
+            .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+            .push_bind(limit);
+
+        let entries = builder
+            .build_query_as::<MessageLogEntry>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// 按保留策略清理消息日志，返回删除的条数
+    ///
+    /// `max_age_secs` 为 0 时不按时间清理，`max_entries` 为 0 时不限制条数
+    pub async fn prune_message_log(&self, max_age_secs: u64, max_entries: u64) -> Result<u64> {
+        let mut deleted = 0;
+
+        if max_age_secs > 0 {
+            let cutoff = Utc::now() - chrono::Duration::seconds(max_age_secs as i64);
+            let result = sqlx::query("DELETE FROM message_log WHERE created_at < ?1")
+                .bind(cutoff.format(SQLITE_TIMESTAMP_FORMAT).to_string())
+                .execute(&self.pool)
+                .await?;
+            deleted += result.rows_affected();
+        }
+
+        if max_entries > 0 {
+            let query = r#"
+                DELETE FROM message_log
+                WHERE id <= (SELECT id FROM message_log ORDER BY id DESC LIMIT 1 OFFSET ?1)
+            "#;
+            let result = sqlx::query(query)
+                .bind(max_entries as i64)
+                .execute(&self.pool)
+                .await?;
+            deleted += result.rows_affected();
+        }
+
+        Ok(deleted)
+    }
+
     /// 获取消息历史
     pub async fn get_message_history(
         &self,
@@ -362,6 +592,285 @@ impl Storage {
         Ok(result)
     }
 
+    // 持久投递队列
+
+    /// 保存待投递的消息，同一消息与接收方已存在时返回 false
+    pub async fn enqueue_durable_message(
+        &self,
+        message_id: &str,
+        recipient: &str,
+        message: &str,
+        attempts: i64,
+        next_attempt_at: i64,
+    ) -> Result<bool> {
+        let query = r#"
+            INSERT INTO durable_messages (message_id, recipient, message, attempts, next_attempt_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(message_id, recipient) DO NOTHING
+        "#;
+
+        let result = sqlx::query(query)
+            .bind(message_id)
+            .bind(recipient)
+            .bind(message)
+            .bind(attempts)
+            .bind(next_attempt_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 获取待投递的消息
+    pub async fn get_durable_message(
+        &self,
+        message_id: &str,
+        recipient: &str,
+    ) -> Result<Option<DurableMessage>> {
+        let query = "SELECT * FROM durable_messages WHERE message_id = ?1 AND recipient = ?2";
+
+        let entry = sqlx::query_as::<_, DurableMessage>(query)
+            .bind(message_id)
+            .bind(recipient)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(entry)
+    }
+
+    /// 获取已到投递时间、接收方在列表中的消息（按投递时间排序）
+    pub async fn due_durable_messages(
+        &self,
+        recipients: &[String],
+        now: i64,
+        limit: i64,
+    ) -> Result<Vec<DurableMessage>> {
+        if recipients.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut builder =
+            sqlx::QueryBuilder::new("SELECT * FROM durable_messages WHERE next_attempt_at <= ");
+        builder.push_bind(now).push(" AND recipient IN (");
+        let mut separated = builder.separated(", ");
+        for recipient in recipients {
+            separated.push_bind(recipient);
+        }
+        builder
+            .push(") ORDER BY next_attempt_at LIMIT ")
+            .push_bind(limit);
+
+        let entries = builder
+            .build_query_as::<DurableMessage>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// 更新消息的投递次数与下次投递时间
+    pub async fn reschedule_durable_message(
+        &self,
+        message_id: &str,
+        recipient: &str,
+        attempts: i64,
+        next_attempt_at: i64,
+        last_error: Option<&str>,
+    ) -> Result<bool> {
+        let query = r#"
+            UPDATE durable_messages
+            SET attempts = ?1, next_attempt_at = ?2, last_error = COALESCE(?3, last_error)
+            WHERE message_id = ?4 AND recipient = ?5
+        "#;
+
+        let result = sqlx::query(query)
+            .bind(attempts)
+            .bind(next_attempt_at)
+            .bind(last_error)
+            .bind(message_id)
+            .bind(recipient)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 确认消息（删除），消息不存在时返回 false
+    pub async fn ack_durable_message(&self, message_id: &str, recipient: &str) -> Result<bool> {
+        let query = "DELETE FROM durable_messages WHERE message_id = ?1 AND recipient = ?2";
+
+        let result = sqlx::query(query)
+            .bind(message_id)
+            .bind(recipient)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 将消息移入死信表
+    pub async fn dead_letter_message(
+        &self,
+        message_id: &str,
+        recipient: &str,
+        last_error: Option<&str>,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let insert = r#"
+            INSERT INTO dead_letters (message_id, recipient, message, attempts, last_error)
+            SELECT message_id, recipient, message, attempts, COALESCE(?1, last_error)
+            FROM durable_messages WHERE message_id = ?2 AND recipient = ?3
+        "#;
+        let moved = sqlx::query(insert)
+            .bind(last_error)
+            .bind(message_id)
+            .bind(recipient)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        sqlx::query("DELETE FROM durable_messages WHERE message_id = ?1 AND recipient = ?2")
+            .bind(message_id)
+            .bind(recipient)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(moved > 0)
+    }
+
+    /// 获取死信（按进入死信表的时间倒序）
+    pub async fn list_dead_letters(
+        &self,
+        recipient: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<DeadLetter>> {
+        let mut builder = sqlx::QueryBuilder::new("SELECT * FROM dead_letters");
+        if let Some(recipient) = recipient {
+            builder.push(" WHERE recipient = ").push_bind(recipient);
+        }
+        builder.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+        let entries = builder
+            .build_query_as::<DeadLetter>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// 将死信重新放回持久投递队列（投递次数清零，立即投递），死信不存在时返回 false
+    pub async fn replay_dead_letter(&self, id: i64, next_attempt_at: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let insert = r#"
+            INSERT INTO durable_messages (message_id, recipient, message, attempts, next_attempt_at)
+            SELECT message_id, recipient, message, 0, ?1 FROM dead_letters WHERE id = ?2
+            ON CONFLICT(message_id, recipient) DO UPDATE
+            SET attempts = 0, next_attempt_at = excluded.next_attempt_at
+        "#;
+        sqlx::query(insert)
+            .bind(next_attempt_at)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let removed = sqlx::query("DELETE FROM dead_letters WHERE id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(removed > 0)
+    }
+
+    // 保留消息
+
+    /// 保存主题的保留消息，只保留最近 `depth` 条
+    pub async fn save_retained_message(
+        &self,
+        topic: &str,
+        message_id: &str,
+        message: &str,
+        depth: usize,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO retained_messages (topic, message_id, message) VALUES (?1, ?2, ?3)",
+        )
+        .bind(topic)
+        .bind(message_id)
+        .bind(message)
+        .execute(&mut *tx)
+        .await?;
+
+        let trim = r#"
+            DELETE FROM retained_messages
+            WHERE topic = ?1 AND id NOT IN (
+                SELECT id FROM retained_messages WHERE topic = ?1 ORDER BY id DESC LIMIT ?2
+            )
+        "#;
+        sqlx::query(trim)
+            .bind(topic)
+            .bind(depth as i64)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 加载所有保留消息（按主题分组，每组内按发布顺序）
+    pub async fn load_retained_messages(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query("SELECT topic, message FROM retained_messages ORDER BY topic, id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("topic"), row.get("message")))
+            .collect())
+    }
+
+    /// 删除主题的保留消息
+    pub async fn clear_retained_messages(&self, topic: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM retained_messages WHERE topic = ?1")
+            .bind(topic)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // 消息总线统计
+
+    /// 保存消息总线统计快照（覆盖上一次的快照）
+    pub async fn save_bus_stats(&self, stats: &str) -> Result<()> {
+        let query = r#"
+            INSERT INTO bus_stats (id, stats, updated_at)
+            VALUES (1, ?1, CURRENT_TIMESTAMP)
+            ON CONFLICT(id) DO UPDATE SET
+                stats = excluded.stats,
+                updated_at = excluded.updated_at
+        "#;
+
+        sqlx::query(query).bind(stats).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// 读取最近一次保存的消息总线统计快照
+    pub async fn load_bus_stats(&self) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT stats FROM bus_stats WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("stats")))
+    }
+
     // 订阅管理
 
     /// 添加订阅
@@ -415,6 +924,27 @@ impl Storage {
         Ok(rows.into_iter().map(|row| row.get("topic")).collect())
     }
 
+    /// 移除插件的所有订阅
+    pub async fn remove_plugin_subscriptions(&self, plugin_id: &str) -> Result<u64> {
+        let query = "DELETE FROM plugin_subscriptions WHERE plugin_id = ?1";
+
+        let result = sqlx::query(query)
+            .bind(plugin_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 获取所有有订阅记录的插件
+    pub async fn list_subscribed_plugins(&self) -> Result<Vec<String>> {
+        let query = "SELECT DISTINCT plugin_id FROM plugin_subscriptions ORDER BY plugin_id";
+
+        let rows = sqlx::query(query).fetch_all(&self.pool).await?;
+
+        Ok(rows.into_iter().map(|row| row.get("plugin_id")).collect())
+    }
+
     /// 获取数据库连接池（用于高级操作）
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
@@ -447,6 +977,317 @@ mod tests {
         assert_eq!(retrieved, Some(value));
     }
 
+    #[tokio::test]
+    async fn test_plugin_config() {
+        let storage = setup_test_db().await;
+
+        assert!(storage.get_plugin_config("sensor").await.unwrap().is_none());
+
+        let config = serde_json::json!({"interval_ms": 500});
+        storage.set_plugin_config("sensor", &config).await.unwrap();
+        assert_eq!(
+            storage.get_plugin_config("sensor").await.unwrap(),
+            Some(config)
+        );
+
+        // 覆盖已有配置
+        let config = serde_json::json!({"interval_ms": 250});
+        storage.set_plugin_config("sensor", &config).await.unwrap();
+        assert_eq!(
+            storage.get_plugin_config("sensor").await.unwrap(),
+            Some(config)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plugin_state() {
+        let storage = setup_test_db().await;
+
+        assert!(storage.get_plugin_state("sensor").await.unwrap().is_none());
+
+        storage.set_plugin_state("sensor", "running").await.unwrap();
+        storage
+            .set_plugin_config("sensor", &serde_json::json!({}))
+            .await
+            .unwrap();
+        storage.set_plugin_state("sensor", "paused").await.unwrap();
+
+        let metadata = storage
+            .get_plugin_metadata("sensor")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.state, "paused");
+        assert_eq!(metadata.config, Some(serde_json::json!({})));
+    }
+
+    #[tokio::test]
+    async fn test_message_log_search_and_prune() {
+        let storage = setup_test_db().await;
+
+        let messages = [
+            ("m1", "sensor", "", Some("health.cpu")),
+            ("m2", "sensor", "", Some("health.mem")),
+            ("m3", "app", "sensor", None),
+        ];
+        for (id, from, to, topic) in messages {
+            storage
+                .log_message(id, from, to, Some(b"{}"), None, topic)
+                .await
+                .unwrap();
+        }
+        storage
+            .update_message_status("m1", "delivered", None)
+            .await
+            .unwrap();
+        storage
+            .update_message_status("m2", "failed", Some("通道已关闭"))
+            .await
+            .unwrap();
+        storage
+            .set_message_signature("m2", "ab12", true)
+            .await
+            .unwrap();
+
+        let by_topic = storage
+            .search_message_log(&MessageLogQuery {
+                topic: Some("health.mem".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(by_topic.len(), 1);
+        assert_eq!(by_topic[0].status, "failed");
+        assert_eq!(by_topic[0].error.as_deref(), Some("通道已关闭"));
+        assert_eq!(by_topic[0].signature.as_deref(), Some("ab12"));
+        assert!(by_topic[0].verified_from);
+
+        let by_plugin = storage
+            .search_message_log(&MessageLogQuery {
+                plugin_id: Some("sensor".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(by_plugin.len(), 3);
+
+        let delivered = storage
+            .search_message_log(&MessageLogQuery {
+                status: Some("delivered".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert!(delivered[0].delivered_at.is_some());
+
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        let recent = MessageLogQuery {
+            since: Some(hour_ago),
+            ..Default::default()
+        };
+        assert_eq!(storage.search_message_log(&recent).await.unwrap().len(), 3);
+        let old = MessageLogQuery {
+            until: Some(hour_ago),
+            ..Default::default()
+        };
+        assert!(storage.search_message_log(&old).await.unwrap().is_empty());
+
+        // 超过保留时长的记录被删除，其余按条数上限保留最新的
+        sqlx::query(
+            "UPDATE message_log SET created_at = '2000-01-01 00:00:00' WHERE message_id = 'm1'",
+        )
+        .execute(storage.pool())
+        .await
+        .unwrap();
+        assert_eq!(storage.prune_message_log(3600, 0).await.unwrap(), 1);
+        assert_eq!(storage.prune_message_log(0, 1).await.unwrap(), 1);
+        let remaining = storage
+            .search_message_log(&MessageLogQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].message_id, "m3");
+    }
+
+    #[tokio::test]
+    async fn test_retained_messages_keep_latest() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+
+        for i in 0..3 {
+            storage
+                .save_retained_message(
+                    "system.stats",
+                    &format!("m{i}"),
+                    &format!("{{\"n\":{i}}}"),
+                    2,
+                )
+                .await
+                .unwrap();
+        }
+        storage
+            .save_retained_message("health.cpu", "h0", "{}", 1)
+            .await
+            .unwrap();
+
+        let retained = storage.load_retained_messages().await.unwrap();
+        assert_eq!(
+            retained,
+            vec![
+                ("health.cpu".to_string(), "{}".to_string()),
+                ("system.stats".to_string(), "{\"n\":1}".to_string()),
+                ("system.stats".to_string(), "{\"n\":2}".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            storage
+                .clear_retained_messages("system.stats")
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(storage.load_retained_messages().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bus_stats_snapshot_is_replaced() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        assert!(storage.load_bus_stats().await.unwrap().is_none());
+
+        storage.save_bus_stats(r#"{"n":1}"#).await.unwrap();
+        storage.save_bus_stats(r#"{"n":2}"#).await.unwrap();
+        assert_eq!(
+            storage.load_bus_stats().await.unwrap().as_deref(),
+            Some(r#"{"n":2}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_durable_queue_and_dead_letters() {
+        let storage = setup_test_db().await;
+
+        assert!(storage
+            .enqueue_durable_message("m1", "sensor", "{}", 0, 100)
+            .await
+            .unwrap());
+        assert!(!storage
+            .enqueue_durable_message("m1", "sensor", "{}", 0, 100)
+            .await
+            .unwrap());
+        storage
+            .enqueue_durable_message("m1", "logger", "{}", 0, 500)
+            .await
+            .unwrap();
+
+        // 只返回已到期且接收方在列表中的消息
+        let recipients = vec!["sensor".to_string(), "logger".to_string()];
+        let due = storage
+            .due_durable_messages(&recipients, 200, 10)
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].recipient, "sensor");
+        let only_logger = vec!["logger".to_string()];
+        assert!(storage
+            .due_durable_messages(&only_logger, 200, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        storage
+            .reschedule_durable_message("m1", "sensor", 3, 1000, Some("timeout"))
+            .await
+            .unwrap();
+        let entry = storage
+            .get_durable_message("m1", "sensor")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.attempts, 3);
+        assert_eq!(entry.next_attempt_at, 1000);
+        assert_eq!(entry.last_error.as_deref(), Some("timeout"));
+
+        // 确认后删除
+        assert!(storage.ack_durable_message("m1", "logger").await.unwrap());
+        assert!(!storage.ack_durable_message("m1", "logger").await.unwrap());
+
+        // 移入死信表后可以重放
+        assert!(storage
+            .dead_letter_message("m1", "sensor", None)
+            .await
+            .unwrap());
+        assert!(storage
+            .get_durable_message("m1", "sensor")
+            .await
+            .unwrap()
+            .is_none());
+        let dead = storage.list_dead_letters(Some("sensor"), 10).await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 3);
+        assert_eq!(dead[0].last_error.as_deref(), Some("timeout"));
+
+        assert!(storage.replay_dead_letter(dead[0].id, 0).await.unwrap());
+        assert!(!storage.replay_dead_letter(dead[0].id, 0).await.unwrap());
+        assert!(storage
+            .list_dead_letters(None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let entry = storage
+            .get_durable_message("m1", "sensor")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions() {
+        let storage = setup_test_db().await;
+        for plugin_id in ["monitor", "ghost"] {
+            storage.set_plugin_state(plugin_id, "loaded").await.unwrap();
+        }
+
+        storage
+            .add_subscription("monitor", "health.#")
+            .await
+            .unwrap();
+        storage
+            .add_subscription("monitor", "health.#")
+            .await
+            .unwrap();
+        storage
+            .add_subscription("ghost", "system.+.stats")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_plugin_subscriptions("monitor").await.unwrap(),
+            vec!["health.#"]
+        );
+        assert_eq!(
+            storage.list_subscribed_plugins().await.unwrap(),
+            vec!["ghost", "monitor"]
+        );
+
+        assert_eq!(
+            storage.remove_plugin_subscriptions("ghost").await.unwrap(),
+            1
+        );
+        assert_eq!(
+            storage.list_subscribed_plugins().await.unwrap(),
+            vec!["monitor"]
+        );
+        assert!(storage
+            .remove_subscription("monitor", "health.#")
+            .await
+            .unwrap());
+        assert!(!storage
+            .remove_subscription("monitor", "health.#")
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     async fn test_update_data() {
         let storage = setup_test_db().await;
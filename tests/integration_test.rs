@@ -4,7 +4,8 @@
 
 use anyhow::Result;
 use minimal_kernel::config::{
-    Config, DatabaseConfig, IdentityConfig, LogLevel, LoggingConfig, NetworkConfig, PluginConfig,
+    Config, DatabaseConfig, IdentityConfig, LogLevel, LoggingConfig, MessageBusConfig,
+    NetworkConfig, PluginConfig,
 };
 use minimal_kernel::kernel::Kernel;
 use std::path::PathBuf;
@@ -29,6 +30,7 @@ fn create_test_config(temp_dir: &TempDir) -> Config {
             timeout_ms: 5000,
             max_memory_mb: 128,
             enabled: vec![],
+            ..PluginConfig::default()
         },
         logging: LoggingConfig {
             level: LogLevel::Debug,
@@ -48,6 +50,7 @@ fn create_test_config(temp_dir: &TempDir) -> Config {
             private_key_file: None,
             allow_env_key: true,
         },
+        message_bus: MessageBusConfig::default(),
     }
 }
 
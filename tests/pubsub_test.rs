@@ -2,7 +2,9 @@
 //!
 //! 测试主题订阅、发布和取消订阅功能
 
-use minimal_kernel::kernel::{message::Message, message_bus::create_message_bus};
+use minimal_kernel::kernel::{
+    message::Message, message_bus::create_message_bus, plugin_queue::PluginReceiver,
+};
 use tokio::time::{sleep, timeout, Duration};
 
 #[tokio::test]
@@ -227,3 +229,96 @@ async fn test_get_topic_subscribers() {
     assert!(subscribers_after.contains(&"plugin3".to_string()));
     assert!(!subscribers_after.contains(&"plugin2".to_string()));
 }
+
+#[tokio::test]
+async fn test_wildcard_topic_subscription() {
+    let (handle, router) = create_message_bus(100);
+    let mut single_rx = handle.register_plugin("single".to_string());
+    let mut multi_rx = handle.register_plugin("multi".to_string());
+    let mut stats_rx = handle.register_plugin("stats".to_string());
+
+    assert!(handle.subscribe_topic("single", "health.*"));
+    assert!(handle.subscribe_topic("multi", "health.#"));
+    assert!(handle.subscribe_topic("stats", "system.+.stats"));
+
+    // 通配符必须独占一层，# 只能在末尾
+    assert!(!handle.subscribe_topic("single", "health.#.cpu"));
+    assert!(!handle.subscribe_topic("single", "health.cpu*"));
+
+    let sender = handle.get_sender();
+    let router_handle = tokio::spawn(async move {
+        router.run().await;
+    });
+
+    for topic in ["health.cpu", "health.cpu.load", "system.node1.stats"] {
+        let message = Message::new_topic("publisher".to_string(), topic.to_string(), vec![]);
+        sender.send(message).await.unwrap();
+    }
+    sleep(Duration::from_millis(100)).await;
+
+    let topics = |rx: &mut PluginReceiver| {
+        let mut topics = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            topics.push(message.topic.unwrap());
+        }
+        topics
+    };
+
+    assert_eq!(topics(&mut single_rx), vec!["health.cpu"]);
+    assert_eq!(topics(&mut multi_rx), vec!["health.cpu", "health.cpu.load"]);
+    assert_eq!(topics(&mut stats_rx), vec!["system.node1.stats"]);
+
+    router_handle.abort();
+}
+
+#[tokio::test]
+async fn test_overlapping_patterns_deliver_once() {
+    let (handle, router) = create_message_bus(100);
+    let mut plugin_rx = handle.register_plugin("plugin1".to_string());
+    let mut bridge_rx = handle.register_plugin("bridge".to_string());
+
+    // 同一插件的多个模式都命中同一主题
+    assert!(handle.subscribe_topic("plugin1", "health.cpu"));
+    assert!(handle.subscribe_topic("plugin1", "health.*"));
+    assert!(handle.subscribe_topic("plugin1", "health.#"));
+    assert!(handle.subscribe_topic("bridge", "#"));
+
+    // * 与 + 是同一个模式
+    assert!(!handle.subscribe_topic("plugin1", "health.+"));
+
+    let mut matched = handle.match_topic_subscribers("health.cpu");
+    matched.sort();
+    assert_eq!(matched, vec!["bridge", "plugin1"]);
+
+    let sender = handle.get_sender();
+    let router_handle = tokio::spawn(async move {
+        router.run().await;
+    });
+
+    let message = Message::new_topic(
+        "publisher".to_string(),
+        "health.cpu".to_string(),
+        b"once".to_vec(),
+    );
+    sender.send(message).await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let msg = plugin_rx.try_recv().expect("插件应该收到消息");
+    assert_eq!(msg.payload, b"once");
+    assert!(plugin_rx.try_recv().is_err(), "插件不应重复收到消息");
+
+    assert!(bridge_rx.try_recv().is_ok(), "# 订阅者应该收到消息");
+    assert!(bridge_rx.try_recv().is_err());
+
+    // 取消其中一个模式后仍通过其余模式收到
+    assert!(handle.unsubscribe_topic("plugin1", "health.#"));
+    let mut matched = handle.match_topic_subscribers("health.cpu");
+    matched.sort();
+    assert_eq!(matched, vec!["bridge", "plugin1"]);
+
+    // 注销后移除全部模式
+    handle.unregister_plugin("plugin1");
+    assert_eq!(handle.match_topic_subscribers("health.cpu"), vec!["bridge"]);
+
+    router_handle.abort();
+}